@@ -5,6 +5,8 @@ fn main() {
         &["grammars", "tree-sitter-rust", "src"][..],
         &["grammars", "tree-sitter-python", "src"][..],
         &["grammars", "tree-sitter-javascript", "src"][..],
+        &["grammars", "tree-sitter-typescript", "typescript", "src"][..],
+        &["grammars", "tree-sitter-typescript", "tsx", "src"][..],
     ]
     .iter()
     .map(|path| path.iter().collect::<PathBuf>())
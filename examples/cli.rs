@@ -1,4 +1,6 @@
-use asterisk::config::Config;
+use serde::Serialize;
+use serde_json::json;
+use stackwalk::config::Config;
 /// Example CLI application demonstrating usage of the asterisk library.
 ///
 /// This example:
@@ -11,31 +13,34 @@ use asterisk::config::Config;
 /// ```
 /// cargo run --example cli -- /path/to/directory/to/index
 /// ```
-use asterisk::indexer::index_directory;
-use serde::Serialize;
-use serde_json::json;
+use stackwalk::indexer::index_directory;
 use std::env;
-use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::collections::HashSet;
 
 #[derive(Serialize)]
 struct Output {
-    blocks: Vec<asterisk::block::Block>,
-    call_stack: asterisk::call_stack::CallStack,
+    blocks: Vec<stackwalk::block::Block>,
+    call_stack: stackwalk::call_stack::CallStack,
 }
 
 fn main() {
-    let toml_str = fs::read_to_string("asterisk.toml").expect("Unable to read file");
-    let config = Config::from_toml(&toml_str).unwrap();
+    let config = Config::from_file("asterisk.toml").expect("Unable to load asterisk.toml");
 
     let args: Vec<String> = env::args().collect();
     let dir_path = &args[1];
-    let (blocks, call_stack, call_graph) = index_directory(&config, dir_path);
+    let index = index_directory(&config, dir_path).expect("Failed to index directory");
 
-    // convert blocks and call_stack to hashset then to vec again
-    let blocks = blocks.into_iter().collect::<HashSet<_>>().into_iter().collect();
+    for diagnostic in &index.diagnostics {
+        eprintln!("Skipped {}", diagnostic);
+    }
+
+    let (blocks, call_stack, call_graph, type_graph) = (
+        index.blocks,
+        index.call_stack,
+        index.call_graph,
+        index.type_graph,
+    );
 
     let output = Output { blocks, call_stack };
 
@@ -46,7 +51,7 @@ fn main() {
 
     let pretty_json = serde_json::to_string_pretty(&json_output).unwrap();
 
-    let project_name = dir_path.split('/').last().unwrap_or("blockoli");
+    let project_name = dir_path.split('/').next_back().unwrap_or("blockoli");
     let output_file_name = format!("{}.json", project_name);
 
     let mut output_file = File::create(&output_file_name).expect("Failed to create output file");
@@ -66,14 +71,13 @@ fn main() {
 
     let mermaid = call_graph.to_mermaid();
     let mermaid_file_name = format!("{}_call_graph.mermaid", project_name);
-    let mut mermaid_file =
-        File::create(&mermaid_file_name).expect("Failed to create Mermaid file");
+    let mut mermaid_file = File::create(&mermaid_file_name).expect("Failed to create Mermaid file");
     write!(mermaid_file, "{}", mermaid).expect("Failed to write to Mermaid file");
 
     println!(
         "Call graph generated. Mermaid file written to {}",
         mermaid_file_name
-    );    
+    );
 
     let json_flow_chart = call_graph.to_json_flowchart();
     let flow_chart_file_name = format!("{}_call_graph.json", project_name);
@@ -81,6 +85,34 @@ fn main() {
         File::create(&flow_chart_file_name).expect("Failed to create Flow Chart file");
     write!(flow_chart_file, "{}", json_flow_chart).expect("Failed to write to Flow Chart file");
 
+    let type_graphviz = type_graph.to_graphviz();
+    let type_graphviz_file_name = format!("{}_type_graph.dot", project_name);
+    let mut type_graphviz_file =
+        File::create(&type_graphviz_file_name).expect("Failed to create Graphviz file");
+    write!(type_graphviz_file, "{}", type_graphviz).expect("Failed to write to Graphviz file");
+
+    let type_mermaid = type_graph.to_mermaid();
+    let type_mermaid_file_name = format!("{}_type_graph.mermaid", project_name);
+    let mut type_mermaid_file =
+        File::create(&type_mermaid_file_name).expect("Failed to create Mermaid file");
+    write!(type_mermaid_file, "{}", type_mermaid).expect("Failed to write to Mermaid file");
+
+    let type_flow_chart = type_graph.to_json_flowchart();
+    let type_flow_chart_file_name = format!("{}_type_graph.json", project_name);
+    let mut type_flow_chart_file =
+        File::create(&type_flow_chart_file_name).expect("Failed to create Flow Chart file");
+    write!(type_flow_chart_file, "{}", type_flow_chart)
+        .expect("Failed to write to Flow Chart file");
+
+    println!(
+        "Type graph generated. Files written to {}, {} and {}",
+        type_graphviz_file_name, type_mermaid_file_name, type_flow_chart_file_name
+    );
+
+    for (edge, reason) in call_graph.unresolved_calls() {
+        println!("Unresolved Call: {} -> {} ({})", edge.from, edge.to, reason);
+    }
+
     let entry_points = call_graph.get_entry_points();
     if !entry_points.is_empty() {
         for entry_point in entry_points {
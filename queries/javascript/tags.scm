@@ -0,0 +1,349 @@
+; Built-in stackwalk queries for JavaScript.
+;
+; See queries/rust/tags.scm for the captures stackwalk understands.
+
+; Functions and generators.
+
+(function_declaration
+  name: (identifier) @name) @definition.function
+
+(generator_function_declaration
+  name: (identifier) @name) @definition.function
+
+; Methods of classes and object literals.
+
+(method_definition
+  name: (_) @name) @definition.method
+
+; Classes. A class expression is named after what it is assigned to.
+
+(class_declaration
+  name: (identifier) @name) @definition.class
+
+(variable_declarator
+  name: (identifier) @name
+  value: (class) @definition.class)
+
+(assignment_expression
+  left: [
+    (identifier) @name
+    (member_expression property: (property_identifier) @name)
+  ]
+  right: (class) @definition.class)
+
+(export_statement
+  "default" @name
+  value: (class) @definition.class)
+
+; The class a class extends.
+
+(class_declaration
+  (class_heritage (_) @base)) @definition.class
+
+(class
+  (class_heritage (_) @base)) @definition.class
+
+; An object literal assigned to a variable groups its methods like a class does.
+
+(variable_declarator
+  name: (identifier) @name
+  value: (object) @definition.namespace)
+
+; Anonymous functions are named after what they are bound to: a variable, a property,
+; a class field or the default export.
+
+(variable_declarator
+  name: (identifier) @name
+  value: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+  ] @definition.function)
+
+(pair
+  key: (_) @name
+  value: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+  ] @definition.function)
+
+(field_definition
+  property: (_) @name
+  value: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+  ] @definition.function)
+
+(assignment_expression
+  left: [
+    (identifier) @name
+    (member_expression property: (property_identifier) @name)
+  ]
+  right: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+  ] @definition.function)
+
+(export_statement
+  "default" @name
+  value: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+  ] @definition.function)
+
+; Any other anonymous function, such as a callback.
+
+[
+  (arrow_function)
+  (function_expression)
+  (generator_function)
+] @definition.closure
+
+; Calls whose kind their context reveals come before the plain calls, which would
+; capture them otherwise.
+
+((await_expression
+  (call_expression
+    function: (_) @name) @reference.call)
+  (#not-eq? @name "import")
+  (#set! reference.kind "await"))
+
+((new_expression
+  constructor: (_) @name) @reference.call
+  (#set! reference.kind "constructor"))
+
+; Dynamic `import()` is not a call to a function.
+((call_expression
+  function: (_) @name) @reference.call
+  (#not-eq? @name "import"))
+
+; Functions passed on as values, to be called later: as arguments, assigned to
+; properties or returned. Only those naming a function become callback edges. A
+; function defined in place is a callback of the function creating it wherever it is.
+
+(arguments
+  [(identifier) (member_expression)] @name @reference.callback)
+
+(assignment_expression
+  left: (member_expression)
+  right: [(identifier) (member_expression)] @name @reference.callback)
+
+(return_statement
+  [(identifier) (member_expression)] @name @reference.callback)
+
+; Event handlers of JSX elements, such as `onClick={handle}`.
+(jsx_attribute
+  (jsx_expression
+    [(identifier) (member_expression)] @name @reference.callback))
+
+; Parameters and local variables, which shadow the functions of the same name in their
+; function, so that passing one on is no callback.
+
+(formal_parameters
+  (identifier) @variable)
+
+(formal_parameters
+  (assignment_pattern
+    left: (identifier) @variable))
+
+(arrow_function
+  parameter: (identifier) @variable)
+
+(variable_declarator
+  name: (identifier) @variable)
+
+; ES module imports. A default import imports the export named `default`.
+
+((import_statement
+  (import_clause
+    (identifier) @import.alias)
+  source: (string) @import.module) @import
+  (#set! import.name "default"))
+
+(import_statement
+  (import_clause
+    (namespace_import (identifier) @import.alias))
+  source: (string) @import.module) @import
+
+(import_statement
+  (import_clause
+    (named_imports
+      (import_specifier
+        name: (_) @import.name
+        alias: (identifier)? @import.alias)))
+  source: (string) @import.module) @import
+
+; CommonJS `require` and dynamic `import()`, bound to variables.
+
+((variable_declarator
+  name: (identifier) @import.alias
+  value: (call_expression
+    function: (identifier) @_require
+    arguments: (arguments . (string) @import.module))) @import
+  (#eq? @_require "require"))
+
+((variable_declarator
+  name: (identifier) @import.alias
+  value: (member_expression
+    object: (call_expression
+      function: (identifier) @_require
+      arguments: (arguments . (string) @import.module))
+    property: (property_identifier) @import.name)) @import
+  (#eq? @_require "require"))
+
+((variable_declarator
+  name: (object_pattern
+    (shorthand_property_identifier_pattern) @import.name)
+  value: (call_expression
+    function: (identifier) @_require
+    arguments: (arguments . (string) @import.module))) @import
+  (#eq? @_require "require"))
+
+((variable_declarator
+  name: (object_pattern
+    (pair_pattern
+      key: (property_identifier) @import.name
+      value: (identifier) @import.alias))
+  value: (call_expression
+    function: (identifier) @_require
+    arguments: (arguments . (string) @import.module))) @import
+  (#eq? @_require "require"))
+
+(variable_declarator
+  name: (identifier) @import.alias
+  value: (await_expression
+    (call_expression
+      function: (import)
+      arguments: (arguments . (string) @import.module)))) @import
+
+(variable_declarator
+  name: (object_pattern
+    (shorthand_property_identifier_pattern) @import.name)
+  value: (await_expression
+    (call_expression
+      function: (import)
+      arguments: (arguments . (string) @import.module)))) @import
+
+(variable_declarator
+  name: (object_pattern
+    (pair_pattern
+      key: (property_identifier) @import.name
+      value: (identifier) @import.alias))
+  value: (await_expression
+    (call_expression
+      function: (import)
+      arguments: (arguments . (string) @import.module)))) @import
+
+; Re-exports.
+
+(export_statement
+  (export_clause
+    (export_specifier
+      name: (_) @import.name
+      alias: (_)? @import.alias))
+  source: (string) @import.module) @import @import.reexport
+
+((export_statement
+  "*"
+  source: (string) @import.module) @import @import.reexport
+  (#set! import.name "*"))
+
+(export_statement
+  (namespace_export (_) @import.alias)
+  source: (string) @import.module) @import @import.reexport
+
+; Definitions exported under another name, including the `default` export and
+; CommonJS `module.exports`.
+
+(export_statement
+  (export_clause
+    (export_specifier
+      name: (identifier) @export.name
+      alias: (_) @export.alias))
+  !source) @export
+
+((export_statement
+  "default"
+  declaration: [
+    (function_declaration name: (identifier) @export.name)
+    (generator_function_declaration name: (identifier) @export.name)
+    (class_declaration name: (identifier) @export.name)
+  ]) @export
+  (#set! export.alias "default"))
+
+((export_statement
+  "default"
+  value: (identifier) @export.name) @export
+  (#set! export.alias "default"))
+
+((assignment_expression
+  left: (member_expression
+    object: (identifier) @_module
+    property: (property_identifier) @_exports)
+  right: (identifier) @export.name) @export
+  (#eq? @_module "module")
+  (#eq? @_exports "exports")
+  (#set! export.alias "default"))
+
+; A function assigned to `module.exports` is named `exports`.
+
+((assignment_expression
+  left: (member_expression
+    object: (identifier) @_module
+    property: (property_identifier) @export.name)
+  right: [
+    (arrow_function)
+    (function_expression)
+    (generator_function)
+    (class)
+  ]) @export
+  (#eq? @_module "module")
+  (#eq? @export.name "exports")
+  (#set! export.alias "default"))
+
+((assignment_expression
+  left: (member_expression
+    object: (identifier) @_module
+    property: (property_identifier) @_exports)
+  right: (object
+    (pair
+      key: (property_identifier) @export.alias
+      value: (identifier) @export.name))) @export
+  (#eq? @_module "module")
+  (#eq? @_exports "exports"))
+
+((assignment_expression
+  left: (member_expression
+    object: (member_expression
+      object: (identifier) @_module
+      property: (property_identifier) @_exports)
+    property: (property_identifier) @export.alias)
+  right: (identifier) @export.name) @export
+  (#eq? @_module "module")
+  (#eq? @_exports "exports"))
+
+((assignment_expression
+  left: (member_expression
+    object: (identifier) @_exports
+    property: (property_identifier) @export.alias)
+  right: (identifier) @export.name) @export
+  (#eq? @_exports "exports"))
+
+; Variables of known type, used to resolve method calls on them: instances created
+; with `new`, which are known to be of exactly their class.
+
+((variable_declarator
+  name: (identifier) @binding.name
+  value: (new_expression
+    constructor: [(identifier) (member_expression)] @binding.type)) @binding
+  (#set! binding.exact "true"))
+
+((assignment_expression
+  left: (identifier) @binding.name
+  right: (new_expression
+    constructor: [(identifier) (member_expression)] @binding.type)) @binding
+  (#set! binding.exact "true"))
@@ -0,0 +1,148 @@
+; Built-in stackwalk queries for Python.
+;
+; See queries/rust/tags.scm for the captures stackwalk understands.
+
+(function_definition
+  name: (identifier) @name) @definition.function
+
+; A lambda bound to a variable is called through it.
+(assignment
+  left: (identifier) @name
+  right: (lambda) @definition.closure)
+
+(lambda) @definition.closure
+
+(class_definition
+  name: (identifier) @name) @definition.class
+
+; Base classes, in the order the method resolution order considers them.
+(class_definition
+  superclasses: (argument_list [(identifier) (attribute)] @base)) @definition.class
+
+; Calls whose kind their context reveals come before the plain calls, which would
+; capture them otherwise.
+
+; The target of a thread or process runs on its own.
+((call
+  function: (_) @_spawner
+  arguments: (argument_list
+    (keyword_argument
+      name: (identifier) @_target
+      value: [(identifier) (attribute) (lambda)] @name @reference.call)))
+  (#match? @_spawner "(^|\\.)(Thread|Process)$")
+  (#eq? @_target "target")
+  (#set! reference.kind "spawn"))
+
+; A coroutine handed to the event loop runs as a task of its own.
+((call
+  function: (_) @_spawner
+  arguments: (argument_list
+    .
+    (call
+      function: (_) @name) @reference.call))
+  (#match? @_spawner "(^|\\.)(create_task|ensure_future|run_coroutine_threadsafe)$")
+  (#set! reference.kind "spawn"))
+
+((await
+  (call
+    function: (_) @name) @reference.call)
+  (#set! reference.kind "await"))
+
+(call
+  function: (_) @name) @reference.call
+
+; Functions passed on as values, to be called later: as arguments, assigned to
+; attributes or returned. Only those naming a function become callback edges.
+
+(argument_list
+  [(identifier) (attribute)] @name @reference.callback)
+
+(keyword_argument
+  value: [(identifier) (attribute)] @name @reference.callback)
+
+(assignment
+  left: (attribute)
+  right: [(identifier) (attribute)] @name @reference.callback)
+
+(return_statement
+  [(identifier) (attribute)] @name @reference.callback)
+
+; Parameters and local variables, which shadow the functions of the same name in their
+; function, so that passing one on is no callback.
+
+(parameters
+  (identifier) @variable)
+
+(default_parameter
+  name: (identifier) @variable)
+
+(typed_parameter
+  (identifier) @variable)
+
+(typed_default_parameter
+  name: (identifier) @variable)
+
+(lambda_parameters
+  (identifier) @variable)
+
+(assignment
+  left: (identifier) @variable)
+
+(for_statement
+  left: (identifier) @variable)
+
+(import_from_statement
+  module_name: (_) @import.module
+  name: (dotted_name) @import.name) @import
+
+(import_from_statement
+  module_name: (_) @import.module
+  name: (aliased_import
+    name: (dotted_name) @import.name
+    alias: (identifier) @import.alias)) @import
+
+(import_from_statement
+  module_name: (_) @import.module
+  (wildcard_import) @import.name) @import
+
+(import_statement
+  name: (dotted_name) @import.module) @import
+
+(import_statement
+  name: (aliased_import
+    name: (dotted_name) @import.module
+    alias: (identifier) @import.alias)) @import
+
+; Imports in an `except ImportError:` handler replace the ones that failed.
+
+(try_statement
+  (except_clause
+    (_) @_exception
+    (block
+      [
+        (import_statement)
+        (import_from_statement)
+      ] @import.fallback))
+  (#match? @_exception "ImportError|ModuleNotFoundError"))
+
+; Variables of known type, used to resolve method calls on them: annotated variables
+; and parameters, and instances of classes, which are capitalized by convention. An
+; instance is known to be of exactly its class.
+
+(assignment
+  left: (identifier) @binding.name
+  type: (type [(identifier) (attribute)] @binding.type)) @binding
+
+((assignment
+  left: (identifier) @binding.name
+  right: (call function: [(identifier) (attribute)] @binding.type)) @binding
+  (#match? @binding.type "(^|\\.)[A-Z][A-Za-z0-9_]*$")
+  (#set! binding.exact "true"))
+
+(typed_parameter
+  (identifier) @binding.name
+  type: (type [(identifier) (attribute)] @binding.type)) @binding
+
+(typed_default_parameter
+  name: (identifier) @binding.name
+  type: (type [(identifier) (attribute)] @binding.type)) @binding
@@ -0,0 +1,282 @@
+; Built-in stackwalk queries for Rust.
+;
+; Captures:
+;   @definition.function, @definition.method  a named function; @name is its name
+;   @definition.macro                          a macro, which is called like a function
+;   @definition.class, @definition.trait, ...  a scope owning methods; @name names it,
+;                                              @trait names the trait it implements, each
+;                                              @base names a type it extends and each
+;                                              @implements a trait or interface it
+;                                              implements
+;   @definition.closure                        an anonymous function; @name, if any, is
+;                                              the local variable it is bound to
+;   @reference.call                            a call; @name is the callee expression and
+;                                              `(#set! reference.kind "...")` sets its
+;                                              kind: constructor, spawn, await, macro, ...
+;   @reference.callback                        a function referenced as a value, to be
+;                                              called later; @name is the expression
+;   @import                                    an import; @import.module, @import.name and
+;                                              @import.alias describe one imported binding
+;   @import.fallback                           an import statement that only runs when an
+;                                              earlier import of its bindings failed
+;   @import.reexport                           an import statement that also exports its
+;                                              bindings to other modules
+;   @export                                    a definition exported under another name;
+;                                              @export.name is its name in the module and
+;                                              @export.alias the name it is exported as
+;   @module                                    a module declaration; @module.name is its
+;                                              name and @module.path its `#[path]`, if any
+;   @binding                                   a variable of known type; @binding.name is
+;                                              its name and @binding.type its type, which
+;                                              `(#set! binding.exact "true")` marks as
+;                                              exact rather than possibly a subtype
+;   @variable                                  a parameter or local variable, which
+;                                              shadows the functions of the same name
+;                                              in its function
+
+; Functions and methods, including required trait methods.
+
+(function_item
+  name: (identifier) @name) @definition.function
+
+(function_signature_item
+  name: (identifier) @name) @definition.function
+
+; A closure bound to a variable is called through it.
+(let_declaration
+  pattern: (identifier) @name
+  value: (closure_expression) @definition.closure)
+
+(closure_expression) @definition.closure
+
+; Inherent `impl` blocks are named after their self type.
+
+(impl_item
+  type: [
+    (type_identifier) @name
+    (primitive_type) @name
+    (scoped_type_identifier name: (type_identifier) @name)
+    (generic_type type: (type_identifier) @name)
+    (generic_type type: (scoped_type_identifier name: (type_identifier) @name))
+    (reference_type type: (type_identifier) @name)
+    (reference_type type: (generic_type type: (type_identifier) @name))
+  ]
+  !trait) @definition.impl
+
+; Trait `impl` blocks also record the implemented trait.
+
+(impl_item
+  trait: [
+    (type_identifier) @trait
+    (scoped_type_identifier name: (type_identifier) @trait)
+    (generic_type type: (type_identifier) @trait)
+    (generic_type type: (scoped_type_identifier name: (type_identifier) @trait))
+  ]
+  type: [
+    (type_identifier) @name
+    (primitive_type) @name
+    (scoped_type_identifier name: (type_identifier) @name)
+    (generic_type type: (type_identifier) @name)
+    (generic_type type: (scoped_type_identifier name: (type_identifier) @name))
+    (reference_type type: (type_identifier) @name)
+    (reference_type type: (generic_type type: (type_identifier) @name))
+  ]) @definition.impl
+
+(impl_item
+  trait: [(type_identifier) (scoped_type_identifier)] @implements) @definition.impl
+
+(impl_item
+  trait: (generic_type type: (_) @implements)) @definition.impl
+
+(trait_item
+  name: (type_identifier) @name @trait) @definition.trait
+
+; Supertraits.
+
+(trait_item
+  bounds: (trait_bounds [(type_identifier) (scoped_type_identifier)] @base)) @definition.trait
+
+(trait_item
+  bounds: (trait_bounds (generic_type type: (_) @base))) @definition.trait
+
+; Inline modules scope the items defined in their body.
+
+(mod_item
+  name: (identifier) @name
+  body: (declaration_list)) @definition.module
+
+; Macros are called like functions.
+
+(macro_definition
+  name: (identifier) @name) @definition.macro
+
+; Calls. Those whose kind their context reveals come before the plain calls, which
+; would capture them otherwise.
+
+; A function, closure or future handed to a spawner runs on a thread or task of its
+; own.
+((call_expression
+  function: (_) @_spawner
+  arguments: (arguments
+    .
+    [(identifier) (scoped_identifier) (closure_expression)] @name @reference.call))
+  (#match? @_spawner "(^|::)spawn(_blocking|_local)?$")
+  (#set! reference.kind "spawn"))
+
+((call_expression
+  function: (_) @_spawner
+  arguments: (arguments
+    .
+    (call_expression
+      function: (_) @name) @reference.call))
+  (#match? @_spawner "(^|::)spawn(_blocking|_local)?$")
+  (#set! reference.kind "spawn"))
+
+((await_expression
+  (call_expression
+    function: (_) @name) @reference.call)
+  (#set! reference.kind "await"))
+
+((call_expression
+  function: (scoped_identifier) @name) @reference.call
+  (#match? @name "(^|::)[A-Z][A-Za-z0-9_]*(::<.+>)?::(new|default|from|(new|with|from)_[a-z0-9_]+)$")
+  (#set! reference.kind "constructor"))
+
+((macro_invocation
+  macro: (_) @name) @reference.call
+  (#set! reference.kind "macro"))
+
+(call_expression
+  function: (_) @name) @reference.call
+
+; Functions passed on as values, to be called later: as arguments, assigned to fields
+; or returned. Only those naming a function become callback edges.
+
+(arguments
+  [(identifier) (scoped_identifier)] @name @reference.callback)
+
+(assignment_expression
+  left: (field_expression)
+  right: [(identifier) (scoped_identifier)] @name @reference.callback)
+
+(field_initializer
+  value: [(identifier) (scoped_identifier)] @name @reference.callback)
+
+(return_expression
+  [(identifier) (scoped_identifier)] @name @reference.callback)
+
+(function_item
+  body: (block
+    [(identifier) (scoped_identifier)] @name @reference.callback
+    .))
+
+; Parameters and local variables, which shadow the functions of the same name in their
+; function, so that passing one on is no callback.
+
+(parameter
+  pattern: (identifier) @variable)
+
+(closure_parameters
+  (identifier) @variable)
+
+(let_declaration
+  pattern: (identifier) @variable)
+
+(for_expression
+  pattern: (identifier) @variable)
+
+; Modules. `use` declarations are expanded by the Rust language support, since use
+; trees nest arbitrarily. A `#[path]` attribute names the file of the module after it.
+
+((attribute_item
+  (attribute
+    (identifier) @_attribute
+    value: (string_literal) @module.path))
+  .
+  (mod_item
+    name: (identifier) @module.name) @module
+  (#eq? @_attribute "path"))
+
+(mod_item
+  name: (identifier) @module.name) @module
+
+; Variables of known type, used to resolve method calls on them: annotated variables
+; and parameters, struct literals, unit structs, and the values of constructors, which
+; return `Self` by convention.
+
+; Trait objects, also behind a pointer, and `impl Trait` are typed by their trait.
+
+(_
+  pattern: (identifier) @binding.name
+  type: [
+    (dynamic_type trait: [(type_identifier) (scoped_type_identifier)] @binding.type)
+    (abstract_type trait: [(type_identifier) (scoped_type_identifier)] @binding.type)
+    (reference_type type: (dynamic_type
+      trait: [(type_identifier) (scoped_type_identifier)] @binding.type))
+    (generic_type type_arguments: (type_arguments . (dynamic_type
+      trait: [(type_identifier) (scoped_type_identifier)] @binding.type)))
+  ]) @binding
+
+(let_declaration
+  pattern: (identifier) @binding.name
+  type: [
+    (type_identifier) @binding.type
+    (scoped_type_identifier) @binding.type
+    (generic_type type: (_) @binding.type)
+    (reference_type type: [
+      (type_identifier) @binding.type
+      (scoped_type_identifier) @binding.type
+      (generic_type type: (_) @binding.type)
+    ])
+  ]) @binding
+
+(parameter
+  pattern: (identifier) @binding.name
+  type: [
+    (type_identifier) @binding.type
+    (scoped_type_identifier) @binding.type
+    (generic_type type: (_) @binding.type)
+    (reference_type type: [
+      (type_identifier) @binding.type
+      (scoped_type_identifier) @binding.type
+      (generic_type type: (_) @binding.type)
+    ])
+  ]) @binding
+
+; A type parameter is typed by its first bound, so parameters of that type are too.
+
+(constrained_type_parameter
+  left: (type_identifier) @binding.name
+  bounds: (trait_bounds . [(type_identifier) (scoped_type_identifier)] @binding.type)) @binding
+
+(where_predicate
+  left: (type_identifier) @binding.name
+  bounds: (trait_bounds . [(type_identifier) (scoped_type_identifier)] @binding.type)) @binding
+
+((let_declaration
+  pattern: (identifier) @binding.name
+  value: (struct_expression
+    name: [(type_identifier) (scoped_type_identifier)] @binding.type)) @binding
+  (#set! binding.exact "true"))
+
+; A unit struct is constructed by its name alone, which is told apart from other
+; variables and constants by its case.
+
+((let_declaration
+  pattern: (identifier) @binding.name
+  value: (identifier) @binding.type) @binding
+  (#match? @binding.type "^[A-Z]([A-Za-z0-9]*[a-z][A-Za-z0-9]*)?$")
+  (#set! binding.exact "true"))
+
+((let_declaration
+  pattern: (identifier) @binding.name
+  value: (call_expression
+    function: (scoped_identifier
+      path: [
+        (identifier) @binding.type
+        (scoped_identifier) @binding.type
+        (generic_type type: (_) @binding.type)
+      ]
+      name: (identifier) @_constructor))) @binding
+  (#match? @_constructor "^(new|default|from|new_.+|with_.+|from_.+)$")
+  (#set! binding.exact "true"))
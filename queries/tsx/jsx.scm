@@ -0,0 +1,7 @@
+; The patterns TSX adds to the TypeScript query, for the JSX nodes the TypeScript
+; grammar does not have.
+
+; Event handlers of JSX elements, such as `onClick={handle}`.
+(jsx_attribute
+  (jsx_expression
+    [(identifier) (member_expression)] @name @reference.callback))
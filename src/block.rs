@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Represents the type of a code block.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
@@ -9,6 +10,186 @@ pub enum BlockType {
     NonFunction,
 }
 
+/// Represents a position in a source file.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub struct Position {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number in characters, starting at 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Represents the location of a range of code in a source file.
+///
+/// The `Display` implementation renders the location as `path:line:column`, which
+/// most editors and terminals turn into a clickable link.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct SourceLocation {
+    /// The path of the file containing the code.
+    pub file_path: String,
+    /// The position of the first character of the code.
+    pub start: Position,
+    /// The position just past the last character of the code.
+    pub end: Position,
+    /// The byte offset of the start of the code in the file.
+    pub start_byte: usize,
+    /// The byte offset just past the end of the code in the file.
+    pub end_byte: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file_path, self.start)
+    }
+}
+
+/// Represents how a call reaches the function it calls.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum EdgeKind {
+    /// The call names a function, such as `helper()` or `module.helper()`.
+    #[default]
+    Direct,
+    /// The call names a method of a class of the tree, such as `self.area()` or
+    /// `c.add()` on a receiver of known type.
+    Method,
+    /// The call creates an instance of a class, such as `Calc()`, `new Calc()` or
+    /// `Calc::new()`.
+    Constructor,
+    /// The function overrides or implements the method the call names, so the call
+    /// reaches it through dynamic dispatch when the receiver is of a subtype.
+    Virtual,
+    /// The function is not called but passed on as a value, to be called later by the
+    /// function it is passed to.
+    CallbackReference,
+    /// The function runs on another thread or task, such as the target of
+    /// `threading.Thread(target=f)` or the future passed to `tokio::spawn(f())`.
+    Spawn,
+    /// The call is awaited, such as `await f()` or `f().await`.
+    Await,
+    /// The call invokes a macro, such as Rust's `println!()`.
+    Macro,
+}
+
+impl EdgeKind {
+    /// Maps the `reference.kind` a query pattern sets on a call to an `EdgeKind`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the kind in snake case, e.g. `"spawn"`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the kind, or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "direct" => Some(EdgeKind::Direct),
+            "method" => Some(EdgeKind::Method),
+            "constructor" => Some(EdgeKind::Constructor),
+            "virtual" => Some(EdgeKind::Virtual),
+            "callback_reference" => Some(EdgeKind::CallbackReference),
+            "spawn" => Some(EdgeKind::Spawn),
+            "await" => Some(EdgeKind::Await),
+            "macro" => Some(EdgeKind::Macro),
+            _ => None,
+        }
+    }
+}
+
+/// Represents one place a function is called or referenced from.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct CallLocation {
+    /// The path of the file containing the call.
+    pub file_path: String,
+    /// The position of the call expression.
+    pub position: Position,
+    /// The number of arguments passed, or `None` if the function is not called there,
+    /// like a callback passed on as a value, or its arguments cannot be counted, like
+    /// those of a macro.
+    pub arguments: Option<usize>,
+}
+
+/// Represents what the callee of a call was resolved to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// The callee is a function or class of the indexed tree.
+    #[default]
+    Resolved,
+    /// The callee is defined outside the indexed tree.
+    External {
+        /// The module or crate the callee comes from, such as Python's `os` or the
+        /// crate `serde`, or the module of the language's built-in functions, such as
+        /// `builtins` for Python's `print` or `std` for Rust's `println!`.
+        module: String,
+    },
+    /// The callee could not be found.
+    Unresolved {
+        /// Why the callee could not be found.
+        reason: UnresolvedReason,
+    },
+}
+
+impl Resolution {
+    /// Returns whether the callee is defined outside the indexed tree, e.g. in a crate
+    /// that was not indexed.
+    pub fn is_external(&self) -> bool {
+        matches!(self, Resolution::External { .. })
+    }
+}
+
+/// Represents why the callee of a call could not be found.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnresolvedReason {
+    /// The name is neither defined in the file nor imported nor built into the
+    /// language.
+    UndefinedName,
+    /// The name is imported from a module that could not be found.
+    ModuleNotFound,
+    /// The module or class the name refers to was found, but does not define it.
+    MissingDefinition,
+    /// The function is looked up on a value whose type is not known.
+    UnknownReceiver,
+}
+
+impl fmt::Display for UnresolvedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            UnresolvedReason::UndefinedName => "undefined name",
+            UnresolvedReason::ModuleNotFound => "module not found",
+            UnresolvedReason::MissingDefinition => "missing definition",
+            UnresolvedReason::UnknownReceiver => "unknown receiver",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// Represents a call made from a block to another function.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct OutgoingCall {
+    /// The key of the called block.
+    pub callee: String,
+    /// The position of the first call expression in the calling file.
+    pub position: Position,
+    /// Every call of the callee from the block, in source order.
+    #[serde(default)]
+    pub sites: Vec<CallLocation>,
+    /// What the callee was resolved to.
+    #[serde(default)]
+    pub resolution: Resolution,
+    /// How the call reaches the callee.
+    #[serde(default)]
+    pub kind: EdgeKind,
+}
+
 /// Represents a block of code, which can be a function or a non-function block.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct Block {
@@ -18,12 +199,26 @@ pub struct Block {
     pub block_type: BlockType,
     /// The content of the block (i.e., the code).
     pub content: String,
+    /// The location of the block in its source file.
+    pub location: SourceLocation,
     /// The name of the function, if the block is a function block.
     pub function_name: Option<String>,
-    /// The name of the class containing the block, if applicable.
+    /// The fully qualified path of the function within its file, if the block is a
+    /// function block.
+    ///
+    /// The path follows the conventions of the language, e.g. `outer.<locals>.inner`
+    /// in Python or `Type::method::{closure#0}` in Rust.
+    pub qualified_name: String,
+    /// The name of the innermost class enclosing the block, if applicable.
+    ///
+    /// For Rust methods this is the self type of the enclosing `impl` block.
     pub class_name: Option<String>,
-    /// The keys of the blocks called by this block.
-    pub outgoing_calls: Vec<String>,
+    /// The name of the trait the block belongs to, if applicable.
+    ///
+    /// Set for methods in Rust `trait` items and `impl Trait for Type` blocks.
+    pub trait_name: Option<String>,
+    /// The calls made by this block.
+    pub outgoing_calls: Vec<OutgoingCall>,
 }
 
 impl Block {
@@ -34,16 +229,19 @@ impl Block {
     /// * `node_key` - The unique key for the block.
     /// * `block_type` - The type of the block (function or non-function).
     /// * `content` - The content of the block (i.e., the code).
+    /// * `location` - The location of the block in its source file.
     /// * `function_name` - The name of the function, if the block is a function block.
     /// * `class_name` - The name of the class containing the block, if applicable.
     ///
     /// # Returns
     ///
-    /// A new `Block` instance with the specified parameters and an empty `outgoing_calls` vector.
+    /// A new `Block` instance with the specified parameters, no `trait_name` and an empty
+    /// `outgoing_calls` vector. The `qualified_name` defaults to the function name.
     pub fn new(
         node_key: String,
         block_type: BlockType,
         content: String,
+        location: SourceLocation,
         function_name: Option<String>,
         class_name: Option<String>,
     ) -> Self {
@@ -51,8 +249,11 @@ impl Block {
             node_key,
             block_type,
             content,
+            location,
+            qualified_name: function_name.clone().unwrap_or_default(),
             function_name,
             class_name,
+            trait_name: None,
             outgoing_calls: Vec::new(),
         }
     }
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::cargo::Workspace;
+use crate::error::{Error, Result};
+use crate::parser::ParsedFile;
+use crate::resolver::ResolvedCalls;
+
+/// The version of the cache format, bumped whenever the layout or the meaning of the
+/// cached data changes. Most cached fields have defaults, so an older cache would
+/// often load without errors and have its stale entries reused otherwise.
+const CACHE_VERSION: u32 = 19;
+
+/// A persistent cache of the parsed and resolved files of an index.
+///
+/// Entries are keyed by the path of the file and only used while the hash of the file's
+/// contents matches. The whole cache is discarded when it was written with another
+/// version of the cache format or the configuration it was built with changes. The
+/// files outside the cache that the cached calls depend on, like configuration files,
+/// are hashed too, so that the files depending on them are resolved again once they
+/// change.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    /// The version of the cache format.
+    version: u32,
+    /// The fingerprint of the configuration the cache was built with.
+    fingerprint: u64,
+    /// The Cargo packages the calls of the cached files were resolved with.
+    #[serde(default)]
+    workspace: Workspace,
+    /// The cached files, keyed by module name.
+    files: BTreeMap<String, CachedFile>,
+    /// The hashes of the files the cached calls depend on that are not cached
+    /// themselves, keyed by path, or `None` for those that did not exist.
+    #[serde(default)]
+    dependencies: BTreeMap<String, Option<u64>>,
+}
+
+/// Represents a file stored in the cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// The hash of the contents of the file.
+    pub hash: u64,
+    /// The code structure extracted from the file.
+    pub parsed_file: ParsedFile,
+    /// The calls of the file, as resolved when the cache was written.
+    pub resolved_calls: ResolvedCalls,
+}
+
+impl Cache {
+    /// Creates a new, empty `Cache` for a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `fingerprint` - The fingerprint of the configuration.
+    pub fn new(fingerprint: u64) -> Self {
+        Cache {
+            version: CACHE_VERSION,
+            fingerprint,
+            workspace: Workspace::default(),
+            files: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a `Cache` for a configuration from a set of files, hashing the current
+    /// contents of the other files their calls depend on.
+    ///
+    /// # Arguments
+    ///
+    /// * `fingerprint` - The fingerprint of the configuration.
+    /// * `workspace` - The Cargo packages the calls of the files were resolved with.
+    /// * `files` - The files to cache, keyed by module name.
+    pub fn from_files(
+        fingerprint: u64,
+        workspace: Workspace,
+        files: BTreeMap<String, CachedFile>,
+    ) -> Self {
+        let dependencies = files
+            .values()
+            .flat_map(|file| &file.resolved_calls.dependencies)
+            .filter(|dependency| !files.contains_key(*dependency))
+            .map(|dependency| (dependency.clone(), dependency_hash(Path::new(dependency))))
+            .collect();
+
+        Cache {
+            version: CACHE_VERSION,
+            fingerprint,
+            workspace,
+            files,
+            dependencies,
+        }
+    }
+
+    /// Loads a cache from a file.
+    ///
+    /// A missing or unreadable cache file, or one written by another version of the
+    /// cache format or with another configuration, yields an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file.
+    /// * `fingerprint` - The fingerprint of the current configuration.
+    ///
+    /// # Returns
+    ///
+    /// The loaded `Cache`.
+    pub fn load(path: &Path, fingerprint: u64) -> Self {
+        let cache = File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, Cache>(BufReader::new(file)).ok());
+
+        match cache {
+            Some(cache) if cache.version == CACHE_VERSION && cache.fingerprint == fingerprint => {
+                cache
+            }
+            _ => Cache::new(fingerprint),
+        }
+    }
+
+    /// Writes the cache to a file, creating its parent directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the cache was written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let write_error = |source| Error::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+
+        let file = File::create(path).map_err(write_error)?;
+        serde_json::to_writer(BufWriter::new(file), self).map_err(|error| write_error(error.into()))
+    }
+
+    /// Returns the fingerprint of the configuration the cache was built with.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns the Cargo packages the calls of the cached files were resolved with.
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    /// Returns the cached files, keyed by module name.
+    pub fn files(&self) -> &BTreeMap<String, CachedFile> {
+        &self.files
+    }
+
+    /// Returns the paths of the files the cached calls depend on, other than the cached
+    /// files, that were created, modified or removed since the cache was written.
+    pub fn changed_dependencies(&self) -> Vec<String> {
+        self.dependencies
+            .iter()
+            .filter(|(path, hash)| dependency_hash(Path::new(path)) != **hash)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Consumes the cache and returns the cached files, keyed by module name.
+    pub fn into_files(self) -> BTreeMap<String, CachedFile> {
+        self.files
+    }
+}
+
+/// Hashes a file that cached calls depend on.
+///
+/// # Returns
+///
+/// An `Option` containing the hash of the contents of the file, or `None` if there is
+/// no such file. A directory hashes like an empty file, as only its existence matters.
+fn dependency_hash(path: &Path) -> Option<u64> {
+    if path.is_dir() {
+        return Some(content_hash(&[]));
+    }
+    fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+/// Computes the 64-bit FNV-1a hash of some bytes.
+///
+/// Unlike the hashers of the standard library, the result is stable across Rust
+/// versions and platforms, so it can be persisted.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to hash.
+///
+/// # Returns
+///
+/// The hash of the bytes.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn save_reports_write_errors() {
+        let fixture = Fixture::new(&[("file", "")]);
+        // The parent of the cache file is a file, so the cache cannot be written.
+        let path = fixture.root().join("file").join("cache.json");
+
+        let error = Cache::new(0).save(&path).unwrap_err();
+
+        assert!(matches!(error, Error::Write { .. }));
+        assert!(error.to_string().starts_with("failed to write"));
+    }
+
+    #[test]
+    fn load_discards_caches_of_other_configurations_and_layouts() {
+        let fixture = Fixture::new(&[("app.py", "def main():\n    pass\n")]);
+        let mut config = Config::default();
+        config.cache = Some(fixture.root().join("cache.json"));
+        let path = config.cache.clone().unwrap();
+        fixture.index_with(&config);
+
+        let fingerprint = config.fingerprint();
+        assert_eq!(Cache::load(&path, fingerprint).files().len(), 1);
+        assert!(Cache::load(&path, fingerprint + 1).files().is_empty());
+
+        // A cache whose files no longer match the layout of `CachedFile`.
+        fs::write(
+            &path,
+            format!(
+                "{{\"version\": {}, \"fingerprint\": {}, \"files\": {{\"app.py\": {{\"hash\": 0}}}}}}",
+                CACHE_VERSION, fingerprint
+            ),
+        )
+        .unwrap();
+        assert!(Cache::load(&path, fingerprint).files().is_empty());
+    }
+
+    #[test]
+    fn load_discards_caches_of_other_format_versions() {
+        let fixture = Fixture::new(&[("app.py", "def main():\n    pass\n")]);
+        let mut config = Config::default();
+        config.cache = Some(fixture.root().join("cache.json"));
+        let path = config.cache.clone().unwrap();
+        fixture.index_with(&config);
+        let fingerprint = config.fingerprint();
+        assert_eq!(Cache::load(&path, fingerprint).files().len(), 1);
+
+        // A cache of an older format may still deserialize, as most fields have
+        // defaults, but it is not used.
+        let mut cache: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        cache["version"] = (CACHE_VERSION - 1).into();
+        fs::write(&path, cache.to_string()).unwrap();
+        assert!(Cache::load(&path, fingerprint).files().is_empty());
+    }
+}
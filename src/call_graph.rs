@@ -1,7 +1,8 @@
-use crate::call_stack::CallStackNode;
-use std::collections::{HashMap, HashSet};
+use crate::block::{CallLocation, EdgeKind, Resolution, UnresolvedReason};
+use crate::call_stack::{CallStackNode, ChildCall};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 /// Represents a call graph, which is a directed graph of function calls.
@@ -10,17 +11,86 @@ use serde_json::json;
 /// where each node corresponds to a function and each edge represents a function call.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallGraph {
-    /// A map of node keys to their corresponding `CallStackNode`s.
-    nodes: HashMap<String, CallStackNode>,
-    /// A vector of edges, where each edge is a tuple of the caller and callee node keys.
-    edges: Vec<(String, String)>,
+    /// A map of node keys to their corresponding `CallStackNode`s, ordered by key so
+    /// that every export of the same graph is identical.
+    nodes: BTreeMap<String, CallStackNode>,
+    /// A vector of edges, at most one per caller and callee, grouped by caller in key
+    /// order.
+    edges: Vec<CallEdge>,
+}
+
+/// Represents the calls from one function to another in the call graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    /// The key of the caller node.
+    pub from: String,
+    /// The key of the callee node.
+    pub to: String,
+    /// How the caller reaches the callee.
+    pub kind: EdgeKind,
+    /// Every place the caller calls the callee from, in source order.
+    pub sites: Vec<CallLocation>,
+}
+
+/// Represents a chain of calls through the call graph, i.e. a call stack that can
+/// occur at runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallChain {
+    /// The keys of the functions on the chain, from the outermost caller to the
+    /// innermost callee.
+    pub frames: Vec<String>,
+    /// The key of the function the chain was cut at, if following the chain further
+    /// would enter a function that is already on it again, like a recursive call.
+    pub cycle: Option<String>,
+    /// Whether the chain was cut short at the maximum depth.
+    pub truncated: bool,
+}
+
+/// How an export draws the nodes of one kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeDisplay {
+    /// Every node is drawn on its own.
+    #[default]
+    Show,
+    /// The nodes are drawn as one node per module, or as a single node if they have no
+    /// module.
+    Collapse,
+    /// The nodes, and the edges to them, are left out.
+    Hide,
+}
+
+/// Options for exporting a call graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// How nodes that stand in for functions outside the indexed tree are drawn.
+    /// Collapsing them draws one node per external module, such as `os` or `serde`.
+    pub external: NodeDisplay,
+    /// How nodes that stand in for callees that could not be resolved are drawn.
+    /// Collapsing them draws a single `unresolved` node.
+    pub unresolved: NodeDisplay,
+}
+
+/// The key of the node unresolved callees are collapsed into.
+const UNRESOLVED_KEY: &str = "unresolved";
+
+impl CallEdge {
+    /// Returns how many times the caller calls the callee.
+    pub fn count(&self) -> usize {
+        self.sites.len()
+    }
+}
+
+impl Default for CallGraph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CallGraph {
     /// Creates a new, empty `CallGraph`.
     pub fn new() -> Self {
         CallGraph {
-            nodes: HashMap::new(),
+            nodes: BTreeMap::new(),
             edges: Vec::new(),
         }
     }
@@ -37,39 +107,215 @@ impl CallGraph {
 
     /// Adds a new edge to the call graph.
     ///
+    /// If the graph already has an edge of the same kind from the caller to the callee,
+    /// the call sites are added to that edge instead, so that a function that is, say,
+    /// both spawned and awaited by the same caller keeps an edge of each kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The key of the caller node.
+    /// * `to` - The key of the callee node.
+    /// * `kind` - How the caller reaches the callee.
+    /// * `sites` - The places the caller calls the callee from.
+    pub fn add_edge(&mut self, from: String, to: String, kind: EdgeKind, sites: Vec<CallLocation>) {
+        let start = self
+            .edges
+            .partition_point(|edge| edge.from.as_str() < from.as_str());
+        let end = self
+            .edges
+            .partition_point(|edge| edge.from.as_str() <= from.as_str());
+
+        match self.edges[start..end]
+            .iter_mut()
+            .find(|edge| edge.to == to && edge.kind == kind)
+        {
+            Some(edge) => edge.sites.extend(sites),
+            None => self.edges.insert(
+                end,
+                CallEdge {
+                    from,
+                    to,
+                    kind,
+                    sites,
+                },
+            ),
+        }
+    }
+
+    /// Adds a node to the call graph together with the edges to its children.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_key` - The unique key for the node.
+    /// * `node` - The `CallStackNode` to add.
+    pub fn insert_node(&mut self, node_key: String, node: CallStackNode) {
+        for child in &node.children {
+            self.add_edge(
+                node_key.clone(),
+                child.node_key.clone(),
+                child.kind,
+                child.sites.clone(),
+            );
+        }
+        self.nodes.insert(node_key, node);
+    }
+
+    /// Removes a node from the call graph together with the edges from it.
+    ///
+    /// Edges to the node are kept, as callers may still refer to it by key.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_key` - The unique key for the node.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the removed `CallStackNode`, or `None` if the node was
+    /// not found.
+    pub fn remove_node(&mut self, node_key: &str) -> Option<CallStackNode> {
+        self.edges.retain(|edge| edge.from != node_key);
+        self.nodes.remove(node_key)
+    }
+
+    /// Returns the edges of the graph, grouped by caller in key order.
+    pub fn edges(&self) -> &[CallEdge] {
+        &self.edges
+    }
+
+    /// Retrieves the edge from one node to another, the first one if the caller reaches
+    /// the callee in several ways.
+    ///
     /// # Arguments
     ///
     /// * `from` - The key of the caller node.
     /// * `to` - The key of the callee node.
-    pub fn add_edge(&mut self, from: String, to: String) {
-        self.edges.push((from, to));
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a reference to the `CallEdge`, or `None` if the caller
+    /// does not call the callee.
+    pub fn edge(&self, from: &str, to: &str) -> Option<&CallEdge> {
+        self.edges
+            .iter()
+            .find(|edge| edge.from == from && edge.to == to)
+    }
+
+    /// Returns the edges of one kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the edges.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the caller and callee keys of the edges, grouped by caller in
+    /// key order.
+    pub fn edges_of_kind(&self, kind: EdgeKind) -> impl Iterator<Item = (&str, &str)> {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.kind == kind)
+            .map(|edge| (edge.from.as_str(), edge.to.as_str()))
+    }
+
+    /// Returns a copy of the graph with only the edges of some kinds, e.g. to export
+    /// the graph without the calls through dynamic dispatch.
+    ///
+    /// Every node is kept, including those the remaining edges no longer reach.
+    ///
+    /// # Arguments
+    ///
+    /// * `kinds` - The kinds of the edges to keep.
+    ///
+    /// # Returns
+    ///
+    /// A new `CallGraph` with the same nodes and the edges of the given kinds.
+    pub fn filter_by_kinds(&self, kinds: &[EdgeKind]) -> CallGraph {
+        let mut nodes = self.nodes.clone();
+        for node in nodes.values_mut() {
+            node.children.retain(|child| kinds.contains(&child.kind));
+        }
+
+        CallGraph {
+            nodes,
+            edges: self
+                .edges
+                .iter()
+                .filter(|edge| kinds.contains(&edge.kind))
+                .cloned()
+                .collect(),
+        }
     }
 
     /// Converts the `CallGraph` to a Graphviz DOT format string.
     ///
+    /// Nodes that stand in for functions outside the indexed tree are drawn dashed.
+    /// Every kind of edge is drawn in a style of its own: calls through dynamic
+    /// dispatch dashed, callback references dotted, method calls blue, constructor
+    /// calls bold green, spawns red, awaited calls purple and macro invocations gray.
+    ///
     /// # Returns
     ///
     /// A string containing the Graphviz DOT representation of the call graph.
     pub fn to_graphviz(&self) -> String {
+        self.to_graphviz_with(&ExportOptions::default())
+    }
+
+    /// Converts the `CallGraph` to a Graphviz DOT format string, drawing external and
+    /// unresolved nodes as the options say. Unresolved nodes are drawn dotted.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The `ExportOptions` of the export.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the Graphviz DOT representation of the call graph.
+    pub fn to_graphviz_with(&self, options: &ExportOptions) -> String {
+        let graph = self.displayed(options);
         let mut graphviz = String::from("digraph CallGraph {\n");
         graphviz.push_str("  rankdir=LR;\n");
         graphviz.push_str("  node [shape=box];\n");
 
-        for (node_key, node) in &self.nodes {
-            let file_name = node.file_path.split('/').last().unwrap_or("");
-            let mut node_label = format!("{}::{}", file_name, node.function_name);
-            if let Some(class_name) = &node.class_name {
-                node_label = format!(
-                    "{}::{}",
-                    file_name,
-                    format!("{}::{}", class_name, node.function_name)
-                );
-            }
-            graphviz.push_str(&format!("  \"{}\" [label=\"{}\"];\n", node_key, node_label));
+        for (node_key, node) in &graph.nodes {
+            let style = match node.resolution {
+                Resolution::Unresolved { .. } => ", style=dotted",
+                Resolution::External { .. } => ", style=dashed",
+                Resolution::Resolved => "",
+            };
+            graphviz.push_str(&format!(
+                "  \"{}\" [label=\"{}\"{}];\n",
+                dot_escape(node_key),
+                dot_escape(&node_label(node)),
+                style
+            ));
         }
 
-        for (from, to) in &self.edges {
-            graphviz.push_str(&format!("  \"{}\" -> \"{}\";\n", from, to));
+        for edge in &graph.edges {
+            let style = match edge.kind {
+                EdgeKind::Direct => "",
+                EdgeKind::Method => ", color=blue",
+                EdgeKind::Constructor => ", color=darkgreen, style=bold",
+                EdgeKind::Virtual => ", style=dashed",
+                EdgeKind::CallbackReference => ", style=dotted, arrowhead=odot",
+                EdgeKind::Spawn => ", color=red, arrowhead=veevee",
+                EdgeKind::Await => ", color=purple, arrowhead=onormal",
+                EdgeKind::Macro => ", color=gray, arrowhead=diamond",
+            };
+            // Functions called from several places are labelled with the count.
+            let count = edge.count().max(1);
+            let label = if count > 1 {
+                format!(", label=\"{}\"", count)
+            } else {
+                String::new()
+            };
+            graphviz.push_str(&format!(
+                "  \"{}\" -> \"{}\" [weight={}{}{}];\n",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to),
+                count,
+                label,
+                style
+            ));
         }
 
         graphviz.push('}');
@@ -78,32 +324,73 @@ impl CallGraph {
 
     /// Converts the `CallGraph` to a Mermaid diagram format string.
     ///
+    /// Nodes that stand in for functions outside the indexed tree get the `external`
+    /// class, which draws them dashed. Edges other than direct calls are labelled with
+    /// their kind; those through dynamic dispatch and callback references are dotted,
+    /// and constructor calls and spawns thick.
+    ///
     /// # Returns
     ///
     /// A string containing the Mermaid representation of the call graph.
     pub fn to_mermaid(&self) -> String {
+        self.to_mermaid_with(&ExportOptions::default())
+    }
+
+    /// Converts the `CallGraph` to a Mermaid diagram format string, drawing external
+    /// and unresolved nodes as the options say. Unresolved nodes get the `unresolved`
+    /// class, which draws them dotted.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The `ExportOptions` of the export.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the Mermaid representation of the call graph.
+    pub fn to_mermaid_with(&self, options: &ExportOptions) -> String {
+        let graph = self.displayed(options);
         let mut mermaid = String::from("graph TD;\n");
-        for (node_key, node) in &self.nodes {
-            let file_name = node.file_path.split('/').last().unwrap_or("");
-            let mut node_label = format!("{}::{}", file_name, node.function_name);
-            if let Some(class_name) = &node.class_name {
-                node_label = format!(
-                    "{}::{}",
-                    file_name,
-                    format!("{}::{}", class_name, node.function_name)
-                );
-            }
-            // convert spaces into underscores
-            let node_key = node_key.replace(' ', "_");
-            node_label = node_label.replace(' ', "_");
+        for (node_key, node) in &graph.nodes {
+            let node_key = mermaid_id(node_key);
+            let node_label = mermaid_label(&node_label(node));
             mermaid.push_str(&format!("  {}[\"{}\"];\n", node_key, node_label));
         }
 
-        for (from, to) in &self.edges {
-            // convert spaces into underscores
-            let from = from.replace(' ', "_");
-            let to = to.replace(' ', "_");
-            mermaid.push_str(&format!("  {} --> {};\n", from, to));
+        for edge in &graph.edges {
+            let from = mermaid_id(&edge.from);
+            let to = mermaid_id(&edge.to);
+            let arrow = match edge.kind {
+                EdgeKind::Direct => "-->",
+                EdgeKind::Method => "-->|method|",
+                EdgeKind::Constructor => "==>|constructor|",
+                EdgeKind::Virtual => "-.->|virtual|",
+                EdgeKind::CallbackReference => "-.->|callback|",
+                EdgeKind::Spawn => "==>|spawn|",
+                EdgeKind::Await => "-->|await|",
+                EdgeKind::Macro => "-->|macro|",
+            };
+            mermaid.push_str(&format!("  {} {} {};\n", from, arrow, to));
+        }
+
+        let external: Vec<String> = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.resolution.is_external())
+            .map(|(node_key, _)| mermaid_id(node_key))
+            .collect();
+        if !external.is_empty() {
+            mermaid.push_str("  classDef external stroke-dasharray: 5 5;\n");
+            mermaid.push_str(&format!("  class {} external;\n", external.join(",")));
+        }
+        let unresolved: Vec<String> = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| matches!(node.resolution, Resolution::Unresolved { .. }))
+            .map(|(node_key, _)| mermaid_id(node_key))
+            .collect();
+        if !unresolved.is_empty() {
+            mermaid.push_str("  classDef unresolved stroke-dasharray: 2 2;\n");
+            mermaid.push_str(&format!("  class {} unresolved;\n", unresolved.join(",")));
         }
 
         mermaid
@@ -115,25 +402,47 @@ impl CallGraph {
     ///
     /// A pretty JSON string representing the call graph with nodes and edges.
     pub fn to_json_flowchart(&self) -> String {
-        let nodes: Vec<_> = self.nodes.iter().map(|(key, node)| {
-            let file_name = node.file_path.split('/').last().unwrap_or("");
-            let node_label = if let Some(class_name) = &node.class_name {
-                format!("{}::{}::{}", file_name, class_name, node.function_name)
-            } else {
-                format!("{}::{}", file_name, node.function_name)
-            };
-            json!({
-                "id": key,
-                "label": node_label
+        self.to_json_flowchart_with(&ExportOptions::default())
+    }
+
+    /// Converts the `CallGraph` to a JSON formatted string suitable for generating
+    /// flowcharts, listing external and unresolved nodes as the options say.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The `ExportOptions` of the export.
+    ///
+    /// # Returns
+    ///
+    /// A pretty JSON string representing the call graph with nodes and edges.
+    pub fn to_json_flowchart_with(&self, options: &ExportOptions) -> String {
+        let graph = self.displayed(options);
+        let nodes: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|(key, node)| {
+                json!({
+                    "id": key,
+                    "label": node_label(node),
+                    "external": node.resolution.is_external(),
+                    "resolution": node.resolution
+                })
             })
-        }).collect();
+            .collect();
 
-        let edges: Vec<_> = self.edges.iter().map(|(from, to)| {
-            json!({
-                "from": from,
-                "to": to
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "from": edge.from,
+                    "to": edge.to,
+                    "kind": edge.kind,
+                    "count": edge.count(),
+                    "sites": edge.sites
+                })
             })
-        }).collect();
+            .collect();
 
         let flowchart = json!({
             "nodes": nodes,
@@ -143,31 +452,778 @@ impl CallGraph {
         serde_json::to_string_pretty(&flowchart).unwrap()
     }
 
+    /// Lists the calls whose callee could not be resolved.
+    ///
+    /// # Returns
+    ///
+    /// The edges to unresolved nodes, grouped by caller in key order, each with the
+    /// reason its callee could not be resolved.
+    pub fn unresolved_calls(&self) -> Vec<(&CallEdge, UnresolvedReason)> {
+        self.edges
+            .iter()
+            .filter_map(|edge| match self.nodes.get(&edge.to)?.resolution {
+                Resolution::Unresolved { reason } => Some((edge, reason)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Retrieves a list of potential entry points in the call graph.
     ///
-    /// Defines an entry point as a node with no incoming edges and at least one outgoing edge, 
+    /// Defines an entry point as a node with no incoming edges and at least one outgoing edge,
     /// representing functions that could initiate execution paths.
     ///
     /// # Returns
     ///
-    /// A `Vec<String>` containing the keys of all potential entry point nodes.
+    /// A `Vec<String>` containing the keys of all potential entry point nodes, sorted.
     pub fn get_entry_points(&self) -> Vec<String> {
         let mut incoming_edges = HashSet::new();
-        let mut candidates = HashSet::new();
 
-        for (_, to) in &self.edges {
-            incoming_edges.insert(to.clone());
+        for edge in &self.edges {
+            incoming_edges.insert(edge.to.clone());
+        }
+
+        self.nodes
+            .keys()
+            .filter(|node_key| !incoming_edges.contains(*node_key))
+            .cloned()
+            .collect()
+    }
+
+    /// Enumerates the call chains that start at a function.
+    ///
+    /// Every chain follows the calls from the function until it reaches a function
+    /// that calls nothing, or is cut where its next call would re-enter a function
+    /// already on it, which is recorded as its `cycle`, or at the maximum depth. The
+    /// chains only contain each function once, so recursion does not make them
+    /// endless. All kinds of edges are followed; use `filter_by_kinds` to follow only
+    /// some.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The key of the function the chains start at.
+    /// * `max_depth` - The maximum number of frames of a chain.
+    /// * `max_paths` - The maximum number of chains to enumerate.
+    ///
+    /// # Returns
+    ///
+    /// The `CallChain`s from the function in depth-first order, calls in source order,
+    /// or an empty vector if the function is not part of the graph.
+    pub fn stacks_from(&self, entry: &str, max_depth: usize, max_paths: usize) -> Vec<CallChain> {
+        self.chains(entry, false, max_depth, max_paths)
+    }
+
+    /// Enumerates the call chains that reach a function from the entry points of the
+    /// graph, e.g. to find out how user input can reach it.
+    ///
+    /// Chains are followed back from the function through its callers until they
+    /// reach a function nothing calls. A function that is only called from functions
+    /// already on the chain, like a group of mutually recursive functions nothing
+    /// else calls, starts a chain cut at the first of those callers, which is
+    /// recorded as its `cycle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The key of the function the chains lead to.
+    /// * `max_depth` - The maximum number of frames of a chain.
+    /// * `max_paths` - The maximum number of chains to enumerate.
+    ///
+    /// # Returns
+    ///
+    /// The `CallChain`s to the function, each from its outermost caller, or an empty
+    /// vector if the function is not part of the graph.
+    pub fn stacks_to(&self, target: &str, max_depth: usize, max_paths: usize) -> Vec<CallChain> {
+        self.chains(target, true, max_depth, max_paths)
+    }
+
+    /// Finds a chain of calls from one function to another with the fewest calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The key of the outermost caller.
+    /// * `to` - The key of the innermost callee.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the shortest `CallChain`, the first in source order of
+    /// the calls if there are several, or `None` if `from` does not reach `to`.
+    pub fn shortest_stack(&self, from: &str, to: &str) -> Option<CallChain> {
+        let callees = self.neighbours(false);
+        // The caller each function was first reached from.
+        let mut parents: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        let mut reached = from == to && self.nodes.contains_key(from);
+
+        while let Some(node_key) = queue.pop_front().filter(|_| !reached) {
+            for &callee in callees.get(node_key).into_iter().flatten() {
+                if callee == from || parents.contains_key(callee) {
+                    continue;
+                }
+                parents.insert(callee, node_key);
+                if callee == to {
+                    reached = true;
+                    break;
+                }
+                queue.push_back(callee);
+            }
+        }
+        if !reached {
+            return None;
+        }
+
+        let mut frames = vec![to.to_string()];
+        let mut node_key = to;
+        while node_key != from {
+            node_key = parents[node_key];
+            frames.push(node_key.to_string());
+        }
+        frames.reverse();
+
+        Some(CallChain {
+            frames,
+            cycle: None,
+            truncated: false,
+        })
+    }
+
+    /// Maps the key of every function to the keys of its callees, or of its callers if
+    /// `reverse` is set, in the order of the edges.
+    fn neighbours(&self, reverse: bool) -> HashMap<&str, Vec<&str>> {
+        let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &self.edges {
+            let (from, to) = if reverse {
+                (&edge.to, &edge.from)
+            } else {
+                (&edge.from, &edge.to)
+            };
+            let to_keys = neighbours.entry(from).or_default();
+            // Edges of several kinds between the same functions make up one frame.
+            if !to_keys.contains(&to.as_str()) {
+                to_keys.push(to);
+            }
+        }
+        neighbours
+    }
+
+    /// Enumerates the call chains from a function through its callees, or back
+    /// through its callers if `reverse` is set, depth first.
+    fn chains(
+        &self,
+        start: &str,
+        reverse: bool,
+        max_depth: usize,
+        max_paths: usize,
+    ) -> Vec<CallChain> {
+        let neighbours = self.neighbours(reverse);
+        let mut chains = Vec::new();
+        if !self.nodes.contains_key(start) && !neighbours.contains_key(start) {
+            return chains;
+        }
+
+        let chain = |path: &[&str], cycle: Option<&str>, truncated: bool| {
+            let mut frames: Vec<String> = path.iter().map(|frame| frame.to_string()).collect();
+            if reverse {
+                frames.reverse();
+            }
+            CallChain {
+                frames,
+                cycle: cycle.map(str::to_string),
+                truncated,
+            }
+        };
+
+        // The functions on the current chain, and the index of the next neighbour of
+        // each to follow.
+        let mut path = vec![start];
+        let mut cursors = vec![0];
+        while chains.len() < max_paths {
+            let (Some(&node_key), Some(cursor)) = (path.last(), cursors.last_mut()) else {
+                break;
+            };
+            let next = neighbours.get(node_key).map_or(&[][..], Vec::as_slice);
+
+            if *cursor == 0 {
+                let mut cycles = next.iter().filter(|neighbour| path.contains(neighbour));
+                if next.is_empty() || path.len() >= max_depth {
+                    chains.push(chain(&path, None, !next.is_empty()));
+                    path.pop();
+                    cursors.pop();
+                    continue;
+                } else if !reverse {
+                    for &cycle in cycles.take(max_paths - chains.len()) {
+                        chains.push(chain(&path, Some(cycle), false));
+                    }
+                } else if cycles.clone().count() == next.len() {
+                    chains.push(chain(&path, cycles.next().copied(), false));
+                }
+            }
+
+            match next[*cursor..]
+                .iter()
+                .position(|neighbour| !path.contains(neighbour))
+            {
+                Some(offset) => {
+                    let neighbour = next[*cursor + offset];
+                    *cursor += offset + 1;
+                    path.push(neighbour);
+                    cursors.push(0);
+                }
+                None => {
+                    path.pop();
+                    cursors.pop();
+                }
+            }
         }
 
-        // Detecting only 'main' functions or equivalents as entry points
+        chains.truncate(max_paths);
+        chains
+    }
+
+    /// Builds the graph an export draws, with external and unresolved nodes shown,
+    /// collapsed or hidden as the options say.
+    fn displayed(&self, options: &ExportOptions) -> CallGraph {
+        if options.external == NodeDisplay::Show && options.unresolved == NodeDisplay::Show {
+            return self.clone();
+        }
+
+        // The key each node is drawn under, or `None` if it is hidden.
+        let display_key = |node_key: &str| -> Option<String> {
+            let Some(node) = self.nodes.get(node_key) else {
+                return Some(node_key.to_string());
+            };
+            let display = match node.resolution {
+                Resolution::Resolved => NodeDisplay::Show,
+                Resolution::External { .. } => options.external,
+                Resolution::Unresolved { .. } => options.unresolved,
+            };
+            match (display, &node.resolution) {
+                (NodeDisplay::Hide, _) => None,
+                (NodeDisplay::Collapse, Resolution::External { module }) => Some(module.clone()),
+                (NodeDisplay::Collapse, _) => Some(UNRESOLVED_KEY.to_string()),
+                (NodeDisplay::Show, _) => Some(node_key.to_string()),
+            }
+        };
+
+        let mut graph = CallGraph::new();
         for (node_key, node) in &self.nodes {
-            if node.function_name == "main" && !incoming_edges.contains(node_key) {
-                candidates.insert(node_key.clone());
-            } else if !incoming_edges.contains(node_key) {
-                candidates.insert(node_key.clone());
+            let Some(node_display_key) = display_key(node_key) else {
+                continue;
+            };
+            let mut children: Vec<ChildCall> = Vec::new();
+            for child in &node.children {
+                let Some(child_key) = display_key(&child.node_key) else {
+                    continue;
+                };
+                // Calls of each kind into a collapsed node stay apart, like edges do.
+                match children
+                    .iter_mut()
+                    .find(|other| other.node_key == child_key && other.kind == child.kind)
+                {
+                    Some(other) => other.sites.extend(child.sites.iter().cloned()),
+                    None => children.push(ChildCall {
+                        node_key: child_key,
+                        ..child.clone()
+                    }),
+                }
+            }
+
+            let node = if node_display_key == *node_key {
+                CallStackNode {
+                    children,
+                    ..node.clone()
+                }
+            } else {
+                // A collapsed node is labelled with its module.
+                CallStackNode {
+                    file_path: String::new(),
+                    location: Default::default(),
+                    class_name: None,
+                    trait_name: None,
+                    function_name: node_display_key.clone(),
+                    qualified_name: node_display_key.clone(),
+                    children: Vec::new(),
+                    resolution: node.resolution.clone(),
+                    stand_in: true,
+                }
+            };
+            graph.nodes.insert(node_display_key, node);
+        }
+
+        for edge in &self.edges {
+            if let (Some(from), Some(to)) = (display_key(&edge.from), display_key(&edge.to)) {
+                graph.add_edge(from, to, edge.kind, edge.sites.clone());
+            }
+        }
+
+        graph
+    }
+}
+
+/// Escapes a string for a quoted Graphviz id or label.
+pub(crate) fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Turns a key into a Mermaid node id. Besides letters and digits, ids keep only the
+/// `.` and `/` of paths. Any other byte, which Mermaid could read as the shape or the
+/// edge of a node, becomes `_` followed by its two hex digits, and `_` itself becomes
+/// `__`, so that different keys never share an id.
+pub(crate) fn mermaid_id(key: &str) -> String {
+    let mut id = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'/' => id.push(char::from(byte)),
+            b'_' => id.push_str("__"),
+            _ => id.push_str(&format!("_{:02x}", byte)),
+        }
+    }
+    id
+}
+
+/// Escapes a label for a quoted Mermaid node, with spaces turned into underscores.
+pub(crate) fn mermaid_label(label: &str) -> String {
+    label.replace(' ', "_").replace('"', "#quot;")
+}
+
+/// Returns the label of a node in the exported graphs: the name of its file followed
+/// by its qualified name, or only the latter for nodes without a file.
+fn node_label(node: &CallStackNode) -> String {
+    let file_name = node.file_path.split('/').next_back().unwrap_or("");
+    if file_name.is_empty() {
+        node.qualified_name.clone()
+    } else {
+        format!("{}::{}", file_name, node.qualified_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use crate::parser::EXPRESSION_RECEIVER;
+
+    /// Creates the node of a function of the tree.
+    fn node(file_path: &str, qualified_name: &str) -> CallStackNode {
+        CallStackNode {
+            file_path: file_path.to_string(),
+            location: Default::default(),
+            class_name: None,
+            trait_name: None,
+            function_name: qualified_name.to_string(),
+            qualified_name: qualified_name.to_string(),
+            children: Vec::new(),
+            resolution: Resolution::Resolved,
+            stand_in: false,
+        }
+    }
+
+    /// Builds a graph of functions from its direct calls, in order.
+    fn graph(calls: &[(&str, &str)]) -> CallGraph {
+        let mut graph = CallGraph::new();
+        for &(from, to) in calls {
+            for key in [from, to] {
+                graph.add_node(key.to_string(), node("main.py", key));
+            }
+            graph.add_edge(
+                from.to_string(),
+                to.to_string(),
+                EdgeKind::Direct,
+                Vec::new(),
+            );
+        }
+        graph
+    }
+
+    /// Creates a chain of frames, cut at a cycle or at the maximum depth.
+    fn chain(frames: &[&str], cycle: Option<&str>, truncated: bool) -> CallChain {
+        CallChain {
+            frames: frames.iter().map(|frame| frame.to_string()).collect(),
+            cycle: cycle.map(str::to_string),
+            truncated,
+        }
+    }
+
+    /// A token of the DOT language.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Id(String),
+        Arrow,
+        Punct(char),
+    }
+
+    /// Splits DOT source into tokens, unescaping quoted ids.
+    fn tokenize(dot: &str) -> Result<Vec<Token>, String> {
+        let mut tokens = Vec::new();
+        let mut chars = dot.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {}
+                '"' => {
+                    let mut id = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c @ ('"' | '\\')) => id.push(c),
+                                Some(c) => id.extend(['\\', c]),
+                                None => return Err("unterminated escape".to_string()),
+                            },
+                            Some(c) => id.push(c),
+                            None => return Err(format!("unterminated string {:?}", id)),
+                        }
+                    }
+                    tokens.push(Token::Id(id));
+                }
+                '-' if chars.peek() == Some(&'>') => {
+                    chars.next();
+                    tokens.push(Token::Arrow);
+                }
+                '{' | '}' | '[' | ']' | ';' | ',' | '=' => tokens.push(Token::Punct(c)),
+                c if c.is_ascii_alphanumeric() || c == '_' => {
+                    let mut id = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                            break;
+                        }
+                        id.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Id(id));
+                }
+                c => return Err(format!("unexpected {:?}", c)),
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// The nodes and edges of a DOT graph.
+    #[derive(Debug, Default)]
+    struct Dot {
+        nodes: Vec<(String, Vec<(String, String)>)>,
+        edges: Vec<(String, String)>,
+    }
+
+    /// Parses the subset of the DOT language the exports write: a `digraph` of
+    /// graph attributes, default attributes, nodes and edges, each ended by `;`.
+    fn parse_dot(dot: &str) -> Result<Dot, String> {
+        let tokens = tokenize(dot)?;
+        let mut tokens = tokens.iter().peekable();
+        let mut next = || tokens.next().cloned().ok_or("unexpected end");
+        let id = |token: Token| match token {
+            Token::Id(id) => Ok(id),
+            token => Err(format!("expected an id, found {:?}", token)),
+        };
+
+        if id(next()?)? != "digraph" {
+            return Err("expected a digraph".to_string());
+        }
+        let mut token = next()?;
+        if let Token::Id(_) = token {
+            token = next()?;
+        }
+        if token != Token::Punct('{') {
+            return Err(format!("expected {{, found {:?}", token));
+        }
+
+        let mut graph = Dot::default();
+        loop {
+            let name = match next()? {
+                Token::Punct('}') => break,
+                token => id(token)?,
+            };
+            let mut token = next()?;
+            let mut to = None;
+            match token {
+                Token::Punct('=') => {
+                    id(next()?)?;
+                    token = next()?;
+                }
+                Token::Arrow => {
+                    to = Some(id(next()?)?);
+                    token = next()?;
+                }
+                _ => {}
+            }
+            let mut attributes = Vec::new();
+            if token == Token::Punct('[') {
+                loop {
+                    let key = match next()? {
+                        Token::Punct(']') => break,
+                        Token::Punct(',') => continue,
+                        token => id(token)?,
+                    };
+                    if next()? != Token::Punct('=') {
+                        return Err(format!("expected = after {}", key));
+                    }
+                    attributes.push((key, id(next()?)?));
+                }
+                token = next()?;
+            }
+            if token != Token::Punct(';') {
+                return Err(format!("expected ;, found {:?}", token));
             }
+            match to {
+                Some(to) => graph.edges.push((name, to)),
+                None if name == "node" || name == "edge" || attributes.is_empty() => {}
+                None => graph.nodes.push((name, attributes)),
+            }
+        }
+        match tokens.next() {
+            None => Ok(graph),
+            Some(token) => Err(format!("unexpected {:?} after the graph", token)),
         }
+    }
 
-        candidates.into_iter().collect()
+    #[test]
+    fn graphviz_export_of_a_method_called_on_a_string_parses() {
+        let fixture = Fixture::new(&[(
+            "src/main.rs",
+            "fn main() {\n    let name = \"Alice\".to_string();\n    let tag = \"a\\\"b\".to_string();\n}\n",
+        )]);
+        let index = fixture.index();
+
+        let dot = parse_dot(&index.call_graph.to_graphviz()).unwrap();
+        let main = fixture.key("src/main.rs", "main");
+        let callee = format!("{}.to_string", EXPRESSION_RECEIVER);
+        assert!(dot.nodes.iter().any(|(id, _)| *id == callee));
+        assert_eq!(dot.edges, [(main, callee)]);
+    }
+
+    #[test]
+    fn exports_escape_keys_and_labels() {
+        let file_path = "src/a \"b\".py";
+        let from = r#"src/a "b".py.f\"#.to_string();
+        let to = "src/a \"b\".py.{closure#0}".to_string();
+        let mut graph = CallGraph::new();
+        graph.add_node(from.clone(), node(file_path, "f\\"));
+        graph.add_node(to.clone(), node(file_path, "{closure#0}"));
+        graph.add_edge(from.clone(), to.clone(), EdgeKind::Direct, Vec::new());
+
+        let dot = parse_dot(&graph.to_graphviz()).unwrap();
+        let labels: Vec<_> = dot
+            .nodes
+            .iter()
+            .map(|(id, attributes)| (id.clone(), attributes[0].1.clone()))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                (from.clone(), r#"a "b".py::f\"#.to_string()),
+                (to.clone(), "a \"b\".py::{closure#0}".to_string()),
+            ]
+        );
+        assert_eq!(dot.edges, [(from, to)]);
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.contains(
+            "  src/a_20_22b_22.py.f_5c[\"a_#quot;b#quot;.py::f\\\"];\n  src/a_20_22b_22.py._7bclosure_230_7d"
+        ));
+        assert!(mermaid
+            .contains("  src/a_20_22b_22.py.f_5c --> src/a_20_22b_22.py._7bclosure_230_7d;\n"));
+    }
+
+    #[test]
+    fn mermaid_export_keeps_keys_apart_that_differ_only_in_escaped_characters() {
+        let keys = ["my-mod.py.f", "my_mod.py.f", "T.<Tr>.m", "T._Tr_.m"];
+        let mut graph = CallGraph::new();
+        for key in keys {
+            graph.add_node(key.to_string(), node("", key));
+        }
+        graph.add_edge(
+            keys[0].to_string(),
+            keys[2].to_string(),
+            EdgeKind::Direct,
+            Vec::new(),
+        );
+        graph.add_edge(
+            keys[1].to_string(),
+            keys[3].to_string(),
+            EdgeKind::Direct,
+            Vec::new(),
+        );
+
+        let ids: Vec<_> = keys.iter().map(|key| mermaid_id(key)).collect();
+        assert_eq!(
+            ids,
+            [
+                "my_2dmod.py.f",
+                "my__mod.py.f",
+                "T._3cTr_3e.m",
+                "T.__Tr__.m"
+            ]
+        );
+        let mermaid = graph.to_mermaid();
+        for id in &ids {
+            assert_eq!(mermaid.matches(&format!("  {}[", id)).count(), 1, "{}", id);
+        }
+        assert!(mermaid.contains("  my_2dmod.py.f --> T._3cTr_3e.m;\n"));
+        assert!(mermaid.contains("  my__mod.py.f --> T.__Tr__.m;\n"));
+    }
+
+    #[test]
+    fn collapsed_nodes_keep_the_calls_of_each_kind_apart() {
+        let external = |name: &str| CallStackNode {
+            resolution: Resolution::External {
+                module: "requests".to_string(),
+            },
+            stand_in: true,
+            ..node("requests", name)
+        };
+        let child = |node_key: &str, kind| ChildCall {
+            node_key: node_key.to_string(),
+            kind,
+            sites: Vec::new(),
+        };
+        let mut graph = CallGraph::new();
+        graph.insert_node("requests.get".to_string(), external("get"));
+        graph.insert_node("requests.Session".to_string(), external("Session"));
+        graph.insert_node(
+            "main".to_string(),
+            CallStackNode {
+                children: vec![
+                    child("requests.get", EdgeKind::Direct),
+                    child("requests.Session", EdgeKind::Constructor),
+                    child("requests.post", EdgeKind::Direct),
+                ],
+                ..node("app.py", "main")
+            },
+        );
+        graph.insert_node("requests.post".to_string(), external("post"));
+
+        let displayed = graph.displayed(&ExportOptions {
+            external: NodeDisplay::Collapse,
+            ..Default::default()
+        });
+        let children: Vec<_> = displayed.nodes["main"]
+            .children
+            .iter()
+            .map(|child| (child.node_key.as_str(), child.kind))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("requests", EdgeKind::Direct),
+                ("requests", EdgeKind::Constructor)
+            ]
+        );
+        let edges: Vec<_> = displayed
+            .edges()
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("main", "requests", EdgeKind::Direct),
+                ("main", "requests", EdgeKind::Constructor)
+            ]
+        );
     }
-}
\ No newline at end of file
+
+    /// `main` reaches `d` through `a` and `b`, and `a` and `c` call each other.
+    fn diamond() -> CallGraph {
+        graph(&[
+            ("main", "a"),
+            ("main", "b"),
+            ("a", "c"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "d"),
+        ])
+    }
+
+    #[test]
+    fn stacks_from_mark_cycles() {
+        assert_eq!(
+            diamond().stacks_from("main", 10, 10),
+            [
+                chain(&["main", "a", "c"], Some("a"), false),
+                chain(&["main", "a", "c", "d"], None, false),
+                chain(&["main", "b", "c", "a"], Some("c"), false),
+                chain(&["main", "b", "c", "d"], None, false),
+            ]
+        );
+        assert!(diamond().stacks_from("missing", 10, 10).is_empty());
+    }
+
+    #[test]
+    fn stacks_from_stop_at_max_depth_and_max_paths() {
+        assert_eq!(
+            diamond().stacks_from("main", 3, 10),
+            [
+                chain(&["main", "a", "c"], None, true),
+                chain(&["main", "b", "c"], None, true),
+            ]
+        );
+        assert_eq!(
+            diamond().stacks_from("main", 10, 2),
+            [
+                chain(&["main", "a", "c"], Some("a"), false),
+                chain(&["main", "a", "c", "d"], None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn stacks_follow_calls_of_several_kinds_once() {
+        let mut graph = graph(&[("main", "work")]);
+        graph.add_edge(
+            "main".to_string(),
+            "work".to_string(),
+            EdgeKind::Await,
+            Vec::new(),
+        );
+
+        assert_eq!(
+            graph.stacks_from("main", 10, 10),
+            [chain(&["main", "work"], None, false)]
+        );
+    }
+
+    #[test]
+    fn stacks_to_start_at_the_outermost_callers() {
+        assert_eq!(
+            diamond().stacks_to("d", 10, 10),
+            [
+                chain(&["main", "a", "c", "d"], None, false),
+                chain(&["main", "b", "c", "d"], None, false),
+            ]
+        );
+        assert_eq!(
+            diamond().stacks_to("d", 2, 10),
+            [chain(&["c", "d"], None, true)]
+        );
+
+        // Functions that only call each other start a chain cut at the cycle.
+        let recursive = graph(&[("f", "g"), ("g", "f"), ("g", "t")]);
+        assert_eq!(
+            recursive.stacks_to("t", 10, 10),
+            [chain(&["f", "g", "t"], Some("g"), false)]
+        );
+    }
+
+    #[test]
+    fn shortest_stack_takes_the_fewest_calls() {
+        let mut graph = diamond();
+        assert_eq!(
+            graph.shortest_stack("main", "d"),
+            Some(chain(&["main", "a", "c", "d"], None, false))
+        );
+        assert_eq!(graph.shortest_stack("d", "main"), None);
+        assert_eq!(
+            graph.shortest_stack("main", "main"),
+            Some(chain(&["main"], None, false))
+        );
+
+        graph.add_edge(
+            "b".to_string(),
+            "d".to_string(),
+            EdgeKind::Direct,
+            Vec::new(),
+        );
+        assert_eq!(
+            graph.shortest_stack("main", "d"),
+            Some(chain(&["main", "b", "d"], None, false))
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
+use crate::block::{CallLocation, EdgeKind, Resolution, SourceLocation};
 use crate::call_graph::CallGraph;
 
 /// Represents a call stack, which is a tree-like structure of function calls.
@@ -10,27 +11,68 @@ use crate::call_graph::CallGraph;
 /// associated metadata, such as the file path, class name (if applicable), and child nodes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallStack {
-    nodes: HashMap<String, CallStackNode>,
+    nodes: BTreeMap<String, CallStackNode>,
 }
 
 /// Represents a node in the call stack, which corresponds to a function call.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CallStackNode {
     /// The path of the file containing the function.
     pub file_path: String,
+    /// The location of the function in its source file.
+    pub location: SourceLocation,
     /// The name of the class containing the function, if applicable.
     pub class_name: Option<String>,
+    /// The name of the trait the function belongs to, if applicable.
+    pub trait_name: Option<String>,
     /// The name of the function.
     pub function_name: String,
-    /// The keys of the child nodes (i.e., functions called by this function).
-    pub children: Vec<String>,
+    /// The fully qualified path of the function within its file.
+    pub qualified_name: String,
+    /// The calls to the child nodes (i.e., functions called by this function).
+    pub children: Vec<ChildCall>,
+    /// Whether the node is a function of the tree, or stands in for an external or an
+    /// unresolved callee, in which case it has no location or children.
+    #[serde(default)]
+    pub resolution: Resolution,
+    /// Whether the node stands in for a callee that is not a function of the tree,
+    /// such as an external function or a class without a constructor, rather than
+    /// being one.
+    #[serde(default)]
+    pub stand_in: bool,
+}
+
+impl CallStackNode {
+    /// Returns whether the node stands in for a callee that is not a function of the
+    /// tree, rather than being one.
+    pub fn is_stand_in(&self) -> bool {
+        self.stand_in
+    }
+}
+
+/// Represents a call from a node in the call stack to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChildCall {
+    /// The key of the child node.
+    pub node_key: String,
+    /// How the node reaches the child.
+    pub kind: EdgeKind,
+    /// Every call of the child from the node, in source order.
+    #[serde(default)]
+    pub sites: Vec<CallLocation>,
+}
+
+impl Default for CallStack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CallStack {
     /// Creates a new, empty `CallStack`.
     pub fn new() -> Self {
         CallStack {
-            nodes: HashMap::new(),
+            nodes: BTreeMap::new(),
         }
     }
 
@@ -44,6 +86,20 @@ impl CallStack {
         self.nodes.insert(node_key, node);
     }
 
+    /// Removes a node from the call stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_key` - The unique key for the node.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the removed `CallStackNode`, or `None` if the node was
+    /// not found.
+    pub fn remove_node(&mut self, node_key: &str) -> Option<CallStackNode> {
+        self.nodes.remove(node_key)
+    }
+
     /// Retrieves a node from the call stack by its key.
     ///
     /// # Arguments
@@ -64,14 +120,29 @@ impl CallStack {
     ///
     /// * `parent_key` - The unique key for the parent node.
     /// * `child_key` - The unique key for the child node.
-    pub fn add_child(&mut self, parent_key: &str, child_key: &str) {
+    /// * `kind` - How the parent reaches the child.
+    /// * `sites` - The places the parent calls the child from.
+    pub fn add_child(
+        &mut self,
+        parent_key: &str,
+        child_key: &str,
+        kind: EdgeKind,
+        sites: Vec<CallLocation>,
+    ) {
         if let Some(parent_node) = self.nodes.get_mut(parent_key) {
-            parent_node.children.push(child_key.to_string());
+            parent_node.children.push(ChildCall {
+                node_key: child_key.to_string(),
+                kind,
+                sites,
+            });
         }
     }
 
     /// Converts the `CallStack` to a `CallGraph`.
     ///
+    /// Children listed more than once by the same node with the same kind of call are
+    /// merged into a single edge carrying the call sites of all of them.
+    ///
     /// # Returns
     ///
     /// A `CallGraph` representing the same information as the `CallStack`.
@@ -83,8 +154,13 @@ impl CallStack {
         }
 
         for (node_key, node) in &self.nodes {
-            for child_key in &node.children {
-                graph.add_edge(node_key.clone(), child_key.clone());
+            for child in &node.children {
+                graph.add_edge(
+                    node_key.clone(),
+                    child.node_key.clone(),
+                    child.kind,
+                    child.sites.clone(),
+                );
             }
         }
 
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::error::Error;
+use crate::indexer::Diagnostic;
+
+/// The file name of Cargo manifests.
+pub const MANIFEST: &str = "Cargo.toml";
+
+/// The crates every Rust crate can refer to without declaring a dependency.
+const BUILTIN_CRATES: [&str; 5] = ["std", "core", "alloc", "proc_macro", "test"];
+
+/// The tables of a manifest that declare dependencies.
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The Cargo packages of an indexed tree, read from their `Cargo.toml` manifests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    /// The paths of the manifests the packages were read from, sorted.
+    manifests: Vec<PathBuf>,
+    /// The packages, sorted by directory.
+    packages: Vec<Package>,
+}
+
+/// A Cargo package, as declared by its manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    /// The name of the package.
+    pub name: String,
+    /// The directory containing the manifest of the package.
+    pub dir: PathBuf,
+    /// The name of the library crate of the package, as used in paths.
+    pub lib_name: String,
+    /// The root file of the library crate of the package. The file may not exist if
+    /// the package only has binaries.
+    pub lib_path: PathBuf,
+    /// The dependencies of the package, as a map of the names they are used under in
+    /// paths to the names of their packages.
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// The crate a name refers to at the start of a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternCrate<'a> {
+    /// The library crate of a package of the indexed tree.
+    Package(&'a Package),
+    /// A crate that is not part of the indexed tree, such as `std` or a dependency
+    /// from a registry.
+    External,
+}
+
+impl Workspace {
+    /// Reads the packages declared by a set of manifests.
+    ///
+    /// Dependencies are keyed by the name they are declared under, so renamed
+    /// dependencies (`name = { package = "..." }`) map to the package they rename.
+    /// Dependencies inherited with `workspace = true` are looked up in the
+    /// `[workspace.dependencies]` of the workspace whose `members` include the
+    /// package.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifests` - The paths of the `Cargo.toml` files of the indexed tree.
+    /// * `diagnostics` - The diagnostics to report unreadable manifests to.
+    ///
+    /// # Returns
+    ///
+    /// The `Workspace` of the packages.
+    pub fn load(manifests: &[PathBuf], diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let mut tables = Vec::new();
+        for path in manifests {
+            let table = fs::read_to_string(path)
+                .map_err(|source| Error::Io {
+                    path: path.clone(),
+                    source,
+                })
+                .and_then(|text| {
+                    text.parse::<Table>().map_err(|source| Error::Manifest {
+                        path: path.clone(),
+                        source,
+                    })
+                });
+            match table {
+                Ok(table) => {
+                    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                    tables.push((dir, table));
+                }
+                Err(error) => diagnostics.push(Diagnostic {
+                    path: path.clone(),
+                    error,
+                }),
+            }
+        }
+
+        let workspaces: Vec<(&Path, &Table)> = tables
+            .iter()
+            .filter_map(|(dir, table)| Some((dir.as_path(), table.get("workspace")?.as_table()?)))
+            .collect();
+
+        let mut packages: Vec<Package> = tables
+            .iter()
+            .filter_map(|(dir, table)| {
+                let name = table.get("package")?.get("name")?.as_str()?;
+                let inherited = workspaces
+                    .iter()
+                    .find(|(root, workspace)| is_member(root, workspace, dir))
+                    .and_then(|(_, workspace)| workspace.get("dependencies")?.as_table());
+
+                let lib = table.get("lib");
+                let lib_name = lib
+                    .and_then(|lib| lib.get("name")?.as_str())
+                    .unwrap_or(name)
+                    .replace('-', "_");
+                let lib_path = dir.join(
+                    lib.and_then(|lib| lib.get("path")?.as_str())
+                        .unwrap_or("src/lib.rs"),
+                );
+
+                // Platform-specific dependencies are declared under `[target.<cfg>]`.
+                let targets = table
+                    .get("target")
+                    .and_then(Value::as_table)
+                    .into_iter()
+                    .flat_map(|targets| targets.values().filter_map(Value::as_table));
+                let dependencies = std::iter::once(table)
+                    .chain(targets)
+                    .flat_map(|table| {
+                        DEPENDENCY_TABLES
+                            .iter()
+                            .filter_map(move |key| table.get(*key)?.as_table())
+                    })
+                    .flatten()
+                    .map(|(key, spec)| (key.clone(), dependency_package(key, spec, inherited)))
+                    .collect();
+
+                Some(Package {
+                    name: name.to_string(),
+                    dir: dir.clone(),
+                    lib_name,
+                    lib_path,
+                    dependencies,
+                })
+            })
+            .collect();
+        packages.sort_by(|a, b| a.dir.cmp(&b.dir));
+
+        // A dependency that is not renamed is used under the library name of its package.
+        let lib_names: BTreeMap<String, String> = packages
+            .iter()
+            .map(|package| (package.name.clone(), package.lib_name.clone()))
+            .collect();
+        for package in &mut packages {
+            package.dependencies = std::mem::take(&mut package.dependencies)
+                .into_iter()
+                .map(|(key, dependency)| {
+                    let name = match lib_names.get(&dependency) {
+                        Some(lib_name) if key == dependency => lib_name.clone(),
+                        _ => key.replace('-', "_"),
+                    };
+                    (name, dependency)
+                })
+                .collect();
+        }
+
+        let mut manifests = manifests.to_vec();
+        manifests.sort();
+
+        Workspace {
+            manifests,
+            packages,
+        }
+    }
+
+    /// Returns the paths of the manifests the packages were read from, sorted.
+    pub fn manifests(&self) -> &[PathBuf] {
+        &self.manifests
+    }
+
+    /// Returns the packages, sorted by directory.
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    /// Returns the package with a given name.
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Returns the package a file belongs to, i.e. the one whose directory is the
+    /// nearest ancestor of the file.
+    pub fn package_of(&self, path: &Path) -> Option<&Package> {
+        self.packages
+            .iter()
+            .filter(|package| path.starts_with(&package.dir))
+            .max_by_key(|package| package.dir.components().count())
+    }
+
+    /// Returns the crate a name refers to at the start of a path in a package.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The package the path is written in, if any.
+    /// * `name` - The first segment of the path.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ExternCrate`, or `None` if the name is neither a
+    /// built-in crate, a dependency of the package, nor the package's own library.
+    pub fn extern_crate<'a>(
+        &'a self,
+        package: Option<&'a Package>,
+        name: &str,
+    ) -> Option<ExternCrate<'a>> {
+        if BUILTIN_CRATES.contains(&name) {
+            return Some(ExternCrate::External);
+        }
+
+        let package = package?;
+        match package.dependencies.get(name) {
+            Some(dependency) => Some(
+                self.package(dependency)
+                    .map_or(ExternCrate::External, ExternCrate::Package),
+            ),
+            // Binaries, tests and examples use the library of their own package.
+            None if package.lib_name == name => Some(ExternCrate::Package(package)),
+            None => None,
+        }
+    }
+}
+
+/// Returns the name of the package a dependency refers to.
+///
+/// # Arguments
+///
+/// * `key` - The name the dependency is declared under.
+/// * `spec` - The value of the dependency, a version string or a table.
+/// * `inherited` - The `[workspace.dependencies]` the dependency may be inherited from.
+fn dependency_package(key: &str, spec: &Value, inherited: Option<&Table>) -> String {
+    if let Some(package) = spec.get("package").and_then(Value::as_str) {
+        return package.to_string();
+    }
+
+    match (spec.get("workspace").and_then(Value::as_bool), inherited) {
+        (Some(true), Some(inherited)) => match inherited.get(key) {
+            Some(spec) => dependency_package(key, spec, None),
+            None => key.to_string(),
+        },
+        _ => key.to_string(),
+    }
+}
+
+/// Checks if a package directory is a member of a workspace.
+///
+/// # Arguments
+///
+/// * `root` - The directory of the workspace's manifest.
+/// * `workspace` - The `[workspace]` table of the manifest.
+/// * `dir` - The directory of the package.
+fn is_member(root: &Path, workspace: &Table, dir: &Path) -> bool {
+    let Ok(relative) = dir.strip_prefix(root) else {
+        return false;
+    };
+    if relative.as_os_str().is_empty() {
+        return true;
+    }
+
+    let matches_any = |key: &str| {
+        workspace
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .any(|pattern| matches_path(pattern, relative))
+    };
+
+    matches_any("members") && !matches_any("exclude")
+}
+
+/// Matches a relative path against a member pattern like `crates/*`.
+fn matches_path(pattern: &str, path: &Path) -> bool {
+    let pattern: Vec<&str> = Path::new(pattern)
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect();
+    let path: Vec<&str> = path
+        .components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect();
+
+    pattern.len() == path.len()
+        && pattern
+            .iter()
+            .zip(&path)
+            .all(|(pattern, name)| matches_glob(pattern.as_bytes(), name.as_bytes()))
+}
+
+/// Matches a file name against a glob supporting `*` and `?`.
+fn matches_glob(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            matches_glob(rest, name) || (!name.is_empty() && matches_glob(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => matches_glob(rest, name_rest),
+        (Some((expected, rest)), Some((actual, name_rest))) => {
+            expected == actual && matches_glob(rest, name_rest)
+        }
+        _ => false,
+    }
+}
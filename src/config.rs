@@ -1,43 +1,83 @@
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tree_sitter::Query;
+
+use crate::cache::content_hash;
+use crate::error::{Error, Result};
+use crate::language::{LanguageRegistry, LanguageSupport};
+use crate::query::TAGS_FILE_NAME;
 
 /// Represents the configuration for the asterisk library.
 ///
 /// The configuration is loaded from a TOML file and contains
 /// language-specific settings for parsing and indexing code.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 pub struct Config {
     /// A map of language names to their specific configurations.
+    #[serde(default)]
     pub languages: HashMap<String, Language>,
+    /// The number of threads used to parse files, or `None` to use one per CPU.
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// The path of the file that caches parsed files between runs, or `None` to
+    /// disable caching.
+    ///
+    /// Relative paths are resolved against the configuration directory.
+    #[serde(default)]
+    pub cache: Option<PathBuf>,
+    /// Which method calls fan out to the methods that override or implement the called
+    /// method in subtypes, as `Virtual` edges.
+    #[serde(default)]
+    pub dispatch: Dispatch,
+    /// The directory the configuration was loaded from, if it was loaded from a file.
+    ///
+    /// Query files in `<config_dir>/queries/<language>/tags.scm` override the
+    /// built-in queries.
+    #[serde(skip)]
+    pub config_dir: Option<PathBuf>,
+    /// The languages that can be indexed, looked up by file extension.
+    ///
+    /// Contains the built-in languages unless replaced.
+    #[serde(skip)]
+    pub registry: LanguageRegistry,
+    /// The compiled queries, keyed by language name.
+    #[serde(skip)]
+    queries: Mutex<HashMap<String, Arc<Query>>>,
 }
 
-/// Represents the configuration for a specific language.
-#[derive(Deserialize, Debug)]
-pub struct Language {
-    /// The matchers used to identify and extract information from AST nodes.
-    pub matchers: Matchers,
-}
-
-/// Represents the matchers used to identify and extract information from AST nodes.
-#[derive(Deserialize, Debug)]
-pub struct Matchers {
-    /// The name of the AST node type that represents an import statement.
-    pub import_statement: String,
-    /// The matcher for extracting the module name from an import statement.
-    pub module_name: Matcher,
-    /// The matcher for extracting the object name from an import statement.
-    pub object_name: Matcher,
-    /// The matcher for extracting the alias from an import statement.
-    pub alias: Matcher,
+/// Which method calls are expanded to every method they may dispatch to at runtime.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Dispatch {
+    /// Method calls only lead to the method of the receiver's type.
+    #[default]
+    Off,
+    /// Calls on receivers whose type is declared rather than known exactly, such as
+    /// parameters, trait objects, trait bounds and `self`, also lead to the overriding
+    /// methods and trait implementations of every subtype.
+    Conservative,
+    /// Every method call on a type of the tree is expanded, including calls on values
+    /// created with a constructor.
+    All,
 }
 
-/// Represents a matcher used to extract information from an AST node.
-#[derive(Deserialize, Debug)]
-pub struct Matcher {
-    /// The name of the field in the AST node that contains the desired information.
-    pub field_name: String,
-    /// The kind (type) of the AST node that contains the desired information.
-    pub kind: String,
+/// Represents the configuration for a specific language.
+#[derive(Deserialize, Debug, Default)]
+pub struct Language {
+    /// The path of a tree-sitter query file, or of a directory containing a `tags.scm`
+    /// file, that replaces the built-in query for the language.
+    ///
+    /// Relative paths are resolved against the configuration directory.
+    pub queries: Option<PathBuf>,
+    /// Additional directories imported modules are looked up in, like Python's
+    /// `sys.path`.
+    ///
+    /// Relative paths are resolved against the configuration directory.
+    #[serde(default)]
+    pub sys_path: Vec<PathBuf>,
 }
 
 impl Config {
@@ -50,10 +90,340 @@ impl Config {
     /// # Returns
     ///
     /// A `Result` containing the `Config` instance if the TOML was successfully parsed,
-    /// or an error if parsing failed.
-    pub fn from_toml(toml_str: &str) -> Result<Self, toml::de::Error> {
-        let configs: Config = toml::from_str(toml_str).expect("Failed to parse TOML");
+    /// or `Error::Config` if parsing failed.
+    pub fn from_toml(toml_str: &str) -> Result<Self> {
+        let configs: Config = toml::from_str(toml_str)?;
 
         Ok(configs)
     }
+
+    /// Loads a `Config` from a TOML file.
+    ///
+    /// Unlike `from_toml`, the directory of the file is remembered, so query files
+    /// placed next to the configuration are picked up.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the TOML configuration file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Config` instance, or an error if the file could not
+    /// be read or parsed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let toml_str = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut config = Config::from_toml(&toml_str)?;
+        config.config_dir = Some(
+            path.parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(".")),
+        );
+
+        Ok(config)
+    }
+
+    /// Registers a language, in addition to or in place of the built-in ones.
+    ///
+    /// A query already compiled for a language of the same name is dropped, since it
+    /// was compiled for the grammar being replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The `LanguageSupport` to register.
+    pub fn register_language(&mut self, language: impl LanguageSupport + 'static) {
+        self.queries.get_mut().unwrap().remove(language.name());
+        self.registry.register(language);
+    }
+
+    /// Returns the compiled tree-sitter query for a language.
+    ///
+    /// The query is read from the first of these that exists:
+    /// 1. The `queries` path configured for the language.
+    /// 2. `<config_dir>/queries/<language>/tags.scm`.
+    /// 3. The default query of the language.
+    ///
+    /// Queries are compiled once and cached until a language of the same name is
+    /// registered.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language to compile the query for.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the compiled query, or an error if no query exists for the
+    /// language or it could not be read or compiled.
+    pub fn query(&self, language: &dyn LanguageSupport) -> Result<Arc<Query>> {
+        let language_name = language.name();
+        let mut queries = self.queries.lock().unwrap();
+        if let Some(query) = queries.get(language_name) {
+            return Ok(Arc::clone(query));
+        }
+
+        let (source, path) = self.query_source(language)?;
+        let query = Query::new(language.language(), &source).map_err(|source| Error::Query {
+            language: language_name.to_string(),
+            path,
+            source,
+        })?;
+        let query = Arc::new(query);
+        queries.insert(language_name.to_string(), Arc::clone(&query));
+
+        Ok(query)
+    }
+
+    /// Returns the source of the query for a language and the path it was read from,
+    /// or `None` for the language's default query.
+    fn query_source(&self, language: &dyn LanguageSupport) -> Result<(String, Option<PathBuf>)> {
+        match self.query_path(language.name()) {
+            Some(path) => {
+                let source = fs::read_to_string(&path).map_err(|source| Error::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                Ok((source, Some(path)))
+            }
+            None => {
+                let source = language
+                    .default_query()
+                    .ok_or_else(|| Error::MissingQuery(language.name().to_string()))?;
+                Ok((source.to_string(), None))
+            }
+        }
+    }
+
+    /// Returns the path of the cache file, if caching is enabled.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the path of the cache file, resolved against the
+    /// configuration directory.
+    pub fn cache_path(&self) -> Option<PathBuf> {
+        let cache = self.cache.as_ref()?;
+        Some(match &self.config_dir {
+            Some(config_dir) => config_dir.join(cache),
+            None => cache.clone(),
+        })
+    }
+
+    /// Returns the additional directories imported modules of a language are looked up
+    /// in.
+    ///
+    /// # Arguments
+    ///
+    /// * `language_name` - The name of the language, e.g. `"python"`.
+    ///
+    /// # Returns
+    ///
+    /// The configured `sys_path` of the language, resolved against the configuration
+    /// directory.
+    pub fn sys_path(&self, language_name: &str) -> Vec<PathBuf> {
+        let base_dir = self.config_dir.clone().unwrap_or_default();
+
+        self.languages
+            .get(language_name)
+            .map(|language| {
+                language
+                    .sys_path
+                    .iter()
+                    .map(|path| base_dir.join(path))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Computes a fingerprint of everything in the configuration that affects how
+    /// files are parsed.
+    ///
+    /// The fingerprint covers the version of stackwalk, the dispatch mode, the
+    /// registered languages and their extensions, the source of the query used for each
+    /// of them and the directories their imports are looked up in. Cached results are
+    /// only reused while the fingerprint stays the same.
+    ///
+    /// # Returns
+    ///
+    /// The fingerprint of the configuration.
+    pub fn fingerprint(&self) -> u64 {
+        let mut languages: Vec<_> = self.registry.languages().collect();
+        languages.sort_by_key(|language| language.name());
+
+        let mut fingerprint = String::from(env!("CARGO_PKG_VERSION"));
+        fingerprint.push('\0');
+        fingerprint.push_str(&format!("{:?}", self.dispatch));
+        for language in languages {
+            let mut extensions = language.extensions().to_vec();
+            extensions.sort_unstable();
+            let query = self
+                .query_source(language)
+                .map(|(source, _)| source)
+                .unwrap_or_default();
+
+            fingerprint.push('\0');
+            fingerprint.push_str(language.name());
+            fingerprint.push('\0');
+            fingerprint.push_str(&extensions.join(","));
+            fingerprint.push('\0');
+            fingerprint.push_str(&query);
+            for path in self.sys_path(language.name()) {
+                fingerprint.push('\0');
+                fingerprint.push_str(&path.to_string_lossy());
+            }
+        }
+
+        content_hash(fingerprint.as_bytes())
+    }
+
+    /// Returns the path of the query file that overrides the built-in query for a
+    /// language, if there is one.
+    fn query_path(&self, language_name: &str) -> Option<PathBuf> {
+        let base_dir = self.config_dir.clone().unwrap_or_default();
+
+        let configured = self
+            .languages
+            .get(language_name)
+            .and_then(|language| language.queries.as_ref())
+            .map(|path| {
+                let path = base_dir.join(path);
+                if path.is_dir() {
+                    path.join(TAGS_FILE_NAME)
+                } else {
+                    path
+                }
+            });
+
+        configured.or_else(|| {
+            let path = self
+                .config_dir
+                .as_ref()?
+                .join("queries")
+                .join(language_name)
+                .join(TAGS_FILE_NAME);
+            path.is_file().then_some(path)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::fixture::Fixture;
+    use crate::language::{LanguageSupport, Python};
+    use std::path::Path;
+
+    /// A query that extracts Python functions and the calls they make.
+    const CALLS: &str = "(function_definition name: (identifier) @name) @definition.function
+(call function: (identifier) @name) @reference.call";
+
+    /// A query that extracts Python functions but none of their calls.
+    const FUNCTIONS: &str = "(function_definition name: (identifier) @name) @definition.function";
+
+    /// A language parsed with the Python grammar under a name, extensions and query
+    /// of its own.
+    struct Dialect {
+        name: &'static str,
+        extensions: &'static [&'static str],
+        query: &'static str,
+    }
+
+    impl LanguageSupport for Dialect {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn language(&self) -> tree_sitter::Language {
+            Python.language()
+        }
+
+        fn extensions(&self) -> &[&str] {
+            self.extensions
+        }
+
+        fn default_query(&self) -> Option<&str> {
+            Some(self.query)
+        }
+    }
+
+    const SOURCE: &str = "def helper():\n    pass\n\ndef main():\n    helper()\n";
+
+    #[test]
+    fn indexes_files_of_registered_languages() {
+        let fixture = Fixture::new(&[("main.pyish", SOURCE)]);
+        let mut config = Config::default();
+        config.register_language(Dialect {
+            name: "pyish",
+            extensions: &["pyish"],
+            query: CALLS,
+        });
+
+        let index = fixture.index_with(&config);
+        assert_eq!(
+            fixture.callees(&index, "main.pyish", "main"),
+            [fixture.key("main.pyish", "helper")]
+        );
+
+        config.register_language(Dialect {
+            name: "pyish-functions",
+            extensions: &["pyish"],
+            query: FUNCTIONS,
+        });
+
+        let language = config.registry.for_path(Path::new("main.pyish")).unwrap();
+        assert_eq!(language.name(), "pyish-functions");
+        let index = fixture.index_with(&config);
+        assert!(index
+            .call_stack
+            .get_node(&fixture.key("main.pyish", "main"))
+            .is_some_and(|node| !node.is_stand_in()));
+        assert!(fixture.callees(&index, "main.pyish", "main").is_empty());
+    }
+
+    #[test]
+    fn replacing_a_language_drops_its_compiled_query() {
+        let fixture = Fixture::new(&[("main.py", SOURCE)]);
+        let mut config = Config::default();
+        let index = fixture.index_with(&config);
+        assert_eq!(
+            fixture.callees(&index, "main.py", "main"),
+            [fixture.key("main.py", "helper")]
+        );
+
+        config.register_language(Dialect {
+            name: "python",
+            extensions: &["py"],
+            query: FUNCTIONS,
+        });
+
+        let index = fixture.index_with(&config);
+        assert!(index
+            .call_stack
+            .get_node(&fixture.key("main.py", "main"))
+            .is_some_and(|node| !node.is_stand_in()));
+        assert!(fixture.callees(&index, "main.py", "main").is_empty());
+    }
+
+    #[test]
+    fn query_files_in_the_config_directory_override_the_built_in_ones() {
+        let fixture = Fixture::new(&[
+            ("main.py", SOURCE),
+            ("stackwalk.toml", ""),
+            ("queries/python/tags.scm", FUNCTIONS),
+        ]);
+        assert_eq!(
+            fixture.callees(&fixture.index(), "main.py", "main"),
+            [fixture.key("main.py", "helper")]
+        );
+
+        let config = Config::from_file(fixture.root().join("stackwalk.toml")).unwrap();
+        let index = fixture.index_with(&config);
+        assert!(index
+            .call_stack
+            .get_node(&fixture.key("main.py", "main"))
+            .is_some_and(|node| !node.is_stand_in()));
+        assert!(fixture.callees(&index, "main.py", "main").is_empty());
+    }
 }
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::block::Position;
+
+/// A specialized `Result` type for stackwalk operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents the errors that can occur while loading configuration, parsing files
+/// or indexing directories.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or directory could not be read.
+    Io {
+        /// The path that could not be read.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// A file or directory could not be written.
+    Write {
+        /// The path that could not be written.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// A path is not valid UTF-8 and cannot be used as a node key.
+    NonUtf8Path(PathBuf),
+    /// A file has an extension that no grammar is registered for.
+    UnsupportedLanguage(PathBuf),
+    /// There is no tree-sitter query for a language that is being parsed.
+    MissingQuery(String),
+    /// A tree-sitter query could not be compiled.
+    Query {
+        /// The language the query was compiled for.
+        language: String,
+        /// The path of the query file, or `None` for a built-in query.
+        path: Option<PathBuf>,
+        /// The underlying query error.
+        source: tree_sitter::QueryError,
+    },
+    /// A tree-sitter grammar is incompatible with the linked tree-sitter library.
+    Language(tree_sitter::LanguageError),
+    /// Tree-sitter failed to produce a syntax tree for a file.
+    Parse(PathBuf),
+    /// A file has a syntax error. Tree-sitter recovers from it, so the rest of the
+    /// file is indexed all the same.
+    Syntax {
+        /// The path of the file.
+        path: PathBuf,
+        /// The position of the first syntax error in the file.
+        position: Position,
+    },
+    /// The TOML configuration could not be parsed.
+    Config(toml::de::Error),
+    /// A Cargo manifest could not be parsed.
+    Manifest {
+        /// The path of the manifest.
+        path: PathBuf,
+        /// The underlying TOML error.
+        source: toml::de::Error,
+    },
+    /// The directory walker failed to read an entry.
+    Walk(jwalk::Error),
+    /// The thread pool used for indexing could not be created.
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// The file system watcher failed to watch a directory or reported an error.
+    Watch(notify::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            Error::Write { path, source } => {
+                write!(f, "failed to write {}: {}", path.display(), source)
+            }
+            Error::NonUtf8Path(path) => write!(f, "path is not valid UTF-8: {}", path.display()),
+            Error::UnsupportedLanguage(path) => {
+                write!(f, "unsupported language: {}", path.display())
+            }
+            Error::MissingQuery(language) => write!(f, "no query for language `{}`", language),
+            Error::Query {
+                language,
+                path: Some(path),
+                source,
+            } => write!(
+                f,
+                "invalid {} query in {}: {}",
+                language,
+                path.display(),
+                source
+            ),
+            Error::Query {
+                language,
+                path: None,
+                source,
+            } => write!(f, "invalid built-in {} query: {}", language, source),
+            Error::Language(error) => write!(f, "failed to load grammar: {}", error),
+            Error::Parse(path) => write!(f, "failed to parse {}", path.display()),
+            Error::Syntax { path, position } => {
+                write!(f, "syntax error in {}:{}", path.display(), position)
+            }
+            Error::Config(error) => write!(f, "failed to parse configuration: {}", error),
+            Error::Manifest { path, source } => {
+                write!(f, "failed to parse manifest {}: {}", path.display(), source)
+            }
+            Error::Walk(error) => write!(f, "failed to walk directory: {}", error),
+            Error::ThreadPool(error) => write!(f, "failed to create thread pool: {}", error),
+            Error::Watch(error) => write!(f, "failed to watch directory: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Write { source, .. } => Some(source),
+            Error::Query { source, .. } => Some(source),
+            Error::Language(error) => Some(error),
+            Error::Config(error) => Some(error),
+            Error::Manifest { source, .. } => Some(source),
+            Error::Walk(error) => Some(error),
+            Error::ThreadPool(error) => Some(error),
+            Error::Watch(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<tree_sitter::LanguageError> for Error {
+    fn from(error: tree_sitter::LanguageError) -> Self {
+        Error::Language(error)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::Config(error)
+    }
+}
+
+impl From<jwalk::Error> for Error {
+    fn from(error: jwalk::Error) -> Self {
+        Error::Walk(error)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for Error {
+    fn from(error: rayon::ThreadPoolBuildError) -> Self {
+        Error::ThreadPool(error)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(error: notify::Error) -> Self {
+        Error::Watch(error)
+    }
+}
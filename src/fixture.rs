@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::Config;
+use crate::indexer::{index_directory, Index};
+
+/// The number of fixtures created by the current test run, used to keep their
+/// directories apart.
+static FIXTURES: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of source files written for a test, removed when dropped.
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    /// Writes a set of files to a new temporary directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The paths of the files, relative to the directory, and their
+    ///   contents.
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "stackwalk-{}-{}",
+            std::process::id(),
+            FIXTURES.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::create_dir_all(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        Fixture { root }
+    }
+
+    /// Returns the directory of the fixture.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Indexes the fixture with the default configuration.
+    pub fn index(&self) -> Index {
+        self.index_with(&Config::default())
+    }
+
+    /// Indexes the fixture with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration to index the fixture with.
+    pub fn index_with(&self, config: &Config) -> Index {
+        index_directory(config, self.root.to_str().unwrap()).unwrap()
+    }
+
+    /// Returns the node key of a function of the fixture.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file defining the function, relative to the
+    ///   directory.
+    /// * `name` - The qualified name of the function, with `.` between its segments.
+    pub fn key(&self, path: &str, name: &str) -> String {
+        format!("{}.{}", self.root.join(path).display(), name)
+    }
+
+    /// Returns the keys of the callees of a function of the fixture, in the order it
+    /// first calls them.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the fixture.
+    /// * `path` - The path of the file defining the function, relative to the
+    ///   directory.
+    /// * `name` - The qualified name of the function.
+    pub fn callees(&self, index: &Index, path: &str, name: &str) -> Vec<String> {
+        let node_key = self.key(path, name);
+        let node = index
+            .call_stack
+            .get_node(&node_key)
+            .unwrap_or_else(|| panic!("no node {}", node_key));
+        node.children
+            .iter()
+            .map(|child| child.node_key.clone())
+            .collect()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,487 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::block::{Resolution, UnresolvedReason};
+use crate::query::ClassKind;
+use crate::type_graph::{TypeEdgeKind, TypeGraph, TypeNode};
+
+/// Represents a class of the indexed tree and the classes it extends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassNode {
+    /// The path of the file defining the class.
+    pub file_path: String,
+    /// The name of the class.
+    pub name: String,
+    /// The kind of type the class is.
+    #[serde(default)]
+    pub kind: ClassKind,
+    /// The keys of the classes the class extends, in declaration order. Bases that
+    /// could not be resolved keep the name they are written with.
+    pub bases: Vec<String>,
+    /// The keys of the traits or interfaces the class implements, in declaration
+    /// order, resolved like `bases`.
+    #[serde(default)]
+    pub implements: Vec<String>,
+    /// What the bases and implemented types that are not classes of the indexed tree
+    /// resolved to, keyed like `bases` and `implements`: the module outside the tree
+    /// they come from, or why they could not be found.
+    #[serde(default)]
+    pub stand_ins: BTreeMap<String, Resolution>,
+    /// The names of the methods defined directly in the class, in source order, with
+    /// the methods of an `impl Trait for Type` block preceded by `<Trait>.`.
+    pub methods: Vec<String>,
+}
+
+/// The inheritance relationships between the classes of an indexed tree.
+///
+/// Classes are keyed like the nodes of the call graph, so the node key of a method is
+/// the key of its class followed by `.` and the name of the method, as listed in
+/// `ClassNode::methods`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassHierarchy {
+    /// The classes, keyed by class key.
+    classes: BTreeMap<String, ClassNode>,
+}
+
+impl ClassHierarchy {
+    /// Creates a new, empty `ClassHierarchy`.
+    pub fn new() -> Self {
+        ClassHierarchy {
+            classes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a class to the hierarchy, replacing any class with the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class.
+    /// * `class` - The `ClassNode` to add.
+    pub fn add_class(&mut self, class_key: String, class: ClassNode) {
+        self.classes.insert(class_key, class);
+    }
+
+    /// Retrieves a class by its key.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a reference to the `ClassNode`, or `None` if the class
+    /// is not part of the indexed tree.
+    pub fn class(&self, class_key: &str) -> Option<&ClassNode> {
+        self.classes.get(class_key)
+    }
+
+    /// Returns the classes of the hierarchy, keyed by class key.
+    pub fn classes(&self) -> &BTreeMap<String, ClassNode> {
+        &self.classes
+    }
+
+    /// Returns the keys of the classes a class directly extends, in declaration order.
+    pub fn bases(&self, class_key: &str) -> &[String] {
+        self.classes
+            .get(class_key)
+            .map(|class| class.bases.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns what a type extended or implemented by the classes of the hierarchy
+    /// resolved to.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the type.
+    ///
+    /// # Returns
+    ///
+    /// `Resolution::Resolved` for the classes of the hierarchy, and what the type
+    /// resolved to where it is extended or implemented otherwise.
+    pub fn resolution(&self, type_key: &str) -> Resolution {
+        if self.classes.contains_key(type_key) {
+            return Resolution::Resolved;
+        }
+        self.classes
+            .values()
+            .find_map(|class| class.stand_ins.get(type_key))
+            .cloned()
+            .unwrap_or(Resolution::Unresolved {
+                reason: UnresolvedReason::UndefinedName,
+            })
+    }
+
+    /// Returns the keys of the classes that directly extend a class, sorted.
+    pub fn subclasses(&self, class_key: &str) -> Vec<&str> {
+        self.classes
+            .iter()
+            .filter(|(_, class)| class.bases.iter().any(|base| base == class_key))
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// Returns the keys of the classes that extend or implement a class, directly or
+    /// through other classes, sorted.
+    pub fn descendants(&self, class_key: &str) -> Vec<&str> {
+        let mut descendants = BTreeSet::new();
+        let mut stack = vec![class_key];
+
+        while let Some(ancestor) = stack.pop() {
+            for (key, class) in &self.classes {
+                let mut supertypes = class.bases.iter().chain(&class.implements);
+                if supertypes.any(|supertype| supertype == ancestor)
+                    && key != class_key
+                    && descendants.insert(key.as_str())
+                {
+                    stack.push(key);
+                }
+            }
+        }
+
+        descendants.into_iter().collect()
+    }
+
+    /// Finds the methods a call of a method on a class may dispatch to at runtime,
+    /// other than the one it resolves to statically: the methods that override or
+    /// implement it in the classes extending or implementing the class.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class the method is called on.
+    /// * `method` - The name of the method.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the classes defining the methods, sorted.
+    pub fn find_overrides(&self, class_key: &str, method: &str) -> Vec<String> {
+        let defining = self.find_method(class_key, method);
+        let overrides: BTreeSet<String> = self
+            .descendants(class_key)
+            .into_iter()
+            .filter_map(|descendant| self.find_method(descendant, method))
+            .filter(|overriding| Some(overriding) != defining.as_ref())
+            .collect();
+        overrides.into_iter().collect()
+    }
+
+    /// Converts the `ClassHierarchy` to a `TypeGraph`.
+    ///
+    /// Modules and objects that only group functions are left out, as they are not
+    /// types. Types that are extended or implemented but not part of the hierarchy
+    /// become stand-in nodes, external ones for the classes of other libraries and
+    /// unresolved ones for the types that could not be found.
+    ///
+    /// # Returns
+    ///
+    /// The `TypeGraph` of the classes, with an edge from every class to each type it
+    /// extends or implements.
+    pub fn to_type_graph(&self) -> TypeGraph {
+        let mut type_graph = TypeGraph::new();
+        let types = self
+            .classes
+            .iter()
+            .filter(|(_, class)| class.kind != ClassKind::Module);
+
+        for (class_key, class) in types {
+            type_graph.add_node(
+                class_key.clone(),
+                TypeNode {
+                    file_path: class.file_path.clone(),
+                    name: class.name.clone(),
+                    kind: class.kind,
+                    resolution: Resolution::Resolved,
+                },
+            );
+
+            let supertypes = class
+                .bases
+                .iter()
+                .map(|base| (base, TypeEdgeKind::Extends))
+                .chain(
+                    class
+                        .implements
+                        .iter()
+                        .map(|implemented| (implemented, TypeEdgeKind::Implements)),
+                );
+            for (supertype, kind) in supertypes {
+                if !self.classes.contains_key(supertype) {
+                    // A type is extended by types of its own kind, and only traits and
+                    // interfaces are implemented.
+                    let supertype_kind = match (kind, class.kind) {
+                        (TypeEdgeKind::Extends, kind) => kind,
+                        (TypeEdgeKind::Implements, ClassKind::Type) => ClassKind::Trait,
+                        (TypeEdgeKind::Implements, _) => ClassKind::Interface,
+                    };
+                    let resolution = self.resolution(supertype);
+                    type_graph.add_node(
+                        supertype.clone(),
+                        TypeNode::stand_in(supertype, supertype_kind, resolution),
+                    );
+                }
+                type_graph.add_edge(class_key.clone(), supertype.clone(), kind);
+            }
+        }
+
+        type_graph
+    }
+
+    /// Computes the method resolution order of a class.
+    ///
+    /// The order is the C3 linearization Python uses, which for single inheritance, as
+    /// in JavaScript, is simply the chain of `extends`. Hierarchies C3 cannot linearize
+    /// fall back to a depth-first, left-to-right order.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the class and all of its ancestors, starting with the class itself.
+    pub fn mro(&self, class_key: &str) -> Vec<String> {
+        self.linearize(class_key, &mut Vec::new())
+    }
+
+    /// Finds the class that defines a method, looking at the class first and then at
+    /// its ancestors in method resolution order. A method none of them defines may be
+    /// the default method of a trait or interface they implement, like a Rust trait's
+    /// provided method, so the implemented traits are looked at last.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class the method is called on.
+    /// * `method` - The name of the method.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the key of the defining class, or `None` if neither the
+    /// class nor any of its indexed ancestors defines the method.
+    pub fn find_method(&self, class_key: &str, method: &str) -> Option<String> {
+        let mro = self.mro(class_key);
+        if let Some(defining) = self.find_in(mro.clone(), method) {
+            return Some(defining);
+        }
+
+        let mut traits: Vec<String> = Vec::new();
+        for class in mro
+            .iter()
+            .filter_map(|class_key| self.classes.get(class_key))
+        {
+            for implemented in &class.implements {
+                for supertrait in self.mro(implemented) {
+                    if !traits.contains(&supertrait) {
+                        traits.push(supertrait);
+                    }
+                }
+            }
+        }
+        self.find_in(traits, method)
+    }
+
+    /// Finds the class that defines a method called through `super`, i.e. the first
+    /// ancestor of a class in method resolution order that defines it.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class the call is made in.
+    /// * `method` - The name of the method.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the key of the defining class, or `None` if none of the
+    /// indexed ancestors of the class defines the method.
+    pub fn find_super_method(&self, class_key: &str, method: &str) -> Option<String> {
+        self.find_in(self.mro(class_key).into_iter().skip(1).collect(), method)
+    }
+
+    /// Returns the node key of a method defined directly in a class.
+    ///
+    /// A method of the class itself is preferred over the methods of the traits it
+    /// implements, like Rust does; among those, the first one defined is taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_key` - The key of the class.
+    /// * `method` - The name of the method.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the node key of the method, or `None` if the class does
+    /// not define it.
+    pub fn method_key(&self, class_key: &str, method: &str) -> Option<String> {
+        let methods = &self.classes.get(class_key)?.methods;
+        let path = methods
+            .iter()
+            .find(|path| *path == method)
+            .or_else(|| methods.iter().find(|path| method_name(path) == method))?;
+        Some(format!("{}.{}", class_key, path))
+    }
+
+    /// Returns the first class of an MRO that defines a method.
+    fn find_in(&self, mro: Vec<String>, method: &str) -> Option<String> {
+        mro.into_iter().find(|class_key| {
+            self.classes
+                .get(class_key)
+                .is_some_and(|class| class.methods.iter().any(|path| method_name(path) == method))
+        })
+    }
+
+    /// Computes the C3 linearization of a class, cutting inheritance cycles at the
+    /// classes in `visiting`.
+    fn linearize(&self, class_key: &str, visiting: &mut Vec<String>) -> Vec<String> {
+        if visiting.iter().any(|visited| visited == class_key) {
+            return vec![class_key.to_string()];
+        }
+
+        let bases = self.bases(class_key);
+        visiting.push(class_key.to_string());
+        let mut sequences: Vec<Vec<String>> = bases
+            .iter()
+            .map(|base| self.linearize(base, visiting))
+            .collect();
+        sequences.push(bases.to_vec());
+        visiting.pop();
+
+        let mut mro = vec![class_key.to_string()];
+        loop {
+            sequences.retain(|sequence| !sequence.is_empty());
+            if sequences.is_empty() {
+                return mro;
+            }
+
+            // The next class is the first head that does not come after a class that
+            // is still to be placed.
+            let head = sequences
+                .iter()
+                .map(|sequence| &sequence[0])
+                .find(|head| {
+                    !sequences
+                        .iter()
+                        .any(|sequence| sequence[1..].contains(head))
+                })
+                .cloned();
+            let Some(head) = head else {
+                for class_key in sequences.into_iter().flatten() {
+                    if !mro.contains(&class_key) {
+                        mro.push(class_key);
+                    }
+                }
+                return mro;
+            };
+
+            for sequence in &mut sequences {
+                if sequence[0] == head {
+                    sequence.remove(0);
+                }
+            }
+            // A class reached again through an inheritance cycle is only placed once.
+            if !mro.contains(&head) {
+                mro.push(head);
+            }
+        }
+    }
+}
+
+/// Returns the name of a method listed in `ClassNode::methods`, without the trait it
+/// implements.
+fn method_name(path: &str) -> &str {
+    path.rsplit('.').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a hierarchy of classes, each given by its key, its bases and the names of
+    /// its methods.
+    fn hierarchy(classes: &[(&str, &[&str], &[&str])]) -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::new();
+        for (key, bases, methods) in classes {
+            hierarchy.add_class(
+                key.to_string(),
+                ClassNode {
+                    file_path: "classes.py".to_string(),
+                    name: key.to_string(),
+                    kind: ClassKind::Class,
+                    bases: bases.iter().map(|base| base.to_string()).collect(),
+                    implements: Vec::new(),
+                    stand_ins: BTreeMap::new(),
+                    methods: methods.iter().map(|method| method.to_string()).collect(),
+                },
+            );
+        }
+        hierarchy
+    }
+
+    #[test]
+    fn mro_linearizes_a_diamond() {
+        let hierarchy = hierarchy(&[
+            ("A", &[], &["greet"]),
+            ("B", &["A"], &[]),
+            ("C", &["A"], &["greet"]),
+            ("D", &["B", "C"], &[]),
+        ]);
+
+        assert_eq!(hierarchy.mro("D"), ["D", "B", "C", "A"]);
+        // C overrides the method before A is reached.
+        assert_eq!(hierarchy.find_method("D", "greet").as_deref(), Some("C"));
+        assert_eq!(
+            hierarchy.find_super_method("C", "greet").as_deref(),
+            Some("A")
+        );
+        assert_eq!(hierarchy.find_super_method("A", "greet"), None);
+    }
+
+    #[test]
+    fn mro_follows_c3_across_several_levels() {
+        // The example of the C3 paper, as linearized by Python.
+        let hierarchy = hierarchy(&[
+            ("O", &[], &[]),
+            ("A", &["O"], &[]),
+            ("B", &["O"], &[]),
+            ("C", &["O"], &[]),
+            ("D", &["O"], &[]),
+            ("E", &["O"], &[]),
+            ("K1", &["A", "B", "C"], &[]),
+            ("K2", &["D", "B", "E"], &[]),
+            ("K3", &["D", "A"], &[]),
+            ("Z", &["K1", "K2", "K3"], &[]),
+        ]);
+
+        assert_eq!(
+            hierarchy.mro("Z"),
+            ["Z", "K1", "K2", "K3", "D", "A", "B", "C", "E", "O"]
+        );
+    }
+
+    #[test]
+    fn mro_falls_back_when_c3_fails() {
+        // X and Y order their bases in opposite ways, which C3 rejects.
+        let hierarchy = hierarchy(&[
+            ("A", &[], &[]),
+            ("B", &[], &[]),
+            ("X", &["A", "B"], &[]),
+            ("Y", &["B", "A"], &[]),
+            ("Z", &["X", "Y"], &[]),
+        ]);
+
+        assert_eq!(hierarchy.mro("Z"), ["Z", "X", "Y", "A", "B"]);
+    }
+
+    #[test]
+    fn mro_cuts_inheritance_cycles() {
+        let hierarchy = hierarchy(&[("A", &["B"], &[]), ("B", &["A"], &["run"])]);
+
+        assert_eq!(hierarchy.mro("A"), ["A", "B"]);
+        assert_eq!(hierarchy.mro("B"), ["B", "A"]);
+        assert_eq!(hierarchy.find_method("A", "run").as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn mro_keeps_bases_outside_the_hierarchy() {
+        let hierarchy = hierarchy(&[("A", &["object"], &[])]);
+
+        assert_eq!(hierarchy.mro("A"), ["A", "object"]);
+    }
+}
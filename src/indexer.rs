@@ -1,28 +1,699 @@
 use crate::config::Config;
-use jwalk::WalkDir;
-use std::path::Path;
+use jwalk::{Parallelism, WalkDir};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
 
-use crate::block::{Block, BlockType};
+use crate::block::{Block, BlockType, EdgeKind, Resolution, SourceLocation};
+use crate::cache::{content_hash, Cache, CachedFile};
 use crate::call_graph::CallGraph;
-use crate::call_stack::{CallStack, CallStackNode};
-use crate::parser::parse_file;
-use crate::utils::get_supported_extensions;
+use crate::call_stack::{CallStack, CallStackNode, ChildCall};
+use crate::cargo::{Workspace, MANIFEST};
+use crate::error::{Error, Result};
+use crate::hierarchy::ClassHierarchy;
+use crate::parser::{parse_code, ParsedFile};
+use crate::resolver::{resolve_calls, ResolveContext, ResolvedCalls};
+use crate::type_graph::TypeGraph;
+use crate::watch::Watch;
 
-/// Checks if a file is supported by the indexer based on its extension.
+/// The result of indexing a directory.
+#[derive(Debug)]
+pub struct Index {
+    /// The indexed code blocks.
+    pub blocks: Vec<Block>,
+    /// The hierarchy of function calls.
+    pub call_stack: CallStack,
+    /// The relationships between functions.
+    pub call_graph: CallGraph,
+    /// The classes of the indexed tree and the classes they extend.
+    pub class_hierarchy: ClassHierarchy,
+    /// The relationships between types.
+    pub type_graph: TypeGraph,
+    /// The files that could not be indexed, or only in part, and why.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Represents a file that was skipped, or only indexed in part, while indexing a
+/// directory.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// The path of the file that could not be indexed.
+    pub path: PathBuf,
+    /// The error that occurred while indexing the file.
+    pub error: Error,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            // These errors do not name the file they occurred in.
+            Error::MissingQuery(_)
+            | Error::Query { .. }
+            | Error::Language(_)
+            | Error::Config(_)
+            | Error::Watch(_) => {
+                write!(f, "{}: {}", self.path.display(), self.error)
+            }
+            _ => write!(f, "{}", self.error),
+        }
+    }
+}
+
+/// Represents a change made to the call graph by an incremental update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeEvent {
+    /// A function was added.
+    NodeAdded(String),
+    /// A function was removed.
+    NodeRemoved(String),
+    /// The code or location of a function changed, or a node changed between a
+    /// function and a stand-in for an external or unresolved callee under the same key.
+    NodeModified(String),
+    /// A function started calling another one.
+    EdgeAdded {
+        /// The key of the caller node.
+        from: String,
+        /// The key of the callee node.
+        to: String,
+        /// How the caller reaches the callee.
+        kind: EdgeKind,
+    },
+    /// A function stopped calling another one.
+    EdgeRemoved {
+        /// The key of the caller node.
+        from: String,
+        /// The key of the callee node.
+        to: String,
+        /// How the caller reaches the callee.
+        kind: EdgeKind,
+    },
+}
+
+/// A file of the indexed tree, either parsed anew or reused from a previous run.
+struct LoadedFile {
+    /// The hash of the contents of the file.
+    hash: u64,
+    /// The code structure extracted from the file.
+    parsed_file: ParsedFile,
+    /// The calls of the file as resolved by a previous run, if the file did not change.
+    cached_calls: Option<ResolvedCalls>,
+}
+
+impl LoadedFile {
+    /// Returns the module name of the file.
+    fn module_name(&self) -> &str {
+        &self.parsed_file.module_name
+    }
+}
+
+/// Reads a file and parses it, unless it did not change since a previous run.
+///
+/// # Arguments
+///
+/// * `module_name` - The module name, and path, of the file.
+/// * `config` - The `Config` instance containing language-specific settings.
+/// * `previous` - The file as indexed by a previous run, if any.
+///
+/// # Returns
+///
+/// A `Result` containing the `LoadedFile`, or an error if the file could not be read
+/// or parsed.
+fn load_file(
+    module_name: &str,
+    config: &Config,
+    previous: Option<&CachedFile>,
+) -> Result<LoadedFile> {
+    let path = Path::new(module_name);
+    let code = fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let hash = content_hash(code.as_bytes());
+
+    if let Some(previous) = previous.filter(|previous| previous.hash == hash) {
+        return Ok(LoadedFile {
+            hash,
+            parsed_file: previous.parsed_file.clone(),
+            cached_calls: Some(previous.resolved_calls.clone()),
+        });
+    }
+
+    Ok(LoadedFile {
+        hash,
+        parsed_file: parse_code(&code, path, module_name, config)?,
+        cached_calls: None,
+    })
+}
+
+/// Creates the thread pool used to walk and parse files.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing the number of threads.
+///
+/// # Returns
+///
+/// A `Result` containing the thread pool, or an error if it could not be created.
+fn thread_pool(config: &Config) -> Result<Arc<ThreadPool>> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.threads.unwrap_or(0))
+        .build()?;
+
+    Ok(Arc::new(pool))
+}
+
+/// Walks a directory and returns the files in it that a registered language handles,
+/// and the Cargo manifests in it.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing the language registry.
+/// * `dir_path` - The path of the directory to walk.
+/// * `pool` - The thread pool to walk the directory on.
+/// * `diagnostics` - The diagnostics to report unreadable entries to.
+///
+/// # Returns
+///
+/// The module names of the files and the paths of the manifests, both sorted.
+fn walk_directory(
+    config: &Config,
+    dir_path: &Path,
+    pool: &Arc<ThreadPool>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (Vec<String>, Vec<PathBuf>) {
+    let mut files = Vec::new();
+    let mut manifests = Vec::new();
+
+    let walker = WalkDir::new(dir_path)
+        .sort(true)
+        .parallelism(Parallelism::RayonExistingPool {
+            pool: Arc::clone(pool),
+            busy_timeout: None,
+        });
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                diagnostics.push(Diagnostic {
+                    path: error
+                        .path()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| dir_path.to_path_buf()),
+                    error: Error::from(error),
+                });
+                continue;
+            }
+        };
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+        if path.file_name().is_some_and(|name| name == MANIFEST) {
+            manifests.push(path);
+            continue;
+        }
+        if config.registry.for_path(&path).is_none() {
+            continue;
+        }
+
+        match path.to_str() {
+            Some(module_name) => files.push(module_name.to_string()),
+            None => diagnostics.push(Diagnostic {
+                path: path.clone(),
+                error: Error::NonUtf8Path(path),
+            }),
+        }
+    }
+
+    files.sort();
+    manifests.sort();
+    (files, manifests)
+}
+
+/// Loads files in parallel, reusing the previous run's results for unchanged files.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing language-specific settings.
+/// * `pool` - The thread pool to parse the files on.
+/// * `module_names` - The module names of the files to load.
+/// * `previous` - The files as indexed by a previous run.
+/// * `diagnostics` - The diagnostics to report files that could not be loaded to.
+///
+/// # Returns
+///
+/// The `LoadedFile`s, in the order of `module_names`.
+fn load_files(
+    config: &Config,
+    pool: &ThreadPool,
+    module_names: Vec<String>,
+    previous: &BTreeMap<String, CachedFile>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<LoadedFile> {
+    let results: Vec<_> = pool.install(|| {
+        module_names
+            .into_par_iter()
+            .map(|module_name| {
+                let result = load_file(&module_name, config, previous.get(&module_name));
+                (module_name, result)
+            })
+            .collect()
+    });
+
+    let mut loaded_files = Vec::new();
+    for (module_name, result) in results {
+        match result {
+            Ok(loaded_file) => {
+                // A file with syntax errors is indexed all the same.
+                if let Some(position) = loaded_file.parsed_file.syntax_error {
+                    diagnostics.push(Diagnostic {
+                        path: PathBuf::from(&module_name),
+                        error: Error::Syntax {
+                            path: PathBuf::from(&module_name),
+                            position,
+                        },
+                    });
+                }
+                loaded_files.push(loaded_file);
+            }
+            Err(error) => diagnostics.push(Diagnostic {
+                path: PathBuf::from(module_name),
+                error,
+            }),
+        }
+    }
+
+    loaded_files
+}
+
+/// Resolves the calls of loaded files in parallel.
+///
+/// The calls resolved by a previous run are reused, unless a module the file depends
+/// on changed since, or every file has to be resolved again, e.g. because modules
+/// were added that imports may now resolve to.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing the language registry.
+/// * `workspace` - The Cargo packages of the indexed tree.
+/// * `pool` - The thread pool to resolve the calls on.
+/// * `loaded_files` - The files to resolve.
+/// * `other_files` - The rest of the indexed tree, which imports may resolve to.
+/// * `changed` - The module names of the files that were added, removed or modified.
+/// * `resolve_all` - Whether the calls of every file have to be resolved again.
+/// * `diagnostics` - The diagnostics to report files that could not be resolved to.
+///
+/// # Returns
+///
+/// The resolved files, in the order of `loaded_files`.
+#[allow(clippy::too_many_arguments)]
+fn resolve_files(
+    config: &Config,
+    workspace: &Workspace,
+    pool: &ThreadPool,
+    loaded_files: Vec<LoadedFile>,
+    other_files: &[&ParsedFile],
+    changed: &HashSet<String>,
+    resolve_all: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<CachedFile> {
+    let context = ResolveContext::new(
+        config,
+        workspace,
+        loaded_files
+            .iter()
+            .map(|loaded_file| &loaded_file.parsed_file)
+            .chain(other_files.iter().copied()),
+    );
+
+    let results: Vec<_> = pool.install(|| {
+        loaded_files
+            .par_iter()
+            .map(|loaded_file| {
+                let reusable = loaded_file
+                    .cached_calls
+                    .as_ref()
+                    .is_some_and(|cached_calls| {
+                        !resolve_all
+                            && !cached_calls
+                                .dependencies
+                                .iter()
+                                .any(|dependency| changed.contains(dependency))
+                    });
+                if reusable {
+                    return Ok(None);
+                }
+
+                let parsed_file = &loaded_file.parsed_file;
+                match config.registry.get(&parsed_file.language) {
+                    Some(language) => Ok(Some(resolve_calls(parsed_file, language, &context))),
+                    None => Err(PathBuf::from(&parsed_file.module_name)),
+                }
+            })
+            .collect()
+    });
+
+    let mut resolved_files = Vec::new();
+    for (loaded_file, result) in loaded_files.into_iter().zip(results) {
+        let resolved_calls = match result {
+            Ok(Some(resolved_calls)) => resolved_calls,
+            Ok(None) => loaded_file.cached_calls.unwrap_or_default(),
+            Err(path) => {
+                diagnostics.push(Diagnostic {
+                    path: path.clone(),
+                    error: Error::UnsupportedLanguage(path),
+                });
+                continue;
+            }
+        };
+
+        resolved_files.push(CachedFile {
+            hash: loaded_file.hash,
+            parsed_file: loaded_file.parsed_file,
+            resolved_calls,
+        });
+    }
+
+    resolved_files
+}
+
+/// Parses and resolves every file of a directory, using and updating the cache
+/// configured in `config`.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing language-specific settings.
+/// * `dir_path` - The path of the directory to index.
+/// * `pool` - The thread pool to index the directory on.
+///
+/// # Returns
+///
+/// A `Result` containing the indexed files keyed by module name, the Cargo packages
+/// of the directory, and the diagnostics of the files that could not be indexed, or an
+/// error if the directory itself could not be read.
+fn index_files(
+    config: &Config,
+    dir_path: &Path,
+    pool: &Arc<ThreadPool>,
+) -> Result<(BTreeMap<String, CachedFile>, Workspace, Vec<Diagnostic>)> {
+    fs::read_dir(dir_path).map_err(|source| Error::Io {
+        path: dir_path.to_path_buf(),
+        source,
+    })?;
+
+    let mut diagnostics = Vec::new();
+    let (module_names, manifests) = walk_directory(config, dir_path, pool, &mut diagnostics);
+    let workspace = Workspace::load(&manifests, &mut diagnostics);
+
+    let cache_path = config.cache_path();
+    let previous_cache = match &cache_path {
+        Some(cache_path) => Cache::load(cache_path, config.fingerprint()),
+        None => Cache::default(),
+    };
+
+    let loaded_files = load_files(
+        config,
+        pool,
+        module_names,
+        previous_cache.files(),
+        &mut diagnostics,
+    );
+
+    // Every module that was added, removed or modified since the cache was written.
+    let unchanged: HashSet<&str> = loaded_files
+        .iter()
+        .filter(|loaded_file| loaded_file.cached_calls.is_some())
+        .map(LoadedFile::module_name)
+        .collect();
+    let mut changed: HashSet<String> = loaded_files
+        .iter()
+        .map(LoadedFile::module_name)
+        .chain(previous_cache.files().keys().map(String::as_str))
+        .filter(|module_name| !unchanged.contains(module_name))
+        .map(str::to_string)
+        .collect();
+    // So has every file outside the tree, like a configuration file, that imports
+    // were resolved through.
+    changed.extend(previous_cache.changed_dependencies());
+
+    let added = loaded_files.iter().any(|loaded_file| {
+        !previous_cache
+            .files()
+            .contains_key(loaded_file.module_name())
+    });
+    // Paths may resolve to other crates once the manifests change.
+    let relinked = previous_cache.workspace() != &workspace;
+
+    let resolved_files = resolve_files(
+        config,
+        &workspace,
+        pool,
+        loaded_files,
+        &[],
+        &changed,
+        added || relinked,
+        &mut diagnostics,
+    );
+    let files: BTreeMap<_, _> = resolved_files
+        .into_iter()
+        .map(|file| (file.parsed_file.module_name.clone(), file))
+        .collect();
+
+    let Some(cache_path) = cache_path.filter(|_| !changed.is_empty() || relinked) else {
+        return Ok((files, workspace, diagnostics));
+    };
+
+    let cache = Cache::from_files(previous_cache.fingerprint(), workspace.clone(), files);
+    if let Err(error) = cache.save(&cache_path) {
+        diagnostics.push(Diagnostic {
+            path: cache_path,
+            error,
+        });
+    }
+
+    Ok((cache.into_files(), workspace, diagnostics))
+}
+
+/// Builds the call stack nodes of the blocks of a file.
+///
+/// Blocks that share a node key, such as a function defined in both branches of an
+/// `if` or under two `#[cfg]` attributes, make up a single node with the calls of
+/// all of them, in source order; the first block gives the node its location.
 ///
 /// # Arguments
 ///
-/// * `path` - The path of the file to check.
+/// * `blocks` - The blocks of the file, with their outgoing calls resolved.
 ///
 /// # Returns
 ///
-/// `true` if the file's extension is in the list of supported extensions, `false` otherwise.
-fn is_supported_file(path: &Path) -> bool {
-    let extensions = get_supported_extensions();
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| extensions.contains(&ext.to_lowercase()))
-        .unwrap_or(false)
+/// A map of node keys to `CallStackNode`s, each with its children filled in.
+fn call_stack_nodes(blocks: &[Block]) -> BTreeMap<String, CallStackNode> {
+    let mut nodes = BTreeMap::new();
+
+    for block in blocks {
+        match &block.block_type {
+            BlockType::Function => {
+                let node = CallStackNode {
+                    file_path: block.location.file_path.trim_start_matches('/').to_string(),
+                    location: block.location.clone(),
+                    class_name: block.class_name.clone(),
+                    trait_name: block.trait_name.clone(),
+                    function_name: block.function_name.clone().unwrap_or_default(),
+                    qualified_name: block.qualified_name.clone(),
+                    children: block
+                        .outgoing_calls
+                        .iter()
+                        .map(|call| ChildCall {
+                            node_key: call.callee.clone(),
+                            kind: call.kind,
+                            sites: call.sites.clone(),
+                        })
+                        .collect(),
+                    resolution: Resolution::Resolved,
+                    stand_in: false,
+                };
+
+                match nodes.entry(block.node_key.clone()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(node);
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let children = &mut entry.get_mut().children;
+                        for child in node.children {
+                            let existing = children.iter_mut().find(|existing| {
+                                existing.node_key == child.node_key && existing.kind == child.kind
+                            });
+                            match existing {
+                                Some(existing) => existing.sites.extend(child.sites),
+                                None => children.push(child),
+                            }
+                        }
+                    }
+                }
+            }
+            BlockType::NonFunction => {
+                // Handle non-function blocks if needed
+            }
+        }
+    }
+
+    nodes
+}
+
+/// Builds the nodes that stand in for the callees of a set of files that are not
+/// functions of the indexed tree: the external functions they call, the classes they
+/// construct that define no constructor, and the callees that could not be resolved.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing the language registry.
+/// * `files` - The indexed files.
+///
+/// # Returns
+///
+/// A map of node keys to external, class and unresolved `CallStackNode`s.
+fn stand_in_nodes<'a>(
+    config: &Config,
+    files: impl IntoIterator<Item = &'a CachedFile>,
+) -> BTreeMap<String, CallStackNode> {
+    let files: Vec<_> = files.into_iter().collect();
+    let mut nodes = BTreeMap::new();
+
+    // The module of every class, by class key.
+    let classes: HashMap<&str, &str> = files
+        .iter()
+        .flat_map(|file| {
+            let module_name = file.parsed_file.module_name.as_str();
+            file.parsed_file
+                .classes
+                .iter()
+                .map(move |class| (class.node_key.as_str(), module_name))
+        })
+        .collect();
+
+    for file in files {
+        let module_name = file.parsed_file.module_name.as_str();
+        let separator = config
+            .registry
+            .get(&file.parsed_file.language)
+            .map_or(".", |language| language.path_separator());
+        let calls = file.resolved_calls.outgoing_calls.iter().flatten();
+
+        for call in calls {
+            // The keys of external functions start with the name of their module, and
+            // those of unresolved callees with the module they were expected in. The
+            // only resolved callees without a function are classes.
+            let (module, path) = match &call.resolution {
+                Resolution::Resolved => match classes.get(call.callee.as_str()) {
+                    Some(module) => (*module, None),
+                    None => continue,
+                },
+                Resolution::External { module } => (module.as_str(), None),
+                Resolution::Unresolved { .. } => match call.callee.strip_prefix(module_name) {
+                    Some(path) if path.starts_with('.') => (module_name, None),
+                    _ => {
+                        let (module, path) =
+                            call.callee.rsplit_once('.').unwrap_or(("", &call.callee));
+                        (module, Some(path))
+                    }
+                },
+            };
+            let path = path.unwrap_or_else(|| {
+                call.callee
+                    .strip_prefix(module)
+                    .and_then(|path| path.strip_prefix('.'))
+                    .unwrap_or(&call.callee)
+            });
+            let node = CallStackNode {
+                file_path: module.trim_start_matches('/').to_string(),
+                location: SourceLocation::default(),
+                class_name: None,
+                trait_name: None,
+                function_name: path.rsplit('.').next().unwrap_or(path).to_string(),
+                qualified_name: path.replace('.', separator),
+                children: Vec::new(),
+                resolution: call.resolution.clone(),
+                stand_in: true,
+            };
+            nodes.insert(call.callee.clone(), node);
+        }
+    }
+
+    nodes
+}
+
+/// Assembles the `Index` of a set of indexed files.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` instance containing the language registry.
+/// * `files` - The indexed files, keyed by module name.
+/// * `diagnostics` - The diagnostics of the files that could not be indexed.
+///
+/// # Returns
+///
+/// The `Index` of the files.
+fn build_index(
+    config: &Config,
+    files: &BTreeMap<String, CachedFile>,
+    diagnostics: Vec<Diagnostic>,
+) -> Index {
+    let mut blocks = Vec::new();
+    let mut call_stack = CallStack::new();
+
+    for file in files.values() {
+        let file_blocks = file.resolved_calls.apply(&file.parsed_file);
+
+        for (node_key, node) in call_stack_nodes(&file_blocks) {
+            call_stack.add_node(node_key, node);
+        }
+
+        blocks.extend(file_blocks);
+    }
+    for (node_key, node) in stand_in_nodes(config, files.values()) {
+        call_stack.add_node(node_key, node);
+    }
+
+    let call_graph = call_stack.to_call_graph();
+    let class_hierarchy = class_hierarchy(files.values());
+    let type_graph = class_hierarchy.to_type_graph();
+
+    Index {
+        blocks,
+        call_stack,
+        call_graph,
+        class_hierarchy,
+        type_graph,
+        diagnostics,
+    }
+}
+
+/// Collects the classes of the indexed files into a `ClassHierarchy`.
+///
+/// # Arguments
+///
+/// * `files` - The indexed files.
+///
+/// # Returns
+///
+/// The `ClassHierarchy` of the files.
+fn class_hierarchy<'a>(files: impl IntoIterator<Item = &'a CachedFile>) -> ClassHierarchy {
+    let mut hierarchy = ClassHierarchy::new();
+    for file in files {
+        for (class_key, class) in &file.resolved_calls.classes {
+            hierarchy.add_class(class_key.clone(), class.clone());
+        }
+    }
+    hierarchy
 }
 
 /// Generates a unique key for a node in the call stack or call graph.
@@ -41,7 +712,7 @@ pub fn generate_node_key(
     class_name: Option<&str>,
     function_name: &str,
 ) -> String {
-    let mut key = file_path.to_str().unwrap().to_string();
+    let mut key = file_path.to_string_lossy().into_owned();
     if let Some(class) = class_name {
         key.push('.');
         key.push_str(class);
@@ -53,6 +724,18 @@ pub fn generate_node_key(
 
 /// Indexes a directory of code files and generates blocks, a call stack, and a call graph.
 ///
+/// Only files with an extension handled by `config.registry` are indexed. Files that
+/// cannot be read or parsed do not stop the indexing; they are reported in
+/// `Index::diagnostics` and the rest of the tree is indexed as usual.
+///
+/// The directory is walked and its files are parsed in parallel on a thread pool of
+/// `config.threads` threads. The results are merged in path order, so indexing the
+/// same tree always produces the same `Index`.
+///
+/// When `config.cache` is set, the parsed files are persisted between runs. Files whose
+/// contents did not change are not parsed again, and their calls are only resolved
+/// again when a module they import changed.
+///
 /// # Arguments
 ///
 /// * `config` - The `Config` instance containing language-specific settings.
@@ -60,53 +743,891 @@ pub fn generate_node_key(
 ///
 /// # Returns
 ///
-/// A tuple containing:
-/// - A vector of `Block`s representing the indexed code blocks.
-/// - A `CallStack` representing the hierarchy of function calls.
-/// - A `CallGraph` representing the relationships between functions.
-pub fn index_directory(config: &Config, dir_path: &str) -> (Vec<Block>, CallStack, CallGraph) {
-    let mut blocks = Vec::new();
-    let mut call_stack = CallStack::new();
+/// A `Result` containing the `Index` of the directory, or an error if the directory
+/// itself could not be read or the thread pool could not be created.
+pub fn index_directory(config: &Config, dir_path: &str) -> Result<Index> {
+    let pool = thread_pool(config)?;
+    let (files, _, diagnostics) = index_files(config, Path::new(dir_path), &pool)?;
 
-    for entry in WalkDir::new(dir_path) {
-        let entry = entry.unwrap();
-        let path = entry.path();
+    Ok(build_index(config, &files, diagnostics))
+}
 
-        if path.is_file() && is_supported_file(&path) {
-            let module_name = path.to_str().unwrap();
-            let file_blocks = parse_file(&path, module_name, &config);
-            blocks.extend(file_blocks.clone());
-
-            for block in &file_blocks {
-                match &block.block_type {
-                    BlockType::Function => {
-                        let function_name = block.function_name.clone().unwrap_or_default();
-                        let class_name = block.class_name.clone();
-
-                        let node_key =
-                            generate_node_key(&path, class_name.as_deref(), &function_name);
-                        let node = CallStackNode {
-                            file_path: path.to_str().unwrap().trim_start_matches('/').to_string(),
-                            class_name,
-                            function_name: function_name.clone(),
-                            children: Vec::new(),
-                        };
-
-                        call_stack.add_node(node_key.clone(), node);
-
-                        for call in &block.outgoing_calls {
-                            call_stack.add_child(&node_key, call);
-                        }
-                    }
-                    BlockType::NonFunction => {
-                        // Handle non-function blocks if needed
-                    }
+/// Keeps the index of a directory up to date as its files change.
+///
+/// An `Indexer` indexes the directory once when it is created, like `index_directory`.
+/// Afterwards, `update` re-indexes only the files that changed, and the files whose
+/// calls depend on them, and reports the difference as `ChangeEvent`s to every
+/// subscriber. `watch` calls `update` automatically whenever the file system reports
+/// a change.
+pub struct Indexer {
+    /// The configuration the directory is indexed with.
+    config: Config,
+    /// The path of the indexed directory.
+    root: PathBuf,
+    /// The canonical path of the indexed directory, which the file system watcher
+    /// reports changed paths under.
+    canonical_root: PathBuf,
+    /// The thread pool files are parsed on.
+    pool: Arc<ThreadPool>,
+    /// The indexed files, keyed by module name.
+    files: BTreeMap<String, CachedFile>,
+    /// The Cargo packages of the directory.
+    workspace: Workspace,
+    /// The current index of the directory.
+    index: Index,
+    /// The channels change events are sent to.
+    subscribers: Vec<Sender<ChangeEvent>>,
+}
+
+impl Indexer {
+    /// Creates an `Indexer` and indexes a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The `Config` instance containing language-specific settings.
+    /// * `dir_path` - The path of the directory to index.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Indexer`, or an error if the directory could not be
+    /// read or the thread pool could not be created.
+    pub fn new(config: Config, dir_path: impl AsRef<Path>) -> Result<Self> {
+        let root = dir_path.as_ref().to_path_buf();
+        let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let pool = thread_pool(&config)?;
+        let (files, workspace, diagnostics) = index_files(&config, &root, &pool)?;
+        let index = build_index(&config, &files, diagnostics);
+
+        Ok(Indexer {
+            config,
+            root,
+            canonical_root,
+            pool,
+            files,
+            workspace,
+            index,
+            subscribers: Vec::new(),
+        })
+    }
+
+    /// Returns the configuration the directory is indexed with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the path of the indexed directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the canonical path of the indexed directory.
+    pub(crate) fn canonical_root(&self) -> &Path {
+        &self.canonical_root
+    }
+
+    /// Returns the current index of the directory.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Consumes the `Indexer` and returns the current index of the directory.
+    pub fn into_index(self) -> Index {
+        self.index
+    }
+
+    /// Subscribes to the changes made by future updates.
+    ///
+    /// # Returns
+    ///
+    /// A `Receiver` that receives a `ChangeEvent` for every change. Dropping it
+    /// unsubscribes.
+    pub fn subscribe(&mut self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Starts watching the indexed directory, updating the index whenever a file is
+    /// created, modified, removed or renamed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Watch` that owns the `Indexer` while it is watched,
+    /// or an error if the directory could not be watched.
+    pub fn watch(self) -> Result<Watch> {
+        Watch::start(self)
+    }
+
+    /// Reports a problem that is not tied to a single file, such as a watch error.
+    pub(crate) fn push_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.index.diagnostics.push(diagnostic);
+    }
+
+    /// Re-indexes the given paths and updates the index to match.
+    ///
+    /// Paths may be files or directories, and may no longer exist, in which case
+    /// everything that was indexed under them is removed. They may be given under the
+    /// path the directory was indexed with or under its canonical path, like the
+    /// absolute paths reported by the file system watcher. Files in other modules whose
+    /// imports resolve to a changed module have their calls resolved again, and so do
+    /// the files whose imports depend on another changed path, like a configuration
+    /// file or a file that did not exist, and all files when a Cargo manifest changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - The paths that changed.
+    ///
+    /// # Returns
+    ///
+    /// The changes made to the call graph, which are also sent to every subscriber.
+    pub fn update(&mut self, paths: &[PathBuf]) -> Vec<ChangeEvent> {
+        let mut reloaded = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        let mut manifests: BTreeSet<PathBuf> = self.workspace.manifests().iter().cloned().collect();
+        let mut manifests_changed = false;
+        let mut diagnostics = Vec::new();
+
+        let paths: Vec<PathBuf> = paths
+            .iter()
+            .filter_map(|path| self.indexable_path(path))
+            .collect();
+        for path in &paths {
+            let Some(module_name) = path.to_str() else {
+                continue;
+            };
+            let indexed_under_path: Vec<String> = self
+                .files
+                .keys()
+                .filter(|indexed| Path::new(indexed).starts_with(path))
+                .cloned()
+                .collect();
+            let manifests_under_path = manifests
+                .iter()
+                .filter(|manifest| manifest.starts_with(path))
+                .count();
+
+            if path.is_dir() {
+                let (found, found_manifests) =
+                    walk_directory(&self.config, path, &self.pool, &mut diagnostics);
+                removed.extend(
+                    indexed_under_path
+                        .into_iter()
+                        .filter(|indexed| found.binary_search(indexed).is_err()),
+                );
+                reloaded.extend(found);
+                manifests.retain(|manifest| !manifest.starts_with(path));
+                manifests_changed |= manifests_under_path > 0 || !found_manifests.is_empty();
+                manifests.extend(found_manifests);
+            } else if path.is_file() {
+                if path.file_name().is_some_and(|name| name == MANIFEST) {
+                    manifests.insert(path.clone());
+                    manifests_changed = true;
+                } else if self.config.registry.for_path(path).is_some() {
+                    reloaded.insert(module_name.to_string());
                 }
+            } else {
+                removed.extend(indexed_under_path);
+                manifests.retain(|manifest| !manifest.starts_with(path));
+                manifests_changed |= manifests_under_path > 0;
             }
         }
+
+        // The diagnostics of the paths being re-indexed are replaced by fresh ones.
+        let old_manifests = self.workspace.manifests();
+        self.index.diagnostics.retain(|diagnostic| {
+            let path = diagnostic.path.to_str().unwrap_or_default();
+            let reloaded_manifest = manifests_changed && old_manifests.contains(&diagnostic.path);
+            !reloaded.contains(path) && !removed.contains(path) && !reloaded_manifest
+        });
+
+        // Paths may resolve to other crates once the manifests change.
+        let mut relinked = false;
+        if manifests_changed {
+            let manifests: Vec<PathBuf> = manifests.into_iter().collect();
+            let workspace = Workspace::load(&manifests, &mut diagnostics);
+            relinked = workspace != self.workspace;
+            self.workspace = workspace;
+        }
+
+        let loaded_files = load_files(
+            &self.config,
+            &self.pool,
+            reloaded.iter().cloned().collect(),
+            &self.files,
+            &mut diagnostics,
+        );
+
+        // Files that cannot be loaded any more are removed from the index.
+        let loaded: HashSet<&str> = loaded_files.iter().map(LoadedFile::module_name).collect();
+        let unloadable: Vec<String> = reloaded
+            .iter()
+            .filter(|module_name| {
+                !loaded.contains(module_name.as_str()) && self.files.contains_key(*module_name)
+            })
+            .cloned()
+            .collect();
+        removed.extend(unloadable);
+
+        let modified: Vec<LoadedFile> = loaded_files
+            .into_iter()
+            .filter(|loaded_file| loaded_file.cached_calls.is_none())
+            .collect();
+
+        // Paths that are not source files, like configuration files, may be dependencies
+        // of the files that looked them up while resolving their imports.
+        let other_paths = paths
+            .iter()
+            .filter(|path| self.config.registry.for_path(path).is_none())
+            .filter_map(|path| path.to_str().map(str::to_string));
+        let changed: HashSet<String> = modified
+            .iter()
+            .map(|loaded_file| loaded_file.module_name().to_string())
+            .chain(removed.iter().cloned())
+            .chain(other_paths)
+            .collect();
+        let added = modified
+            .iter()
+            .any(|loaded_file| !self.files.contains_key(loaded_file.module_name()));
+        let reclassed = modified.iter().any(|loaded_file| {
+            self.files
+                .get(loaded_file.module_name())
+                .is_some_and(|file| file.parsed_file.classes != loaded_file.parsed_file.classes)
+        });
+        let resolve_all = added || relinked || reclassed;
+
+        // Unchanged files that import a changed module have their calls resolved again,
+        // and so do all files when modules were added, as their imports may now resolve
+        // to them, and when classes changed, as calls may now dispatch to their methods.
+        let dependents = self
+            .files
+            .values()
+            .filter(|file| {
+                !changed.contains(&file.parsed_file.module_name)
+                    && (resolve_all
+                        || file
+                            .resolved_calls
+                            .dependencies
+                            .iter()
+                            .any(|dependency| changed.contains(dependency)))
+            })
+            .map(|file| LoadedFile {
+                hash: file.hash,
+                parsed_file: file.parsed_file.clone(),
+                cached_calls: Some(file.resolved_calls.clone()),
+            });
+        let to_resolve: Vec<_> = modified.into_iter().chain(dependents).collect();
+
+        let resolving: HashSet<&str> = to_resolve.iter().map(LoadedFile::module_name).collect();
+        let other_files: Vec<&ParsedFile> = self
+            .files
+            .values()
+            .map(|file| &file.parsed_file)
+            .filter(|parsed_file| {
+                !changed.contains(&parsed_file.module_name)
+                    && !resolving.contains(parsed_file.module_name.as_str())
+            })
+            .collect();
+
+        let resolved_files = resolve_files(
+            &self.config,
+            &self.workspace,
+            &self.pool,
+            to_resolve,
+            &other_files,
+            &changed,
+            resolve_all,
+            &mut diagnostics,
+        );
+
+        let mut updates: BTreeMap<String, Option<CachedFile>> = removed
+            .into_iter()
+            .map(|module_name| (module_name, None))
+            .collect();
+        for file in resolved_files {
+            updates.insert(file.parsed_file.module_name.clone(), Some(file));
+        }
+
+        // External and unresolved nodes exist as long as any file calls them, and give
+        // way to the functions of the tree that take their keys.
+        let old_stand_ins = stand_in_nodes(&self.config, self.files.values());
+        let new_stand_ins = stand_in_nodes(
+            &self.config,
+            self.files
+                .iter()
+                .filter(|(module_name, _)| !updates.contains_key(*module_name))
+                .map(|(_, file)| file)
+                .chain(updates.values().flatten()),
+        );
+
+        let mut events = Vec::new();
+        // Nodes are added before the edges to them.
+        for (node_key, node) in &new_stand_ins {
+            self.add_stand_in(node_key, node, &mut events);
+        }
+        for (module_name, file) in updates {
+            self.replace_file(&module_name, file, &new_stand_ins, &mut events);
+        }
+        self.index.class_hierarchy = class_hierarchy(self.files.values());
+        self.index.type_graph = self.index.class_hierarchy.to_type_graph();
+        // A function removed from the tree may have taken the key of a node that is
+        // called, now unresolved.
+        for (node_key, node) in &new_stand_ins {
+            self.add_stand_in(node_key, node, &mut events);
+        }
+        for node_key in old_stand_ins.keys() {
+            let is_stand_in = self
+                .index
+                .call_stack
+                .get_node(node_key)
+                .is_some_and(CallStackNode::is_stand_in);
+            if is_stand_in && !new_stand_ins.contains_key(node_key) {
+                self.index.call_stack.remove_node(node_key);
+                self.index.call_graph.remove_node(node_key);
+                events.push(ChangeEvent::NodeRemoved(node_key.clone()));
+            }
+        }
+
+        self.index.diagnostics.extend(diagnostics);
+        self.subscribers.retain(|subscriber| {
+            events
+                .iter()
+                .all(|event| subscriber.send(event.clone()).is_ok())
+        });
+
+        events
     }
 
-    let call_graph = call_stack.to_call_graph();
+    /// Checks if a path is inside the indexed directory and not hidden, like the files
+    /// visited by the initial walk.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path, under the path the directory was indexed with or under its
+    ///   canonical path.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the path under the path the directory was indexed with,
+    /// which module names start with, or `None` if the path is not indexed.
+    fn indexable_path(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path
+            .strip_prefix(&self.root)
+            .or_else(|_| path.strip_prefix(&self.canonical_root))
+            .ok()?;
+        let hidden = relative.components().any(|component| {
+            component
+                .as_os_str()
+                .to_str()
+                .is_some_and(|name| name.starts_with('.') && name != "." && name != "..")
+        });
+
+        match relative.as_os_str().is_empty() {
+            _ if hidden => None,
+            true => Some(self.root.clone()),
+            false => Some(self.root.join(relative)),
+        }
+    }
+
+    /// Adds a node standing in for an external or unresolved callee, unless a function
+    /// of the tree or the same node already has its key.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_key` - The key of the node.
+    /// * `node` - The external or unresolved `CallStackNode`.
+    /// * `events` - The change events to append the addition or modification to.
+    fn add_stand_in(
+        &mut self,
+        node_key: &str,
+        node: &CallStackNode,
+        events: &mut Vec<ChangeEvent>,
+    ) {
+        let event = match self.index.call_stack.get_node(node_key) {
+            None => ChangeEvent::NodeAdded(node_key.to_string()),
+            Some(old_node) if old_node.is_stand_in() && old_node != node => {
+                ChangeEvent::NodeModified(node_key.to_string())
+            }
+            Some(_) => return,
+        };
+        self.index
+            .call_stack
+            .add_node(node_key.to_string(), node.clone());
+        self.index
+            .call_graph
+            .insert_node(node_key.to_string(), node.clone());
+        events.push(event);
+    }
+
+    /// Replaces the indexed version of a file and updates the blocks, call stack and
+    /// call graph to match.
+    ///
+    /// # Arguments
+    ///
+    /// * `module_name` - The module name of the file.
+    /// * `file` - The new version of the file, or `None` if it was removed.
+    /// * `stand_ins` - The external and unresolved nodes of the updated index, which
+    ///   take the place of the removed functions whose keys are still called.
+    /// * `events` - The change events to append the differences to.
+    fn replace_file(
+        &mut self,
+        module_name: &str,
+        file: Option<CachedFile>,
+        stand_ins: &BTreeMap<String, CallStackNode>,
+        events: &mut Vec<ChangeEvent>,
+    ) {
+        let offset: usize = self
+            .files
+            .iter()
+            .take_while(|(indexed, _)| indexed.as_str() < module_name)
+            .map(|(_, file)| file.parsed_file.blocks.len())
+            .sum();
+        let old_blocks = self
+            .files
+            .remove(module_name)
+            .map(|old_file| old_file.resolved_calls.apply(&old_file.parsed_file))
+            .unwrap_or_default();
+        let new_blocks = file
+            .as_ref()
+            .map(|file| file.resolved_calls.apply(&file.parsed_file))
+            .unwrap_or_default();
+
+        let old_nodes = call_stack_nodes(&old_blocks);
+        let new_nodes = call_stack_nodes(&new_blocks);
+        // The contents of every block of a node, as blocks may share it.
+        let contents = |blocks: &[Block]| -> BTreeMap<String, Vec<String>> {
+            let mut contents: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for block in blocks {
+                if block.block_type == BlockType::Function {
+                    contents
+                        .entry(block.node_key.clone())
+                        .or_default()
+                        .push(block.content.clone());
+                }
+            }
+            contents
+        };
+        let old_contents = contents(&old_blocks);
+        let new_contents = contents(&new_blocks);
+
+        let node_keys: BTreeSet<&String> = old_nodes.keys().chain(new_nodes.keys()).collect();
+        for node_key in node_keys {
+            let old_node = old_nodes.get(node_key);
+            let new_node = new_nodes.get(node_key);
+            // A function that is removed while it is still called gives way to a
+            // stand-in, and a function that is added takes the place of one, so the key
+            // stays in the graph.
+            let stand_in = match new_node {
+                Some(_) => None,
+                None => stand_ins.get(node_key),
+            };
+            let replaces_stand_in = self
+                .index
+                .call_stack
+                .get_node(node_key)
+                .is_some_and(CallStackNode::is_stand_in);
+            let old_children = old_node.map_or(&[][..], |node| &node.children);
+            let new_children = new_node.map_or(&[][..], |node| &node.children);
+            // Calls that only moved change the sites of an edge, not the edge.
+            let edges = |children: &[ChildCall]| -> Vec<(String, EdgeKind)> {
+                children
+                    .iter()
+                    .map(|child| (child.node_key.clone(), child.kind))
+                    .collect()
+            };
+            let old_edges = edges(old_children);
+            let new_edges = edges(new_children);
+
+            let node_event = match (old_node, new_node) {
+                (None, Some(_)) if replaces_stand_in => {
+                    Some(ChangeEvent::NodeModified(node_key.clone()))
+                }
+                (None, Some(_)) => Some(ChangeEvent::NodeAdded(node_key.clone())),
+                (Some(_), None) if stand_in.is_some() => {
+                    Some(ChangeEvent::NodeModified(node_key.clone()))
+                }
+                (Some(_), None) => Some(ChangeEvent::NodeRemoved(node_key.clone())),
+                (Some(old_node), Some(new_node)) => {
+                    let definition = |node: &CallStackNode| CallStackNode {
+                        children: Vec::new(),
+                        ..node.clone()
+                    };
+                    let modified = definition(old_node) != definition(new_node)
+                        || old_contents.get(node_key) != new_contents.get(node_key);
+                    modified.then(|| ChangeEvent::NodeModified(node_key.clone()))
+                }
+                (None, None) => None,
+            };
+
+            if node_event.is_none() && old_children == new_children {
+                continue;
+            }
+
+            // A node is added before the edges from it, and removed after them.
+            let (before, after) = match node_event {
+                Some(event @ ChangeEvent::NodeAdded(_)) => (Some(event), None),
+                event => (None, event),
+            };
+            events.extend(before);
+            // An edge whose kind changed is removed and added again.
+            for (to, kind) in old_edges.iter().filter(|edge| !new_edges.contains(edge)) {
+                events.push(ChangeEvent::EdgeRemoved {
+                    from: node_key.clone(),
+                    to: to.clone(),
+                    kind: *kind,
+                });
+            }
+            for (to, kind) in new_edges.iter().filter(|edge| !old_edges.contains(edge)) {
+                events.push(ChangeEvent::EdgeAdded {
+                    from: node_key.clone(),
+                    to: to.clone(),
+                    kind: *kind,
+                });
+            }
+            events.extend(after);
+
+            self.index.call_stack.remove_node(node_key);
+            self.index.call_graph.remove_node(node_key);
+            if let Some(new_node) = new_node.or(stand_in) {
+                self.index
+                    .call_stack
+                    .add_node(node_key.clone(), new_node.clone());
+                self.index
+                    .call_graph
+                    .insert_node(node_key.clone(), new_node.clone());
+            }
+        }
+
+        self.index
+            .blocks
+            .splice(offset..offset + old_blocks.len(), new_blocks);
+
+        if let Some(file) = file {
+            self.files.insert(module_name.to_string(), file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{ChangeEvent, Diagnostic, Indexer};
+    use crate::block::{EdgeKind, Resolution};
+    use crate::config::{Config, Language};
+    use crate::error::Error;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn definitions_sharing_a_key_keep_the_calls_of_each() {
+        let fixture = Fixture::new(&[
+            (
+                "app.py",
+                "import sys\n\nif sys.platform == \"win32\":\n    def f():\n        a()\nelse:\n    def f():\n        b()\n        a()\n\ndef a():\n    pass\n\ndef b():\n    pass\n",
+            ),
+            (
+                "src/lib.rs",
+                "#[cfg(unix)]\nfn plat() {\n    helper();\n}\n\n#[cfg(windows)]\nfn plat() {\n    other();\n}\n\nfn helper() {}\n\nfn other() {}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        for (path, function, callees) in [
+            ("app.py", "f", ["a", "b"]),
+            ("src/lib.rs", "plat", ["helper", "other"]),
+        ] {
+            let callees = callees.map(|callee| fixture.key(path, callee));
+            assert_eq!(fixture.callees(&index, path, function), callees);
+
+            let function = fixture.key(path, function);
+            for callee in &callees {
+                assert!(
+                    index.call_graph.edge(&function, callee).is_some(),
+                    "{}",
+                    callee
+                );
+            }
+        }
+        // The call made from both definitions is one edge with both call sites.
+        let f = fixture.key("app.py", "f");
+        let a = fixture.key("app.py", "a");
+        assert_eq!(index.call_graph.edge(&f, &a).unwrap().count(), 2);
+    }
 
-    (blocks, call_stack, call_graph)
+    /// Returns the diagnostics of an index by the path of their file, relative to the
+    /// fixture.
+    fn diagnostics<'a>(
+        fixture: &Fixture,
+        diagnostics: &'a [Diagnostic],
+    ) -> Vec<(String, &'a Error)> {
+        let mut diagnostics: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let path = diagnostic.path.strip_prefix(fixture.root()).unwrap();
+                (path.display().to_string(), &diagnostic.error)
+            })
+            .collect();
+        diagnostics.sort_by(|(a, _), (b, _)| a.cmp(b));
+        diagnostics
+    }
+
+    #[test]
+    fn reports_files_that_cannot_be_read() {
+        let fixture = Fixture::new(&[("app.py", "def main():\n    pass\n")]);
+        fs::write(fixture.root().join("binary.py"), [0xff, 0xfe, 0x00]).unwrap();
+        let index = fixture.index();
+
+        let diagnostics = diagnostics(&fixture, &index.diagnostics);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "binary.py");
+        assert!(
+            matches!(diagnostics[0].1, Error::Io { .. }),
+            "{}",
+            diagnostics[0].1
+        );
+        // The other files are indexed all the same.
+        assert!(index
+            .call_stack
+            .get_node(&fixture.key("app.py", "main"))
+            .is_some());
+    }
+
+    #[test]
+    fn reports_syntax_errors_and_indexes_the_rest_of_the_file() {
+        let fixture = Fixture::new(&[
+            (
+                "app.py",
+                "def broken(:\n    pass\n\ndef ok():\n    helper()\n\ndef helper():\n    pass\n",
+            ),
+            ("lib.py", "def fine():\n    pass\n"),
+        ]);
+        let index = fixture.index();
+
+        let diagnostics = diagnostics(&fixture, &index.diagnostics);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "app.py");
+        match diagnostics[0].1 {
+            Error::Syntax { path, position } => {
+                assert_eq!(path, &fixture.root().join("app.py"));
+                assert_eq!(position.line, 1);
+            }
+            error => panic!("unexpected diagnostic: {}", error),
+        }
+        assert_eq!(
+            fixture.callees(&index, "app.py", "ok"),
+            [fixture.key("app.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn reports_query_overrides_that_do_not_compile() {
+        let fixture = Fixture::new(&[
+            ("app.py", "def main():\n    pass\n"),
+            ("lib.py", "def fine():\n    pass\n"),
+            ("src/lib.rs", "fn unaffected() {}\n"),
+            (
+                "broken.scm",
+                "(function_definition name: (identifier) @name\n",
+            ),
+        ]);
+        let mut config = Config::default();
+        config.languages.insert(
+            "python".to_string(),
+            Language {
+                queries: Some(fixture.root().join("broken.scm")),
+                sys_path: Vec::new(),
+            },
+        );
+        let index = fixture.index_with(&config);
+
+        let diagnostics = diagnostics(&fixture, &index.diagnostics);
+        let paths: Vec<_> = diagnostics.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["app.py", "lib.py"]);
+        for (_, error) in diagnostics {
+            match error {
+                Error::Query { language, path, .. } => {
+                    assert_eq!(language, "python");
+                    assert_eq!(path.as_deref(), Some(&*fixture.root().join("broken.scm")));
+                }
+                error => panic!("unexpected diagnostic: {}", error),
+            }
+        }
+        // Only the files of the language are skipped.
+        assert!(index
+            .call_stack
+            .get_node(&fixture.key("src/lib.rs", "unaffected"))
+            .is_some());
+    }
+
+    #[test]
+    fn marks_the_nodes_standing_in_for_callees_outside_the_tree() {
+        let fixture = Fixture::new(&[(
+            "app.py",
+            "import os\n\nclass Calc:\n    pass\n\ndef main():\n    os.getcwd()\n    Calc()\n    missing()\n",
+        )]);
+        let index = fixture.index();
+
+        let is_stand_in =
+            |node_key: &str| index.call_stack.get_node(node_key).unwrap().is_stand_in();
+        assert!(!is_stand_in(&fixture.key("app.py", "main")));
+        assert!(is_stand_in("os.getcwd"));
+        // A class without a constructor is resolved, and still no function of the tree.
+        assert!(is_stand_in(&fixture.key("app.py", "Calc")));
+        assert!(is_stand_in(&fixture.key("app.py", "missing")));
+    }
+
+    /// Indexes a fixture with an `Indexer` and the default configuration.
+    fn indexer(fixture: &Fixture) -> Indexer {
+        Indexer::new(Config::default(), fixture.root()).unwrap()
+    }
+
+    /// Returns the change event of a node of a fixture.
+    fn node(
+        event: fn(String) -> ChangeEvent,
+        fixture: &Fixture,
+        path: &str,
+        name: &str,
+    ) -> ChangeEvent {
+        event(fixture.key(path, name))
+    }
+
+    /// Returns the change event of a direct call between two functions of a fixture.
+    fn edge(
+        added: bool,
+        fixture: &Fixture,
+        (from_path, from): (&str, &str),
+        (to_path, to): (&str, &str),
+    ) -> ChangeEvent {
+        let (from, to, kind) = (
+            fixture.key(from_path, from),
+            fixture.key(to_path, to),
+            EdgeKind::Direct,
+        );
+        match added {
+            true => ChangeEvent::EdgeAdded { from, to, kind },
+            false => ChangeEvent::EdgeRemoved { from, to, kind },
+        }
+    }
+
+    #[test]
+    fn update_reports_modified_functions_and_calls() {
+        let fixture = Fixture::new(&[
+            (
+                "a.py",
+                "from b import helper, other\n\ndef main():\n    helper()\n",
+            ),
+            (
+                "b.py",
+                "def helper():\n    pass\n\ndef other():\n    pass\n",
+            ),
+        ]);
+        let mut indexer = indexer(&fixture);
+
+        let a = fixture.root().join("a.py");
+        fs::write(
+            &a,
+            "from b import helper, other\n\ndef main():\n    other()\n\ndef extra():\n    helper()\n",
+        )
+        .unwrap();
+        assert_eq!(
+            indexer.update(&[a]),
+            [
+                node(ChangeEvent::NodeAdded, &fixture, "a.py", "extra"),
+                edge(true, &fixture, ("a.py", "extra"), ("b.py", "helper")),
+                edge(false, &fixture, ("a.py", "main"), ("b.py", "helper")),
+                edge(true, &fixture, ("a.py", "main"), ("b.py", "other")),
+                node(ChangeEvent::NodeModified, &fixture, "a.py", "main"),
+            ]
+        );
+        // A file saved without changes changes nothing.
+        assert!(indexer.update(&[fixture.root().join("b.py")]).is_empty());
+    }
+
+    #[test]
+    fn update_reports_removed_files_and_the_calls_moving_to_stand_ins() {
+        let fixture = Fixture::new(&[
+            (
+                "a.py",
+                "from b import helper\n\ndef main():\n    helper()\n",
+            ),
+            (
+                "b.py",
+                "def helper():\n    pass\n\ndef other():\n    pass\n",
+            ),
+            ("c.py", "from b import other\n\ndef run():\n    other()\n"),
+        ]);
+        let mut indexer = indexer(&fixture);
+
+        let c = fixture.root().join("c.py");
+        fs::remove_file(&c).unwrap();
+        assert_eq!(
+            indexer.update(&[c]),
+            [
+                edge(false, &fixture, ("c.py", "run"), ("b.py", "other")),
+                node(ChangeEvent::NodeRemoved, &fixture, "c.py", "run"),
+            ]
+        );
+
+        // The caller in `a.py` now calls a stand-in for `helper` of a module outside
+        // the tree.
+        let b = fixture.root().join("b.py");
+        fs::remove_file(&b).unwrap();
+        assert_eq!(
+            indexer.update(&[b]),
+            [
+                ChangeEvent::NodeAdded("b.helper".to_string()),
+                edge(false, &fixture, ("a.py", "main"), ("b.py", "helper")),
+                ChangeEvent::EdgeAdded {
+                    from: fixture.key("a.py", "main"),
+                    to: "b.helper".to_string(),
+                    kind: EdgeKind::Direct,
+                },
+                node(ChangeEvent::NodeRemoved, &fixture, "b.py", "helper"),
+                node(ChangeEvent::NodeRemoved, &fixture, "b.py", "other"),
+            ]
+        );
+        let stand_in = indexer.index().call_stack.get_node("b.helper").unwrap();
+        assert!(stand_in.is_stand_in());
+    }
+
+    #[test]
+    fn update_reports_a_function_giving_way_to_a_stand_in_once() {
+        let fixture = Fixture::new(&[
+            ("a.py", "from b import g\n\ndef main():\n    g()\n"),
+            ("b.py", "def helper():\n    pass\n\ndef g():\n    pass\n"),
+        ]);
+        let mut indexer = indexer(&fixture);
+        let b = fixture.root().join("b.py");
+        let g = fixture.key("b.py", "g");
+
+        // The caller still calls `g`, which is now unresolved.
+        fs::write(&b, "def helper():\n    pass\n\ndef g2():\n    pass\n").unwrap();
+        assert_eq!(
+            indexer.update(std::slice::from_ref(&b)),
+            [
+                node(ChangeEvent::NodeModified, &fixture, "b.py", "g"),
+                node(ChangeEvent::NodeAdded, &fixture, "b.py", "g2"),
+            ]
+        );
+        let index = indexer.index();
+        let stand_in = index.call_stack.get_node(&g).unwrap();
+        assert!(stand_in.is_stand_in());
+        assert!(matches!(stand_in.resolution, Resolution::Unresolved { .. }));
+        assert!(index
+            .call_graph
+            .edge(&fixture.key("a.py", "main"), &g)
+            .is_some());
+
+        // Renaming it back turns the stand-in into the function again.
+        fs::write(&b, "def helper():\n    pass\n\ndef g():\n    pass\n").unwrap();
+        assert_eq!(
+            indexer.update(&[b]),
+            [
+                node(ChangeEvent::NodeModified, &fixture, "b.py", "g"),
+                node(ChangeEvent::NodeRemoved, &fixture, "b.py", "g2"),
+            ]
+        );
+        let function = indexer.index().call_stack.get_node(&g).unwrap();
+        assert_eq!(function.resolution, Resolution::Resolved);
+        assert!(!function.is_stand_in());
+    }
 }
@@ -0,0 +1,86 @@
+use std::path::Path;
+use tree_sitter::Language;
+
+use super::{node, LanguageSupport, ResolvedImport};
+use crate::query::ImportTag;
+use crate::resolver::ResolveContext;
+
+extern "C" {
+    fn tree_sitter_javascript() -> Language;
+}
+
+/// The built-in query for JavaScript.
+const TAGS: &str = include_str!("../../queries/javascript/tags.scm");
+
+/// Support for JavaScript source files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JavaScript;
+
+impl LanguageSupport for JavaScript {
+    fn name(&self) -> &str {
+        "javascript"
+    }
+
+    fn language(&self) -> Language {
+        unsafe { tree_sitter_javascript() }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["js", "mjs", "cjs", "jsx"]
+    }
+
+    fn default_query(&self) -> Option<&str> {
+        Some(TAGS)
+    }
+
+    /// Resolves imports the way Node does, see `node::resolve_import`.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        node::resolve_import(import, file_path, context, self.extensions())
+    }
+
+    fn receiver_names(&self) -> &[&str] {
+        &["this"]
+    }
+
+    fn super_names(&self) -> &[&str] {
+        &["super"]
+    }
+
+    fn constructor_name(&self) -> Option<&str> {
+        Some("constructor")
+    }
+
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        node::builtin_module(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::EdgeKind;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn scheduled_functions_and_jsx_handlers_are_callbacks() {
+        let fixture = Fixture::new(&[(
+            "app.jsx",
+            "function tick() {}\nfunction handle() {}\n\nfunction main() {\n  setTimeout(tick, 10);\n  process.nextTick(() => tick());\n  return <button onClick={handle}>{tick}</button>;\n}\n",
+        )]);
+        let index = fixture.index();
+
+        let main = fixture.key("app.jsx", "main");
+        for callee in ["tick", "main.{closure#0}", "handle"] {
+            let edge = index
+                .call_graph
+                .edge(&main, &fixture.key("app.jsx", callee))
+                .unwrap();
+            assert_eq!(edge.kind, EdgeKind::CallbackReference, "{}", callee);
+            assert_eq!(edge.count(), 1, "{}", callee);
+        }
+    }
+}
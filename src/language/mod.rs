@@ -0,0 +1,288 @@
+//! Language support for the indexer.
+//!
+//! Every language stackwalk can index is described by a [`LanguageSupport`]
+//! implementation, and the languages available to an index run are collected in a
+//! [`LanguageRegistry`]. The built-in languages are registered by default; downstream
+//! crates can register their own grammars alongside or in place of them.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tree_sitter::{Node, Query};
+
+use crate::query::{collect_tags, ImportTag, Tags};
+use crate::resolver::ResolveContext;
+
+mod javascript;
+mod node;
+mod python;
+mod rust;
+mod typescript;
+
+pub use javascript::JavaScript;
+pub use python::Python;
+pub use rust::Rust;
+pub use typescript::{Tsx, TypeScript};
+
+/// Describes how to parse and extract code structure from one language.
+///
+/// Only `name`, `language` and `extensions` are required. Extraction is driven by the
+/// language's tree-sitter query, which is read from the configuration directory or,
+/// failing that, from `default_query`.
+pub trait LanguageSupport: Send + Sync {
+    /// Returns the name of the language, e.g. `"python"`.
+    ///
+    /// The name selects the language's section in the configuration and its query
+    /// directory, `queries/<name>/tags.scm`.
+    fn name(&self) -> &str;
+
+    /// Returns the tree-sitter grammar of the language.
+    fn language(&self) -> tree_sitter::Language;
+
+    /// Returns the file extensions handled by the language, without the leading dot.
+    fn extensions(&self) -> &[&str];
+
+    /// Returns the source of the query used when the configuration does not override it.
+    fn default_query(&self) -> Option<&str> {
+        None
+    }
+
+    /// Extracts the definitions, calls and imports of a syntax tree.
+    ///
+    /// The default implementation collects the captures of the language's query; see
+    /// [`collect_tags`] for the capture names it understands.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The compiled query for the language.
+    /// * `root` - The root node of the syntax tree.
+    /// * `code` - The code string the tree was parsed from.
+    ///
+    /// # Returns
+    ///
+    /// The `Tags` found in the tree.
+    fn extract_tags(&self, query: &Query, root: Node, code: &str) -> Tags {
+        collect_tags(query, root, code)
+    }
+
+    /// Resolves an import to the module that defines the imported binding.
+    ///
+    /// # Arguments
+    ///
+    /// * `import` - The import found in the file.
+    /// * `file_path` - The path of the file containing the import.
+    /// * `context` - The `ResolveContext` of the indexed tree.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ResolvedImport`, or `None` if the import cannot be
+    /// resolved, in which case the module is used as written.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        let _ = (import, file_path, context);
+        None
+    }
+
+    /// Returns the separator used between the segments of qualified names.
+    fn path_separator(&self) -> &str {
+        "."
+    }
+
+    /// Returns the segment inserted after every function in qualified names, if any.
+    fn locals_segment(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the name given to the anonymous function at `index` within its
+    /// enclosing scope.
+    fn closure_name(&self, index: usize) -> String {
+        format!("{{closure#{}}}", index)
+    }
+
+    /// Returns the names that refer to the instance or class a method is called on,
+    /// such as Python's `self`, so that calls through them resolve to the enclosing
+    /// class.
+    fn receiver_names(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the names that refer to the bases of the enclosing class, such as
+    /// Python's `super`, so that calls through them resolve to the inherited method.
+    /// A name may also be called to get the receiver, as in `super().method()`.
+    fn super_names(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the name of the method that initializes the instances of a class, such
+    /// as Python's `__init__`, so that constructing a class calls it.
+    fn constructor_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the module a name that is built into the language comes from, such as
+    /// Python's `builtins` for `print`, so that calls of it are external rather than
+    /// unresolved.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name called, or the first segment of a called path or member,
+    ///   e.g. `print`, `console` or `String`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the name of the module, or `None` if the name is not
+    /// built in.
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        let _ = name;
+        None
+    }
+}
+
+/// The module an import was resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedImport {
+    /// The module path that prefixes the node keys of the module's definitions, i.e.
+    /// the path of the module's file as it is indexed.
+    pub module: String,
+    /// Whether the imported binding is the module itself, rather than a definition
+    /// inside it.
+    pub is_module: bool,
+    /// The path of the imported definition within `module`, with segments joined by
+    /// `.`, if it differs from the imported name, e.g. because the definition was
+    /// re-exported under another name.
+    pub name: Option<String>,
+    /// Whether the module is outside the indexed tree, like a crate that was not
+    /// indexed, so its definitions have no nodes of their own.
+    pub external: bool,
+    /// The paths of the files other than `module` that the resolution depends on, such
+    /// as configuration files, including those looked for that do not exist. The
+    /// import has to be resolved again when any of them is created, changed or removed.
+    pub dependencies: Vec<String>,
+}
+
+/// A collection of `LanguageSupport` implementations, looked up by file extension.
+#[derive(Clone)]
+pub struct LanguageRegistry {
+    /// The registered languages, in registration order.
+    languages: Vec<Arc<dyn LanguageSupport>>,
+    /// A map of file extensions to indexes into `languages`.
+    extensions: HashMap<String, usize>,
+}
+
+impl Default for LanguageRegistry {
+    /// Creates a registry containing the built-in languages.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Rust);
+        registry.register(Python);
+        registry.register(JavaScript);
+        registry.register(TypeScript);
+        registry.register(Tsx);
+        registry
+    }
+}
+
+impl fmt::Debug for LanguageRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.languages.iter().map(|language| language.name()))
+            .finish()
+    }
+}
+
+impl LanguageRegistry {
+    /// Creates a new, empty `LanguageRegistry`.
+    pub fn new() -> Self {
+        LanguageRegistry {
+            languages: Vec::new(),
+            extensions: HashMap::new(),
+        }
+    }
+
+    /// Registers a language.
+    ///
+    /// A language registered later takes over the extensions of any language
+    /// registered before it, so built-in languages can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The `LanguageSupport` to register.
+    pub fn register(&mut self, language: impl LanguageSupport + 'static) {
+        let index = self.languages.len();
+        for extension in language.extensions() {
+            self.extensions.insert(extension.to_lowercase(), index);
+        }
+        self.languages.push(Arc::new(language));
+    }
+
+    /// Returns the language registered under a name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the language, e.g. `"rust"`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the most recently registered language with that name.
+    pub fn get(&self, name: &str) -> Option<&dyn LanguageSupport> {
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| language.name() == name)
+            .map(|language| language.as_ref())
+    }
+
+    /// Returns the language that handles a file, based on its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the language, or `None` if no registered language
+    /// handles the file's extension.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn LanguageSupport> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.extensions
+            .get(&extension)
+            .map(|&index| self.languages[index].as_ref())
+    }
+
+    /// Returns the registered languages, in registration order.
+    pub fn languages(&self) -> impl Iterator<Item = &dyn LanguageSupport> {
+        self.languages.iter().map(|language| language.as_ref())
+    }
+
+    /// Returns the file extensions handled by the registered languages.
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.extensions.keys().map(String::as_str)
+    }
+}
+
+/// Removes the `.` and `..` components of a path without touching the file system, so
+/// that it matches the module names of the indexed files.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir if normalized.as_os_str().is_empty() => normalized.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,697 @@
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use super::{normalize, ResolvedImport};
+use crate::query::ImportTag;
+use crate::resolver::ResolveContext;
+
+/// The configuration files that may map bare specifiers to files, in order of preference.
+const CONFIGS: [&str; 2] = ["tsconfig.json", "jsconfig.json"];
+
+/// The conditions of `package.json` `exports` that apply to the indexed code. Like in
+/// Node, the first of them a conditional target lists is picked.
+const CONDITIONS: [&str; 4] = ["node", "import", "require", "default"];
+
+/// The maximum number of re-exports followed to find the definition behind an import.
+const MAX_REEXPORT_DEPTH: usize = 8;
+
+/// The global objects and functions of JavaScript and Node, which need no import.
+const GLOBALS: [&str; 43] = [
+    "console",
+    "setTimeout",
+    "setInterval",
+    "setImmediate",
+    "clearTimeout",
+    "clearInterval",
+    "clearImmediate",
+    "queueMicrotask",
+    "structuredClone",
+    "fetch",
+    "parseInt",
+    "parseFloat",
+    "isNaN",
+    "isFinite",
+    "encodeURI",
+    "decodeURI",
+    "encodeURIComponent",
+    "decodeURIComponent",
+    "JSON",
+    "Math",
+    "Object",
+    "Array",
+    "String",
+    "Number",
+    "Boolean",
+    "Symbol",
+    "BigInt",
+    "Promise",
+    "Date",
+    "RegExp",
+    "Error",
+    "TypeError",
+    "RangeError",
+    "Map",
+    "Set",
+    "WeakMap",
+    "WeakSet",
+    "Reflect",
+    "Proxy",
+    "process",
+    "require",
+    "Buffer",
+    "URL",
+];
+
+/// Returns the module the global objects and functions of JavaScript and Node come
+/// from, for `LanguageSupport::builtin_module`.
+pub(super) fn builtin_module(name: &str) -> Option<&'static str> {
+    GLOBALS.contains(&name).then_some("globalThis")
+}
+
+/// Resolves an import of a JavaScript or TypeScript file the way Node does.
+///
+/// Relative specifiers are looked up as a file, trying each extension in turn, and then
+/// as a directory, through the `main` of its `package.json` or its `index` file. Bare
+/// specifiers are first mapped through the `paths` and `baseUrl` of the nearest
+/// `tsconfig.json` or `jsconfig.json`, then looked up in the `node_modules` directories
+/// of the file's ancestors, through the `exports` or `main` of the package. Anything
+/// else, such as Node's built-in modules, is external.
+///
+/// Imported names are followed to the definitions they refer to, through the exports
+/// and re-exports of the imported modules.
+///
+/// The configuration files and `node_modules` directories looked up along the way,
+/// whether they exist or not, are the dependencies of the resolved import.
+///
+/// # Arguments
+///
+/// * `import` - The import to resolve.
+/// * `file_path` - The path of the file containing the import.
+/// * `context` - The context to look up other files in.
+/// * `extensions` - The extensions to try, in order, for specifiers without one.
+///
+/// # Returns
+///
+/// An `Option` containing the `ResolvedImport`, or `None` if a relative specifier
+/// does not match any indexed file.
+pub(super) fn resolve_import(
+    import: &ImportTag,
+    file_path: &Path,
+    context: &ResolveContext,
+    extensions: &[&str],
+) -> Option<ResolvedImport> {
+    let resolver = ModuleResolver {
+        context,
+        extensions,
+        consulted: RefCell::default(),
+    };
+    let mut resolved = resolver.resolve(import, file_path, 0)?;
+    resolved.dependencies = resolver.consulted.into_inner().into_iter().collect();
+    Some(resolved)
+}
+
+/// Looks up specifiers and the names they export in the indexed tree.
+struct ModuleResolver<'c, 'a> {
+    context: &'c ResolveContext<'a>,
+    extensions: &'c [&'c str],
+    /// The paths looked up outside the indexed tree so far.
+    consulted: RefCell<BTreeSet<String>>,
+}
+
+impl<'c, 'a> ModuleResolver<'c, 'a> {
+    /// Resolves an import, following re-exports up to `MAX_REEXPORT_DEPTH` deep.
+    fn resolve(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        depth: usize,
+    ) -> Option<ResolvedImport> {
+        let specifier = import.module.as_str();
+        let module = if is_relative(specifier) {
+            self.resolve_path(&file_path.parent()?.join(specifier))?
+        } else {
+            match self
+                .resolve_config_paths(file_path, specifier)
+                .or_else(|| self.resolve_package(file_path, specifier))
+            {
+                Some(module) => module,
+                None => {
+                    return Some(ResolvedImport {
+                        module: specifier.to_string(),
+                        is_module: import.name.is_none(),
+                        name: None,
+                        external: true,
+                        dependencies: Vec::new(),
+                    })
+                }
+            }
+        };
+
+        let Some(name) = import.name.as_deref() else {
+            return Some(module_import(module));
+        };
+        Some(
+            self.resolve_export(&module, name, depth)
+                .unwrap_or_else(|| item_import(module, name)),
+        )
+    }
+
+    /// Finds the definition a module exports under a name.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ResolvedImport` of the definition, or `None` if the
+    /// module does not export the name.
+    fn resolve_export(&self, module: &str, name: &str, depth: usize) -> Option<ResolvedImport> {
+        if name == "*" {
+            return Some(module_import(module.to_string()));
+        }
+
+        let file = self.context.file(module)?;
+        if let Some(export) = file.exports.iter().find(|export| export.alias == name) {
+            return Some(item_import(module.to_string(), &export.name));
+        }
+        if file.definitions.iter().any(|definition| definition == name) {
+            return Some(item_import(module.to_string(), name));
+        }
+        if depth >= MAX_REEXPORT_DEPTH {
+            return None;
+        }
+
+        let file_path = Path::new(module);
+        let reexports = || file.imports.iter().filter(|import| import.reexport);
+        // Named re-exports take precedence over the names of `export * from`.
+        reexports()
+            .find(|import| binding_of(import) == Some(name))
+            .and_then(|import| self.resolve(import, file_path, depth + 1))
+            .or_else(|| {
+                reexports()
+                    .filter(|import| import.name.as_deref() == Some("*") && import.alias.is_none())
+                    .find_map(|import| {
+                        let target = self.resolve(import, file_path, depth + 1)?;
+                        if target.external {
+                            return None;
+                        }
+                        self.resolve_export(&target.module, name, depth + 1)
+                    })
+            })
+    }
+
+    /// Looks up a path as a file and then as a directory.
+    fn resolve_path(&self, path: &Path) -> Option<String> {
+        let path = normalize(path);
+        self.resolve_file(&path)
+            .or_else(|| self.resolve_directory(&path))
+    }
+
+    /// Looks up a path as a file, as is and with each extension.
+    fn resolve_file(&self, path: &Path) -> Option<String> {
+        let path = path.to_str()?;
+        let exists = |candidate: &str| self.context.file(candidate).is_some();
+        if exists(path) {
+            return Some(path.to_string());
+        }
+
+        // TypeScript lets `./name.js` refer to the `./name.ts` it is compiled to.
+        let stems = std::iter::once(path).chain(path.strip_suffix(".js"));
+        stems
+            .flat_map(|stem| {
+                self.extensions
+                    .iter()
+                    .map(move |extension| format!("{}.{}", stem, extension))
+            })
+            .find(|candidate| exists(candidate))
+    }
+
+    /// Reads a JSON file, recording it as consulted.
+    fn read_json(&self, path: &Path) -> Option<Value> {
+        self.consult(path);
+        read_json(path)
+    }
+
+    /// Records a path looked up outside the indexed tree.
+    fn consult(&self, path: &Path) {
+        self.consulted
+            .borrow_mut()
+            .insert(path.to_string_lossy().into_owned());
+    }
+
+    /// Looks up a directory through the `main` of its `package.json` or its `index` file.
+    fn resolve_directory(&self, dir: &Path) -> Option<String> {
+        let main = self
+            .read_json(&dir.join("package.json"))
+            .and_then(|package| package.get("main")?.as_str().map(str::to_string));
+        if let Some(main) = main {
+            let main = normalize(&dir.join(main));
+            if let Some(module) = self
+                .resolve_file(&main)
+                .or_else(|| self.resolve_file(&main.join("index")))
+            {
+                return Some(module);
+            }
+        }
+
+        self.resolve_file(&dir.join("index"))
+    }
+
+    /// Looks up a bare specifier through the `paths` and `baseUrl` of the nearest
+    /// `tsconfig.json` or `jsconfig.json`.
+    fn resolve_config_paths(&self, file_path: &Path, specifier: &str) -> Option<String> {
+        let (config_dir, config) = file_path.ancestors().skip(1).find_map(|dir| {
+            CONFIGS
+                .iter()
+                .find_map(|name| self.read_json(&dir.join(name)))
+                .map(|config| (dir, config))
+        })?;
+        let options = config.get("compilerOptions")?;
+        let base_dir = options
+            .get("baseUrl")
+            .and_then(Value::as_str)
+            .map(|base_url| config_dir.join(base_url));
+
+        if let Some(paths) = options.get("paths").and_then(Value::as_object) {
+            // The pattern with the longest prefix wins, and exact patterns beat them all.
+            let mut matches: Vec<(usize, &str, &Value)> = paths
+                .iter()
+                .filter_map(|(pattern, targets)| match pattern.split_once('*') {
+                    Some((prefix, suffix)) => {
+                        let matched = specifier.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                        Some((prefix.len(), matched, targets))
+                    }
+                    None => (pattern == specifier).then_some((usize::MAX, "", targets)),
+                })
+                .collect();
+            matches.sort_by_key(|(specificity, _, _)| std::cmp::Reverse(*specificity));
+
+            let paths_dir = base_dir.as_deref().unwrap_or(config_dir);
+            let module = matches.iter().find_map(|(_, matched, targets)| {
+                targets
+                    .as_array()?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find_map(|target| {
+                        self.resolve_path(&paths_dir.join(target.replace('*', matched)))
+                    })
+            });
+            if module.is_some() {
+                return module;
+            }
+        }
+
+        self.resolve_path(&base_dir?.join(specifier))
+    }
+
+    /// Looks up a bare specifier in the `node_modules` directories of the file's
+    /// ancestors.
+    fn resolve_package(&self, file_path: &Path, specifier: &str) -> Option<String> {
+        let (name, subpath) = split_specifier(specifier);
+        let package_dir = file_path
+            .ancestors()
+            .skip(1)
+            .map(|dir| dir.join("node_modules").join(name))
+            .find(|dir| {
+                self.consult(dir);
+                dir.is_dir()
+            })?;
+
+        // The `exports` of a package, if any, hide every file they do not list.
+        match self
+            .read_json(&package_dir.join("package.json"))
+            .as_ref()
+            .and_then(|package| package.get("exports"))
+        {
+            Some(exports) => {
+                let target = resolve_exports(exports, subpath)?;
+                self.resolve_file(&normalize(&package_dir.join(target)))
+            }
+            None => self.resolve_path(&package_dir.join(subpath)),
+        }
+    }
+}
+
+/// Checks if a specifier is a path rather than the name of a package.
+fn is_relative(specifier: &str) -> bool {
+    specifier == "."
+        || specifier == ".."
+        || specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || specifier.starts_with('/')
+}
+
+/// Splits a bare specifier into the name of its package, which may be scoped, and the
+/// path within the package.
+fn split_specifier(specifier: &str) -> (&str, &str) {
+    let separators = if specifier.starts_with('@') { 2 } else { 1 };
+    match specifier.match_indices('/').nth(separators - 1) {
+        Some((index, _)) => (&specifier[..index], &specifier[index + 1..]),
+        None => (specifier, ""),
+    }
+}
+
+/// Finds the file a package exports under a subpath.
+///
+/// # Arguments
+///
+/// * `exports` - The `exports` of the `package.json` of the package.
+/// * `subpath` - The path within the package, empty for the package itself.
+///
+/// # Returns
+///
+/// An `Option` containing the path of the file relative to the package, or `None` if
+/// the package does not export the subpath.
+fn resolve_exports(exports: &Value, subpath: &str) -> Option<String> {
+    let key = if subpath.is_empty() {
+        ".".to_string()
+    } else {
+        format!("./{}", subpath)
+    };
+
+    // Without subpath keys, `exports` is the target of the package itself.
+    let subpaths = exports
+        .as_object()
+        .filter(|map| map.keys().any(|key| key.starts_with('.')));
+    let Some(subpaths) = subpaths else {
+        return (key == ".").then(|| exports_target(exports, "")).flatten();
+    };
+
+    if let Some(target) = subpaths.get(&key) {
+        return exports_target(target, "");
+    }
+    subpaths
+        .iter()
+        .filter_map(|(pattern, target)| {
+            let (prefix, suffix) = pattern.split_once('*')?;
+            let matched = key.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some((prefix.len(), matched, target))
+        })
+        .max_by_key(|(specificity, _, _)| *specificity)
+        .and_then(|(_, matched, target)| exports_target(target, matched))
+}
+
+/// Picks the path of a target of `exports`, which may be conditional or a list of
+/// fallbacks, substituting `matched` for the `*` of patterns.
+fn exports_target(target: &Value, matched: &str) -> Option<String> {
+    match target {
+        Value::String(path) => Some(path.replace('*', matched)),
+        Value::Object(conditions) => conditions
+            .iter()
+            .filter(|(condition, _)| CONDITIONS.contains(&condition.as_str()))
+            .find_map(|(_, target)| exports_target(target, matched)),
+        Value::Array(targets) => targets
+            .iter()
+            .find_map(|target| exports_target(target, matched)),
+        _ => None,
+    }
+}
+
+/// Reads a JSON file, allowing the comments and trailing commas of `tsconfig.json`.
+fn read_json(path: &Path) -> Option<Value> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text)
+        .or_else(|_| serde_json::from_str(&strip_jsonc(&text)))
+        .ok()
+}
+
+/// Removes the comments and trailing commas of a JSON document, leaving strings as is.
+fn strip_jsonc(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                stripped.push(c);
+                while let Some(c) = chars.next() {
+                    stripped.push(c);
+                    match c {
+                        '\\' => stripped.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '}' | ']' => {
+                let trimmed = stripped.trim_end().len();
+                if stripped[..trimmed].ends_with(',') {
+                    stripped.truncate(trimmed - 1);
+                }
+                stripped.push(c);
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+/// Returns the name an import binds in the importing file.
+fn binding_of(import: &ImportTag) -> Option<&str> {
+    import.alias.as_deref().or(import.name.as_deref())
+}
+
+/// Creates the import of a whole module.
+fn module_import(module: String) -> ResolvedImport {
+    ResolvedImport {
+        module,
+        is_module: true,
+        name: None,
+        external: false,
+        dependencies: Vec::new(),
+    }
+}
+
+/// Creates the import of a definition of a module.
+fn item_import(module: String, name: &str) -> ResolvedImport {
+    ResolvedImport {
+        module,
+        is_module: false,
+        name: Some(name.to_string()),
+        external: false,
+        dependencies: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::block::Resolution;
+    use crate::config::Config;
+    use crate::fixture::Fixture;
+    use crate::indexer::Indexer;
+
+    #[test]
+    fn resolves_relative_package_and_path_imports() {
+        let fixture = Fixture::new(&[
+            (
+                "main.js",
+                "import helper from './lib/helper';\nimport { run as go } from './lib';\nimport * as ns from './lib/ns.js';\nimport { readFile } from 'fs';\nconst { tool } = require('pkg');\nconst util = require('@/util');\n\nfunction main() {\n  helper();\n  go();\n  ns.thing();\n  readFile();\n  tool();\n  util.fmt();\n}\n",
+            ),
+            ("lib/helper.js", "export default function helper() {}\n"),
+            ("lib/index.js", "export { run } from './runner';\n"),
+            ("lib/runner.js", "export function run() {}\n"),
+            ("lib/ns.js", "export function thing() {}\n"),
+            ("node_modules/pkg/package.json", "{\"main\": \"entry.js\"}\n"),
+            (
+                "node_modules/pkg/entry.js",
+                "function tool() {}\n\nmodule.exports = { tool };\n",
+            ),
+            ("src/util/index.js", "export function fmt() {}\n"),
+            (
+                "jsconfig.json",
+                "{\"compilerOptions\": {\"baseUrl\": \".\", \"paths\": {\"@/*\": [\"src/*\"]}}}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [
+                fixture.key("lib/helper.js", "helper"),
+                fixture.key("lib/runner.js", "run"),
+                fixture.key("lib/ns.js", "thing"),
+                "fs.readFile".to_string(),
+                fixture.key("node_modules/pkg/entry.js", "tool"),
+                fixture.key("src/util/index.js", "fmt"),
+            ]
+        );
+        assert_eq!(
+            index.call_stack.get_node("fs.readFile").unwrap().resolution,
+            Resolution::External {
+                module: "fs".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_aliased_imports_by_their_exported_name() {
+        let fixture = Fixture::new(&[
+            (
+                "main.js",
+                "import def, { run as go, stop as halt } from './lib.js';\nimport * as lib from './lib.js';\nconst { run: start } = require('./lib.js');\n\nfunction main() {\n  def();\n  go();\n  halt();\n  lib.stop();\n  start();\n}\n",
+            ),
+            (
+                "lib.js",
+                "function run() {}\nfunction stop() {}\nfunction fallback() {}\n\nexport { run, stop };\nexport default fallback;\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [
+                fixture.key("lib.js", "fallback"),
+                fixture.key("lib.js", "run"),
+                fixture.key("lib.js", "stop"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_subpaths_through_package_exports() {
+        let fixture = Fixture::new(&[
+            (
+                "main.js",
+                "import { tool } from 'pkg';\nimport { feature } from 'pkg/feature';\nimport { icon } from 'pkg/icons/star';\nimport { hidden } from 'pkg/internal';\n\nfunction main() {\n  tool();\n  feature();\n  icon();\n  hidden();\n}\n",
+            ),
+            (
+                "node_modules/pkg/package.json",
+                "{\"exports\": {\".\": {\"browser\": \"./browser.js\", \"import\": {\"types\": \"./index.d.js\", \"default\": \"./index.js\"}}, \"./feature\": [{\"worker\": \"./worker.js\"}, \"./feature.js\"], \"./icons/*\": \"./icons/*.js\"}}\n",
+            ),
+            ("node_modules/pkg/browser.js", "export function tool() {}\n"),
+            ("node_modules/pkg/index.js", "export function tool() {}\n"),
+            ("node_modules/pkg/feature.js", "export function feature() {}\n"),
+            ("node_modules/pkg/icons/star.js", "export function icon() {}\n"),
+            ("node_modules/pkg/internal.js", "export function hidden() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        // Targets without a supported condition are skipped, and files the `exports` do
+        // not list cannot be imported.
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [
+                fixture.key("node_modules/pkg/index.js", "tool"),
+                fixture.key("node_modules/pkg/feature.js", "feature"),
+                fixture.key("node_modules/pkg/icons/star.js", "icon"),
+                "pkg/internal.hidden".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_bare_specifiers_through_tsconfig_paths() {
+        let fixture = Fixture::new(&[
+            (
+                "app/main.ts",
+                "import { a } from '@lib/core';\nimport { b } from '@lib/special';\nimport { c } from '@ui/button';\nimport { d } from 'shared/util';\n\nfunction main() {\n  a();\n  b();\n  c();\n  d();\n}\n",
+            ),
+            ("src/lib/core.ts", "export function a() {}\n"),
+            ("src/special/index.ts", "export function b() {}\n"),
+            ("src/ui/button.tsx", "export function c() {}\n"),
+            ("src/shared/util.ts", "export function d() {}\n"),
+            (
+                "tsconfig.json",
+                "{\n  // Comments and trailing commas are allowed.\n  \"compilerOptions\": {\n    \"baseUrl\": \"src\",\n    \"paths\": {\n      \"@lib/*\": [\"lib/*\"],\n      \"@lib/special\": [\"special\"],\n      \"@ui/*\": [\"missing/*\", \"ui/*\"],\n    },\n  },\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        // Exact patterns beat wildcards, each target is tried in turn, and specifiers no
+        // pattern matches are looked up under `baseUrl`.
+        assert_eq!(
+            fixture.callees(&index, "app/main.ts", "main"),
+            [
+                fixture.key("src/lib/core.ts", "a"),
+                fixture.key("src/special/index.ts", "b"),
+                fixture.key("src/ui/button.tsx", "c"),
+                fixture.key("src/shared/util.ts", "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn picks_the_first_supported_exports_condition_in_document_order() {
+        let fixture = Fixture::new(&[
+            (
+                "main.js",
+                "import { tool } from 'pkg';\n\nfunction main() {\n  tool();\n}\n",
+            ),
+            (
+                "node_modules/pkg/package.json",
+                "{\"exports\": {\".\": {\"types\": \"./types.js\", \"require\": \"./cjs.js\", \"import\": \"./esm.js\"}}}\n",
+            ),
+            ("node_modules/pkg/types.js", "export function tool() {}\n"),
+            ("node_modules/pkg/cjs.js", "export function tool() {}\n"),
+            ("node_modules/pkg/esm.js", "export function tool() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [fixture.key("node_modules/pkg/cjs.js", "tool")]
+        );
+    }
+
+    #[test]
+    fn resolves_importers_again_when_their_config_files_change() {
+        let files = [
+            (
+                "main.js",
+                "import { fmt } from '@/util';\n\nfunction main() {\n  fmt();\n}\n",
+            ),
+            ("src/util.js", "export function fmt() {}\n"),
+            ("lib/util.js", "export function fmt() {}\n"),
+            (
+                "jsconfig.json",
+                "{\"compilerOptions\": {\"paths\": {\"@/*\": [\"./src/*\"]}}}\n",
+            ),
+        ];
+        let retarget = |fixture: &Fixture| {
+            let config = fixture.root().join("jsconfig.json");
+            let text = fs::read_to_string(&config).unwrap().replace("src", "lib");
+            fs::write(&config, text).unwrap();
+            config
+        };
+
+        // While watching, the changed path is given to the indexer.
+        let fixture = Fixture::new(&files);
+        let mut indexer = Indexer::new(Config::default(), fixture.root()).unwrap();
+        let config = retarget(&fixture);
+        indexer.update(&[config]);
+        assert_eq!(
+            fixture.callees(indexer.index(), "main.js", "main"),
+            [fixture.key("lib/util.js", "fmt")]
+        );
+
+        // Across runs, the cache notices the change.
+        let fixture = Fixture::new(&files);
+        let mut config = Config::default();
+        config.cache = Some(fixture.root().join(".stackwalk-cache.json"));
+        let index = fixture.index_with(&config);
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [fixture.key("src/util.js", "fmt")]
+        );
+        retarget(&fixture);
+        let index = fixture.index_with(&config);
+        assert_eq!(
+            fixture.callees(&index, "main.js", "main"),
+            [fixture.key("lib/util.js", "fmt")]
+        );
+    }
+}
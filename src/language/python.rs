@@ -0,0 +1,659 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tree_sitter::Language;
+
+use super::{LanguageSupport, ResolvedImport};
+use crate::query::ImportTag;
+use crate::resolver::ResolveContext;
+
+extern "C" {
+    fn tree_sitter_python() -> Language;
+}
+
+/// How many modules deep re-exported names are followed, which stops circular
+/// imports.
+const MAX_REEXPORT_DEPTH: usize = 8;
+
+/// The built-in query for Python.
+const TAGS: &str = include_str!("../../queries/python/tags.scm");
+
+/// The built-in functions, types and exceptions of Python, which need no import.
+const BUILTINS: [&str; 78] = [
+    "abs",
+    "aiter",
+    "all",
+    "anext",
+    "any",
+    "ascii",
+    "bin",
+    "bool",
+    "breakpoint",
+    "bytearray",
+    "bytes",
+    "callable",
+    "chr",
+    "classmethod",
+    "compile",
+    "complex",
+    "delattr",
+    "dict",
+    "dir",
+    "divmod",
+    "enumerate",
+    "eval",
+    "exec",
+    "filter",
+    "float",
+    "format",
+    "frozenset",
+    "getattr",
+    "globals",
+    "hasattr",
+    "hash",
+    "help",
+    "hex",
+    "id",
+    "input",
+    "int",
+    "isinstance",
+    "issubclass",
+    "iter",
+    "len",
+    "list",
+    "locals",
+    "map",
+    "max",
+    "memoryview",
+    "min",
+    "next",
+    "object",
+    "oct",
+    "open",
+    "ord",
+    "pow",
+    "print",
+    "property",
+    "range",
+    "repr",
+    "reversed",
+    "round",
+    "set",
+    "setattr",
+    "slice",
+    "sorted",
+    "staticmethod",
+    "str",
+    "sum",
+    "tuple",
+    "type",
+    "vars",
+    "zip",
+    "__import__",
+    "Exception",
+    "ValueError",
+    "TypeError",
+    "KeyError",
+    "IndexError",
+    "RuntimeError",
+    "NotImplementedError",
+    "AttributeError",
+];
+
+/// Support for Python source files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Python;
+
+impl LanguageSupport for Python {
+    fn name(&self) -> &str {
+        "python"
+    }
+
+    fn language(&self) -> Language {
+        unsafe { tree_sitter_python() }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["py"]
+    }
+
+    fn default_query(&self) -> Option<&str> {
+        Some(TAGS)
+    }
+
+    /// Resolves imports the way the import system does, against the directories that
+    /// would be on `sys.path` when the file runs.
+    ///
+    /// Relative imports are resolved against the package of the file. Absolute imports
+    /// are looked up, in order, in the root of the file's package (the directory of the
+    /// file if it is not in a package), the configured `sys_path` and the directory of
+    /// the nearest `pyproject.toml` and its `src` directory. An absolute import found
+    /// in none of them, like one of the standard library or an installed package, is
+    /// external, and so is one found in a directory outside the indexed tree. A name imported from a module that does not define it but imports
+    /// it itself, like a package re-exporting it from its `__init__.py`, resolves to
+    /// the module that defines it.
+    ///
+    /// Every file looked for along the way, whether it exists or not, such as the
+    /// candidate files of a module and `pyproject.toml`, is a dependency of the
+    /// resolved import.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        let probed = RefCell::default();
+        let mut resolved = self.resolve(import, file_path, context, &probed, 0)?;
+        resolved.dependencies = probed.into_inner().into_iter().collect();
+        Some(resolved)
+    }
+
+    /// Mirrors `__qualname__`, which inserts `<locals>` after every function.
+    fn locals_segment(&self) -> Option<&str> {
+        Some("<locals>")
+    }
+
+    fn closure_name(&self, index: usize) -> String {
+        format!("<lambda#{}>", index)
+    }
+
+    fn receiver_names(&self) -> &[&str] {
+        &["self", "cls"]
+    }
+
+    fn super_names(&self) -> &[&str] {
+        &["super"]
+    }
+
+    fn constructor_name(&self) -> Option<&str> {
+        Some("__init__")
+    }
+
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        BUILTINS.contains(&name).then_some("builtins")
+    }
+}
+
+impl Python {
+    /// Resolves an import, following re-exports up to `MAX_REEXPORT_DEPTH` deep and
+    /// recording the paths looked for in `probed`.
+    fn resolve(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+        probed: &Probed,
+        depth: usize,
+    ) -> Option<ResolvedImport> {
+        let level = import.module.chars().take_while(|&c| c == '.').count();
+        let segments: Vec<&str> = import.module[level..]
+            .split('.')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        let roots = if level > 0 {
+            let mut package_dir = file_path.parent()?;
+            for _ in 1..level {
+                package_dir = package_dir.parent()?;
+            }
+            vec![package_dir.to_path_buf()]
+        } else {
+            search_roots(file_path, &context.config.sys_path(self.name()), probed)
+        };
+
+        // A module that is not among the indexed files, like one found in a `sys_path`
+        // directory outside the tree, is external.
+        let external = || ResolvedImport {
+            module: import.module.clone(),
+            is_module: import.name.is_none(),
+            name: None,
+            external: true,
+            dependencies: Vec::new(),
+        };
+
+        roots
+            .iter()
+            .find_map(|root| {
+                // `from package import name` imports the submodule `name` if there is one.
+                if let Some(name) = &import.name {
+                    let submodule = [segments.as_slice(), &[name.as_str()]].concat();
+                    if let Some(module) = find_module(root, &submodule, probed) {
+                        if context.file(&module).is_none() {
+                            return Some(external());
+                        }
+                        return Some(ResolvedImport {
+                            module,
+                            is_module: true,
+                            name: None,
+                            external: false,
+                            dependencies: Vec::new(),
+                        });
+                    }
+                }
+
+                let module = find_module(root, &segments, probed)?;
+                if context.file(&module).is_none() {
+                    return Some(external());
+                }
+                let reexported = import
+                    .name
+                    .as_deref()
+                    .and_then(|name| self.resolve_reexport(&module, name, context, probed, depth));
+                Some(reexported.unwrap_or(ResolvedImport {
+                    module,
+                    is_module: import.name.is_none(),
+                    name: None,
+                    external: false,
+                    dependencies: Vec::new(),
+                }))
+            })
+            .or_else(|| (level == 0).then(external))
+    }
+
+    /// Finds the definition a module imports under a name it does not define itself.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ResolvedImport` of the definition, or `None` if the
+    /// module defines the name or does not import it.
+    fn resolve_reexport(
+        &self,
+        module: &str,
+        name: &str,
+        context: &ResolveContext,
+        probed: &Probed,
+        depth: usize,
+    ) -> Option<ResolvedImport> {
+        let file = context.file(module)?;
+        if depth >= MAX_REEXPORT_DEPTH || file.definitions.iter().any(|defined| defined == name) {
+            return None;
+        }
+
+        let file_path = Path::new(module);
+        let resolve_name = |import: &ImportTag| {
+            let mut resolved = self.resolve(import, file_path, context, probed, depth + 1)?;
+            if !resolved.is_module && resolved.name.is_none() {
+                resolved.name = import.name.clone();
+            }
+            Some(resolved)
+        };
+        // A name imported by name takes precedence over the names of wildcard imports.
+        file.imports
+            .iter()
+            .filter(|import| import.name.is_some())
+            .find(|import| import.alias.as_deref().or(import.name.as_deref()) == Some(name))
+            .and_then(resolve_name)
+            .or_else(|| {
+                file.imports
+                    .iter()
+                    .filter(|import| import.name.as_deref() == Some("*"))
+                    .find_map(|import| {
+                        let target = self.resolve(import, file_path, context, probed, depth + 1)?;
+                        let target_file = context.file(&target.module)?;
+                        let defines = target_file
+                            .definitions
+                            .iter()
+                            .any(|defined| defined == name);
+                        defines.then(|| ResolvedImport {
+                            module: target.module,
+                            is_module: false,
+                            name: Some(name.to_string()),
+                            external: false,
+                            dependencies: Vec::new(),
+                        })
+                    })
+            })
+    }
+}
+
+/// The paths of the files looked for while resolving an import.
+type Probed = RefCell<BTreeSet<String>>;
+
+/// Checks if a file exists, recording it as looked for.
+fn probe(path: &Path, probed: &Probed) -> bool {
+    probed
+        .borrow_mut()
+        .insert(path.to_string_lossy().into_owned());
+    path.is_file()
+}
+
+/// Returns the directories absolute imports in a file are looked up in, in order.
+///
+/// # Arguments
+///
+/// * `file_path` - The path of the file containing the imports.
+/// * `sys_path` - The configured additional directories.
+/// * `probed` - The paths looked for so far, which the files looked for are added to.
+///
+/// # Returns
+///
+/// A vector of the directories, without duplicates.
+fn search_roots(file_path: &Path, sys_path: &[PathBuf], probed: &Probed) -> Vec<PathBuf> {
+    let file_dir = file_path.parent().unwrap_or(Path::new(""));
+
+    // The root of a package is the parent of its outermost directory with an
+    // `__init__.py`.
+    let mut package_root = file_dir;
+    while probe(&package_root.join("__init__.py"), probed) {
+        match package_root.parent() {
+            Some(parent) => package_root = parent,
+            None => break,
+        }
+    }
+
+    let mut roots = vec![package_root.to_path_buf()];
+    roots.extend(sys_path.iter().cloned());
+    if let Some(project_dir) = file_dir
+        .ancestors()
+        .find(|dir| probe(&dir.join("pyproject.toml"), probed))
+    {
+        roots.push(project_dir.join("src"));
+        roots.push(project_dir.to_path_buf());
+    }
+
+    let mut unique_roots = Vec::new();
+    for root in roots {
+        if !unique_roots.contains(&root) {
+            unique_roots.push(root);
+        }
+    }
+    unique_roots
+}
+
+/// Finds the file of a module under a directory.
+///
+/// # Arguments
+///
+/// * `root` - The directory to look in.
+/// * `segments` - The segments of the dotted module name. With no segments, the
+///   package `root` itself is looked up.
+/// * `probed` - The paths looked for so far, which the files looked for are added to.
+///
+/// # Returns
+///
+/// An `Option` containing the path of the module's `.py` file or of its package's
+/// `__init__.py`, or `None` if there is no such module.
+fn find_module(root: &Path, segments: &[&str], probed: &Probed) -> Option<String> {
+    let package_dir = segments
+        .iter()
+        .fold(root.to_path_buf(), |dir, segment| dir.join(segment));
+    let module_file = segments.split_last().map(|(name, parents)| {
+        parents
+            .iter()
+            .fold(root.to_path_buf(), |dir, segment| dir.join(segment))
+            .join(format!("{}.py", name))
+    });
+
+    module_file
+        .into_iter()
+        .chain([package_dir.join("__init__.py")])
+        .find(|path| probe(path, probed))
+        .and_then(|path| path.to_str().map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::block::Resolution;
+    use crate::config::{Config, Language};
+    use crate::fixture::Fixture;
+    use crate::indexer::Indexer;
+
+    #[test]
+    fn resolves_relative_imports_against_the_package() {
+        let fixture = Fixture::new(&[
+            ("pkg/__init__.py", ""),
+            ("pkg/util.py", "def helper():\n    pass\n"),
+            (
+                "pkg/main.py",
+                "from .util import helper\nfrom . import util\n\ndef main():\n    helper()\n    util.helper()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "pkg/main.py", "main"),
+            [fixture.key("pkg/util.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn resolves_absolute_and_aliased_imports_against_the_package_root() {
+        let fixture = Fixture::new(&[
+            ("app/__init__.py", ""),
+            ("app/util.py", "def helper():\n    pass\n\ndef other():\n    pass\n"),
+            (
+                "app/main.py",
+                "import app.util\nimport app.util as u\nfrom app.util import other as o\n\ndef main():\n    app.util.helper()\n    u.other()\n    o()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "app/main.py", "main"),
+            [
+                fixture.key("app/util.py", "helper"),
+                fixture.key("app/util.py", "other"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_each_name_of_a_relative_from_import() {
+        let fixture = Fixture::new(&[
+            ("pkg/__init__.py", "def top():\n    pass\n"),
+            (
+                "pkg/util.py",
+                "def helper():\n    pass\n\ndef other():\n    pass\n",
+            ),
+            ("pkg/sub/__init__.py", ""),
+            ("pkg/sub/sibling.py", "def run():\n    pass\n"),
+            (
+                "pkg/sub/main.py",
+                "from . import sibling\nfrom .. import util, top\nfrom ..util import helper as h, other\n\ndef main():\n    sibling.run()\n    top()\n    util.helper()\n    other()\n\ndef aliased():\n    h()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "pkg/sub/main.py", "main"),
+            [
+                fixture.key("pkg/sub/sibling.py", "run"),
+                fixture.key("pkg/__init__.py", "top"),
+                fixture.key("pkg/util.py", "helper"),
+                fixture.key("pkg/util.py", "other"),
+            ]
+        );
+        assert_eq!(
+            fixture.callees(&index, "pkg/sub/main.py", "aliased"),
+            [fixture.key("pkg/util.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn follows_names_re_exported_by_a_package() {
+        let fixture = Fixture::new(&[
+            ("pkg/__init__.py", "from .impl import helper\n"),
+            ("pkg/impl.py", "def helper():\n    pass\n"),
+            (
+                "main.py",
+                "from pkg import helper\n\ndef main():\n    helper()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.py", "main"),
+            [fixture.key("pkg/impl.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn follows_names_re_exported_through_wildcard_imports() {
+        let fixture = Fixture::new(&[
+            ("pkg/__init__.py", "from .impl import *\n"),
+            ("pkg/impl.py", "def helper():\n    pass\n"),
+            (
+                "main.py",
+                "import pkg\nfrom pkg import helper\n\ndef main():\n    helper()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.py", "main"),
+            [fixture.key("pkg/impl.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn imports_found_nowhere_in_the_tree_are_external() {
+        let fixture = Fixture::new(&[(
+            "main.py",
+            "import os.path\nfrom json import dumps\n\ndef main():\n    os.path.join()\n    dumps()\n",
+        )]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.py", "main"),
+            ["os.path.join", "json.dumps"]
+        );
+        assert!(index
+            .call_stack
+            .get_node("json.dumps")
+            .unwrap()
+            .resolution
+            .is_external());
+    }
+
+    #[test]
+    fn looks_imports_up_in_the_configured_sys_path() {
+        let fixture = Fixture::new(&[
+            ("vendor/lib.py", "def helper():\n    pass\n"),
+            (
+                "src/main.py",
+                "from lib import helper\n\ndef main():\n    helper()\n",
+            ),
+        ]);
+        let mut config = Config::from_toml("[languages.python]\nsys_path = [\"vendor\"]").unwrap();
+        config.config_dir = Some(fixture.root().to_path_buf());
+        let index = fixture.index_with(&config);
+
+        assert_eq!(
+            fixture.callees(&index, "src/main.py", "main"),
+            [fixture.key("vendor/lib.py", "helper")]
+        );
+    }
+
+    #[test]
+    fn looks_imports_up_in_the_project_roots() {
+        let fixture = Fixture::new(&[
+            ("pyproject.toml", "[project]\nname = \"app\"\n"),
+            ("src/app/__init__.py", ""),
+            ("src/app/core.py", "def run():\n    pass\n"),
+            ("tools.py", "def lint():\n    pass\n"),
+            (
+                "tests/test_core.py",
+                "from app.core import run\nimport tools as t\n\ndef test_run():\n    run()\n    t.lint()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        // Both `src` and the project directory itself are searched.
+        assert_eq!(
+            fixture.callees(&index, "tests/test_core.py", "test_run"),
+            [
+                fixture.key("src/app/core.py", "run"),
+                fixture.key("tools.py", "lint"),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_fallback_imports_found_in_the_tree() {
+        let fixture = Fixture::new(&[
+            ("compat.py", "def dumps():\n    pass\n\ndef loads():\n    pass\n"),
+            (
+                "main.py",
+                "try:\n    from ujson import dumps\nexcept ImportError:\n    from compat import dumps\n\ntry:\n    from compat import loads\nexcept ModuleNotFoundError:\n    from json import loads\n\ndef main():\n    dumps()\n    loads()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "main.py", "main"),
+            [
+                fixture.key("compat.py", "dumps"),
+                fixture.key("compat.py", "loads"),
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_found_in_a_sys_path_outside_the_tree_are_external() {
+        let vendor = Fixture::new(&[("lib.py", "def helper():\n    pass\n")]);
+        let fixture = Fixture::new(&[(
+            "main.py",
+            "from lib import helper\nimport lib\n\ndef main():\n    helper()\n    lib.helper()\n",
+        )]);
+        let mut config = Config::default();
+        config.languages.insert(
+            "python".to_string(),
+            Language {
+                queries: None,
+                sys_path: vec![vendor.root().to_path_buf()],
+            },
+        );
+        let index = fixture.index_with(&config);
+
+        assert_eq!(fixture.callees(&index, "main.py", "main"), ["lib.helper"]);
+        assert_eq!(
+            index.call_stack.get_node("lib.helper").unwrap().resolution,
+            Resolution::External {
+                module: "lib".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_importers_again_when_a_file_they_looked_for_appears() {
+        let files = [
+            (
+                "scripts/main.py",
+                "from tools import helper\n\ndef main():\n    helper()\n",
+            ),
+            ("src/tools.py", "def helper():\n    pass\n"),
+        ];
+        // With a `pyproject.toml`, `src` is searched too.
+        let add_project = |fixture: &Fixture| {
+            let project = fixture.root().join("pyproject.toml");
+            fs::write(&project, "[project]\nname = \"app\"\n").unwrap();
+            project
+        };
+
+        let fixture = Fixture::new(&files);
+        let mut indexer = Indexer::new(Config::default(), fixture.root()).unwrap();
+        assert_eq!(
+            fixture.callees(indexer.index(), "scripts/main.py", "main"),
+            ["tools.helper"]
+        );
+        let project = add_project(&fixture);
+        indexer.update(&[project]);
+        assert_eq!(
+            fixture.callees(indexer.index(), "scripts/main.py", "main"),
+            [fixture.key("src/tools.py", "helper")]
+        );
+
+        let fixture = Fixture::new(&files);
+        let mut config = Config::default();
+        config.cache = Some(fixture.root().join(".stackwalk-cache.json"));
+        fixture.index_with(&config);
+        add_project(&fixture);
+        let index = fixture.index_with(&config);
+        assert_eq!(
+            fixture.callees(&index, "scripts/main.py", "main"),
+            [fixture.key("src/tools.py", "helper")]
+        );
+    }
+}
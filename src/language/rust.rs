@@ -0,0 +1,967 @@
+use std::path::{Path, PathBuf};
+use tree_sitter::{Language, Node, Query};
+
+use super::{normalize, LanguageSupport, ResolvedImport};
+use crate::cargo::{ExternCrate, Package};
+use crate::indexer::generate_node_key;
+use crate::parser::ParsedFile;
+use crate::query::{collect_tags, ImportTag, Tags};
+use crate::resolver::ResolveContext;
+
+extern "C" {
+    fn tree_sitter_rust() -> Language;
+}
+
+/// The macros of the standard library and the items of its prelude, which need no
+/// `use`.
+const PRELUDE: [&str; 37] = [
+    "println",
+    "print",
+    "eprintln",
+    "eprint",
+    "format",
+    "format_args",
+    "vec",
+    "panic",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "write",
+    "writeln",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "matches",
+    "dbg",
+    "include_str",
+    "include_bytes",
+    "env",
+    "concat",
+    "stringify",
+    "String",
+    "Vec",
+    "Box",
+    "Option",
+    "Some",
+    "Result",
+    "Ok",
+    "Err",
+    "drop",
+    "Default",
+    "ToString",
+];
+
+/// The built-in query for Rust.
+const TAGS: &str = include_str!("../../queries/rust/tags.scm");
+
+/// The file names of crate roots, which own the directory they are in like `mod.rs`.
+const CRATE_ROOTS: [&str; 2] = ["lib.rs", "main.rs"];
+
+/// How many `use` declarations are followed to find a re-exported definition.
+const MAX_REEXPORT_DEPTH: usize = 8;
+
+/// Support for Rust source files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rust;
+
+impl LanguageSupport for Rust {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn language(&self) -> Language {
+        unsafe { tree_sitter_rust() }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rs"]
+    }
+
+    fn default_query(&self) -> Option<&str> {
+        Some(TAGS)
+    }
+
+    /// Collects the tags of the query, plus the imports of every `use` declaration.
+    ///
+    /// Use trees such as `use a::{b, c::{d as e, *}}` nest arbitrarily, so they are
+    /// flattened here rather than in the query. Every `mod` declaration also binds the
+    /// name of the module, like `use self::name;` would. The imports made inside an
+    /// inline module record it as their parent, as they only apply to that module.
+    fn extract_tags(&self, query: &Query, root: Node, code: &str) -> Tags {
+        let mut tags = collect_tags(query, root, code);
+
+        collect_uses(root, code, &mut Vec::new(), &mut tags.imports);
+
+        for module in &tags.modules {
+            tags.imports.push(ImportTag {
+                module: "self".to_string(),
+                name: Some(module.name.clone()),
+                alias: None,
+                fallback: false,
+                reexport: false,
+                parents: module.parents.clone(),
+            });
+        }
+
+        tags
+    }
+
+    /// Resolves `use` paths against the module tree of the file's crate.
+    ///
+    /// The module tree is built from the crate root, `lib.rs` or `main.rs`, by following
+    /// `mod` declarations to `name.rs` or `name/mod.rs`, or to the file named by a
+    /// `#[path]` attribute. Paths may start with `crate`, `self`, `super`, a submodule or
+    /// a definition of the current module, which is the inline module the import is
+    /// made in, if any. Names that a module re-exports with `pub use` are followed to
+    /// the module that defines them.
+    ///
+    /// Paths may also start with the name of another crate: a dependency declared in
+    /// the `Cargo.toml` of the file's package, or a built-in crate such as `std`.
+    /// Crates of the indexed tree are resolved through their own module tree, and any
+    /// other crate is resolved to an external module named after the crate.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        let file = context.file(file_path.to_str()?)?;
+        let (root, mut current) = crate_of(context, file);
+        current.extend(import.parents.iter().cloned());
+
+        let mut segments: Vec<&str> = import.module.split("::").collect();
+        let wildcard = import.name.as_deref() == Some("*");
+        if let Some(name) = import.name.as_deref().filter(|_| !wildcard) {
+            segments.push(name);
+        }
+
+        let tree = ModuleTree::new(context, root);
+        let resolved = tree.resolve(&current, &segments, 0)?;
+
+        // A wildcard imports the definitions of a module, not one of its items.
+        if wildcard && !resolved.is_module {
+            return None;
+        }
+
+        Some(resolved)
+    }
+
+    fn path_separator(&self) -> &str {
+        "::"
+    }
+
+    /// `Self` is included, as it names the type of the enclosing `impl` block.
+    fn receiver_names(&self) -> &[&str] {
+        &["self", "Self"]
+    }
+
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        PRELUDE.contains(&name).then_some("std")
+    }
+}
+
+/// Collects the imports of the `use` declarations under a node, descending into the
+/// bodies of inline modules.
+///
+/// # Arguments
+///
+/// * `node` - The node to search.
+/// * `code` - The code string of the file.
+/// * `parents` - The names of the inline modules enclosing `node`, outermost first.
+/// * `imports` - The imports to append to.
+fn collect_uses(node: Node, code: &str, parents: &mut Vec<String>, imports: &mut Vec<ImportTag>) {
+    if node.kind() == "use_declaration" {
+        let reexport = (0..node.named_child_count())
+            .filter_map(|index| node.named_child(index))
+            .any(|child| child.kind() == "visibility_modifier");
+        if let Some(argument) = node.child_by_field_name("argument") {
+            let start = imports.len();
+            flatten_use_tree(argument, code, "", reexport, imports);
+            for import in &mut imports[start..] {
+                import.parents = parents.clone();
+            }
+        }
+        return;
+    }
+
+    if node.kind() == "mod_item" {
+        let (Some(name), Some(body)) = (
+            node.child_by_field_name("name"),
+            node.child_by_field_name("body"),
+        ) else {
+            return;
+        };
+        parents.push(
+            name.utf8_text(code.as_bytes())
+                .unwrap_or_default()
+                .to_string(),
+        );
+        collect_uses(body, code, parents, imports);
+        parents.pop();
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_uses(child, code, parents, imports);
+    }
+}
+
+/// Flattens a use tree into one `ImportTag` per imported binding.
+///
+/// # Arguments
+///
+/// * `node` - The use tree, i.e. the argument of a `use` declaration or an item of a
+///   use list.
+/// * `code` - The code string of the file.
+/// * `prefix` - The path of the enclosing use lists, e.g. `a::c` for the `d` of
+///   `use a::{b, c::{d}}`.
+/// * `reexport` - Whether the `use` declaration is public.
+/// * `imports` - The imports to append to.
+fn flatten_use_tree(
+    node: Node,
+    code: &str,
+    prefix: &str,
+    reexport: bool,
+    imports: &mut Vec<ImportTag>,
+) {
+    let text = |node: Node| {
+        node.utf8_text(code.as_bytes())
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<String>()
+    };
+    let join = |path: String| match (prefix.is_empty(), path.as_str()) {
+        (true, _) => path,
+        // `self` in a use list imports the module the list is in.
+        (false, "self") => prefix.to_string(),
+        (false, _) => format!("{}::{}", prefix, path),
+    };
+    let mut import = |path: String, alias: Option<String>| {
+        let (module, name) = match path.rsplit_once("::") {
+            Some((module, name)) => (module.to_string(), Some(name.to_string())),
+            None => (path, None),
+        };
+        imports.push(ImportTag {
+            module,
+            name,
+            alias,
+            fallback: false,
+            reexport,
+            parents: Vec::new(),
+        });
+    };
+
+    match node.kind() {
+        "use_as_clause" => {
+            let (Some(path), Some(alias)) = (
+                node.child_by_field_name("path"),
+                node.child_by_field_name("alias"),
+            ) else {
+                return;
+            };
+            let alias = text(alias);
+            // `use path as _` only brings traits into scope, which binds no name.
+            if alias != "_" {
+                import(join(text(path)), Some(alias));
+            }
+        }
+        "use_list" => {
+            for index in 0..node.named_child_count() {
+                if let Some(child) = node.named_child(index) {
+                    flatten_use_tree(child, code, prefix, reexport, imports);
+                }
+            }
+        }
+        "scoped_use_list" => {
+            let prefix = match node.child_by_field_name("path") {
+                Some(path) => join(text(path)),
+                None => prefix.to_string(),
+            };
+            if let Some(list) = node.child_by_field_name("list") {
+                flatten_use_tree(list, code, &prefix, reexport, imports);
+            }
+        }
+        "use_wildcard" => {
+            let module = match node.named_child(0) {
+                Some(path) => join(text(path)),
+                None => prefix.to_string(),
+            };
+            imports.push(ImportTag {
+                module,
+                name: Some("*".to_string()),
+                alias: None,
+                fallback: false,
+                reexport,
+                parents: Vec::new(),
+            });
+        }
+        _ => import(join(text(node)), None),
+    }
+}
+
+/// A module of a crate: the file it is defined in and the inline modules enclosing it
+/// in that file.
+#[derive(Clone)]
+struct Module<'a> {
+    /// The file the module is defined in.
+    file: &'a ParsedFile,
+    /// The names of the inline modules the module is nested in, outermost first.
+    inline: Vec<String>,
+}
+
+impl Module<'_> {
+    /// Checks if the module defines an item directly, rather than in a submodule. The
+    /// items of an inline module are the functions and types defined in its body.
+    fn defines(&self, name: &str) -> bool {
+        if self.inline.is_empty() {
+            return self
+                .file
+                .definitions
+                .iter()
+                .any(|definition| definition == name);
+        }
+
+        let module_key = generate_node_key(
+            Path::new(&self.file.module_name),
+            None,
+            &self.inline.join("."),
+        );
+        self.file.classes.iter().any(|class| {
+            (class.node_key == module_key && class.methods.iter().any(|method| method == name))
+                || class.node_key == format!("{}.{}", module_key, name)
+        })
+    }
+}
+
+/// The module tree of a crate, built on demand from the `mod` declarations of the
+/// parsed files.
+struct ModuleTree<'a, 'b> {
+    /// The indexed tree.
+    context: &'b ResolveContext<'a>,
+    /// The root file of the crate.
+    root: &'a ParsedFile,
+    /// The Cargo package the crate belongs to, if any.
+    package: Option<&'a Package>,
+}
+
+impl<'a, 'b> ModuleTree<'a, 'b> {
+    /// Creates the `ModuleTree` of the crate rooted at a file.
+    fn new(context: &'b ResolveContext<'a>, root: &'a ParsedFile) -> Self {
+        ModuleTree {
+            context,
+            root,
+            package: context.workspace.package_of(Path::new(&root.module_name)),
+        }
+    }
+
+    /// Returns the submodule of a module with a given name.
+    fn child(&self, module: &Module<'a>, name: &str) -> Option<Module<'a>> {
+        let declaration = module
+            .file
+            .modules
+            .iter()
+            .find(|tag| tag.name == name && tag.parents == module.inline)?;
+
+        if declaration.inline {
+            let mut inline = module.inline.clone();
+            inline.push(name.to_string());
+            return Some(Module {
+                file: module.file,
+                inline,
+            });
+        }
+
+        let file_path = Path::new(&module.file.module_name);
+        let dir = file_path.parent().unwrap_or(Path::new(""));
+        let owns_dir = is_crate_root(module.file) || file_path.ends_with("mod.rs");
+        // Non-`mod.rs` files keep their submodules in a directory named after them.
+        let module_dir = if owns_dir {
+            dir.to_path_buf()
+        } else {
+            dir.join(file_path.file_stem()?)
+        };
+
+        let candidates = match &declaration.path {
+            Some(path) if module.inline.is_empty() => vec![dir.join(path)],
+            Some(path) => vec![join_all(&module_dir, &module.inline).join(path)],
+            None => {
+                let base = join_all(&module_dir, &module.inline);
+                vec![
+                    base.join(format!("{}.rs", name)),
+                    base.join(name).join("mod.rs"),
+                ]
+            }
+        };
+
+        candidates.iter().find_map(|candidate| {
+            let file = self.context.file(normalize(candidate).to_str()?)?;
+            Some(Module {
+                file,
+                inline: Vec::new(),
+            })
+        })
+    }
+
+    /// Returns the module at a path of module names from the crate root.
+    fn module(&self, path: &[String]) -> Option<Module<'a>> {
+        path.iter().try_fold(
+            Module {
+                file: self.root,
+                inline: Vec::new(),
+            },
+            |module, name| self.child(&module, name),
+        )
+    }
+
+    /// Returns the path of module names from the crate root to the module of a file.
+    fn path_of(&self, file: &ParsedFile) -> Option<Vec<String>> {
+        // Without `#[path]` attributes, the path follows the directory layout.
+        let root_dir = Path::new(&self.root.module_name).parent()?;
+        let relative = Path::new(&file.module_name).strip_prefix(root_dir).ok()?;
+        let mut guess: Vec<String> = relative
+            .components()
+            .filter_map(|component| component.as_os_str().to_str().map(str::to_string))
+            .collect();
+        match guess.pop().as_deref() {
+            Some("mod.rs") | None => {}
+            Some(name) => guess.push(name.trim_end_matches(".rs").to_string()),
+        }
+        if std::ptr::eq(file, self.root) {
+            return Some(Vec::new());
+        }
+        if let Some(module) = self.module(&guess) {
+            if std::ptr::eq(module.file, file) {
+                return Some(guess);
+            }
+        }
+
+        // Otherwise, search the whole tree.
+        let mut stack = vec![(
+            Module {
+                file: self.root,
+                inline: Vec::new(),
+            },
+            Vec::new(),
+        )];
+        let mut visited = vec![self.root.module_name.as_str()];
+        while let Some((module, path)) = stack.pop() {
+            for declaration in &module.file.modules {
+                let Some(child) = declaration
+                    .parents
+                    .starts_with(&module.inline)
+                    .then(|| self.child_at(&module, &declaration.parents, &declaration.name))
+                    .flatten()
+                else {
+                    continue;
+                };
+                let mut child_path = path.clone();
+                child_path.extend(declaration.parents[module.inline.len()..].iter().cloned());
+                child_path.push(declaration.name.clone());
+
+                if child.inline.is_empty() {
+                    if std::ptr::eq(child.file, file) {
+                        return Some(child_path);
+                    }
+                    if visited.contains(&child.file.module_name.as_str()) {
+                        continue;
+                    }
+                    visited.push(child.file.module_name.as_str());
+                    stack.push((child, child_path));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the submodule `name` declared in the inline modules `parents` of a file.
+    fn child_at(&self, module: &Module<'a>, parents: &[String], name: &str) -> Option<Module<'a>> {
+        let parent = Module {
+            file: module.file,
+            inline: parents.to_vec(),
+        };
+        self.child(&parent, name)
+    }
+
+    /// Resolves a path as written in the module at `current`.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The path of the module the path is written in.
+    /// * `segments` - The segments of the path.
+    /// * `depth` - The number of re-exports followed so far.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ResolvedImport`, or `None` if the path does not lead
+    /// into the crate.
+    fn resolve(
+        &self,
+        current: &[String],
+        segments: &[&str],
+        depth: usize,
+    ) -> Option<ResolvedImport> {
+        let (first, rest) = segments.split_first()?;
+        let mut path = current.to_vec();
+        let mut rest = rest;
+
+        match *first {
+            "crate" => path.clear(),
+            "self" => {}
+            "super" => {
+                path.pop()?;
+                while rest.first() == Some(&"super") {
+                    path.pop()?;
+                    rest = &rest[1..];
+                }
+            }
+            // `::name` always starts with a crate name.
+            "" => return self.resolve_extern(rest, depth),
+            name => {
+                // Names of the module itself shadow the names of crates.
+                let module = self.module(&path)?;
+                let is_local = self.child(&module, name).is_some() || module.defines(name);
+                if !is_local {
+                    if let Some(resolved) = self.resolve_extern(segments, depth) {
+                        return Some(resolved);
+                    }
+                    // A crate the package does not declare, or any crate if there is no
+                    // manifest, is external, unless the module imports the name.
+                    let mut local = vec!["self"];
+                    local.extend(segments);
+                    let resolved = self.resolve(current, &local, depth)?;
+                    if !self.defines(&resolved) && is_crate_name(name) {
+                        return Some(external_crate(name, rest));
+                    }
+                    return Some(resolved);
+                }
+                rest = segments;
+            }
+        }
+
+        let mut module = self.module(&path)?;
+        while let Some((name, remaining)) = rest.split_first() {
+            match self.child(&module, name) {
+                Some(child) => {
+                    module = child;
+                    path.push(name.to_string());
+                    rest = remaining;
+                }
+                None => return self.resolve_item(&module, &path, rest, depth),
+            }
+        }
+
+        Some(ResolvedImport {
+            module: module.file.module_name.clone(),
+            is_module: true,
+            name: None,
+            external: false,
+            dependencies: Vec::new(),
+        })
+    }
+
+    /// Resolves a path that starts with the name of another crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - The segments of the path, starting with the crate name.
+    /// * `depth` - The number of re-exports followed so far.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `ResolvedImport`, which is marked external if the
+    /// crate was not indexed, or `None` if the name is not a crate known to the package.
+    fn resolve_extern(&self, segments: &[&str], depth: usize) -> Option<ResolvedImport> {
+        let (name, rest) = segments.split_first()?;
+
+        match self.context.workspace.extern_crate(self.package, name)? {
+            ExternCrate::Package(package) => {
+                let root = self.context.file(normalize(&package.lib_path).to_str()?)?;
+                let tree = ModuleTree::new(self.context, root);
+                let mut path = vec!["crate"];
+                path.extend(rest);
+                tree.resolve(&[], &path, depth + 1)
+            }
+            ExternCrate::External => Some(external_crate(name, rest)),
+        }
+    }
+
+    /// Resolves the path of an item within a module, following re-exports.
+    ///
+    /// # Arguments
+    ///
+    /// * `module` - The module the item is looked up in.
+    /// * `path` - The path of `module` from the crate root.
+    /// * `segments` - The path of the item within the module, e.g. `Person::new`.
+    /// * `depth` - The number of re-exports followed so far.
+    fn resolve_item(
+        &self,
+        module: &Module<'a>,
+        path: &[String],
+        segments: &[&str],
+        depth: usize,
+    ) -> Option<ResolvedImport> {
+        let (name, rest) = segments.split_first()?;
+        // The items of an inline module are keyed under its path in the file.
+        let item_path: Vec<&str> = module
+            .inline
+            .iter()
+            .map(String::as_str)
+            .chain(segments.iter().copied())
+            .collect();
+        let defined = ResolvedImport {
+            module: module.file.module_name.clone(),
+            is_module: false,
+            name: Some(item_path.join(".")),
+            external: false,
+            dependencies: Vec::new(),
+        };
+        if module.defines(name) || depth >= MAX_REEXPORT_DEPTH {
+            return Some(defined);
+        }
+
+        // The name may be imported into the module, e.g. re-exported with `pub use`.
+        let imports = module
+            .file
+            .imports
+            .iter()
+            .filter(|import| import.parents == module.inline);
+        for import in imports.clone() {
+            let binding = import.alias.as_ref().or(import.name.as_ref());
+            if binding.map(String::as_str) != Some(*name) || import.module == "self" {
+                continue;
+            }
+            let mut imported: Vec<&str> = import.module.split("::").collect();
+            imported.extend(import.name.as_deref());
+            imported.extend(rest);
+            if let Some(resolved) = self.resolve(path, &imported, depth + 1) {
+                return Some(resolved);
+            }
+        }
+        for import in imports {
+            if import.name.as_deref() != Some("*") {
+                continue;
+            }
+            let mut imported: Vec<&str> = import.module.split("::").collect();
+            imported.extend(segments);
+            if let Some(resolved) = self
+                .resolve(path, &imported, depth + 1)
+                .filter(|resolved| self.defines(resolved))
+            {
+                return Some(resolved);
+            }
+        }
+
+        Some(defined)
+    }
+
+    /// Checks if the item a path resolved to is defined in the file it resolved to.
+    fn defines(&self, resolved: &ResolvedImport) -> bool {
+        let first = resolved
+            .name
+            .as_deref()
+            .and_then(|name| name.split('.').next());
+        match (self.context.file(&resolved.module), first) {
+            (Some(file), Some(first)) => file.definitions.iter().any(|name| name == first),
+            _ => resolved.is_module,
+        }
+    }
+}
+
+/// Finds the crate a file belongs to and the path of its module in that crate.
+///
+/// # Arguments
+///
+/// * `context` - The `ResolveContext` of the indexed tree.
+/// * `file` - The file to locate.
+///
+/// # Returns
+///
+/// The root file of the crate and the path of the file's module. A file that is not
+/// part of any crate tree is treated as the root of a crate of its own.
+fn crate_of<'a>(
+    context: &ResolveContext<'a>,
+    file: &'a ParsedFile,
+) -> (&'a ParsedFile, Vec<String>) {
+    let file_path = Path::new(&file.module_name);
+
+    for dir in file_path.ancestors().skip(1) {
+        for root_name in CRATE_ROOTS {
+            let Some(root) = dir
+                .join(root_name)
+                .to_str()
+                .and_then(|root_path| context.file(root_path))
+            else {
+                continue;
+            };
+            let tree = ModuleTree::new(context, root);
+            if let Some(path) = tree.path_of(file) {
+                return (root, path);
+            }
+        }
+    }
+
+    (file, Vec::new())
+}
+
+/// Creates the import of a path into a crate outside the indexed tree.
+///
+/// # Arguments
+///
+/// * `name` - The name of the crate.
+/// * `rest` - The segments of the path after the crate name.
+fn external_crate(name: &str, rest: &[&str]) -> ResolvedImport {
+    ResolvedImport {
+        module: name.to_string(),
+        is_module: rest.is_empty(),
+        name: (!rest.is_empty()).then(|| rest.join(".")),
+        external: true,
+        dependencies: Vec::new(),
+    }
+}
+
+/// Checks if a name can be the name of a crate, which is written in snake case, as
+/// opposed to the name of a type or a constant.
+fn is_crate_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Checks if a file is the root of a crate.
+fn is_crate_root(file: &ParsedFile) -> bool {
+    Path::new(&file.module_name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| CRATE_ROOTS.contains(&name))
+}
+
+/// Appends path segments to a directory.
+fn join_all(dir: &Path, segments: &[String]) -> PathBuf {
+    segments
+        .iter()
+        .fold(dir.to_path_buf(), |dir, segment| dir.join(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::Resolution;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn resolves_use_trees_through_mod_declarations() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "mod shapes;\nmod util;\n\nuse shapes::{circle::area, square::{self, perimeter as p}};\n\npub fn run() {\n    area();\n    p();\n    square::side();\n    util::helper();\n}\n",
+            ),
+            ("src/shapes/mod.rs", "pub mod circle;\npub mod square;\n"),
+            ("src/shapes/circle.rs", "pub fn area() {}\n"),
+            ("src/shapes/square.rs", "pub fn perimeter() {}\npub fn side() {}\n"),
+            ("src/util.rs", "pub fn helper() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "run"),
+            [
+                fixture.key("src/shapes/circle.rs", "area"),
+                fixture.key("src/shapes/square.rs", "perimeter"),
+                fixture.key("src/shapes/square.rs", "side"),
+                fixture.key("src/util.rs", "helper"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_crate_self_and_super_paths() {
+        let fixture = Fixture::new(&[
+            ("src/main.rs", "mod a;\n\nfn top() {}\n\nfn main() {\n    a::b::run();\n}\n"),
+            ("src/a.rs", "pub mod b;\n\npub fn sibling() {}\n"),
+            (
+                "src/a/b.rs",
+                "pub fn run() {\n    crate::top();\n    super::sibling();\n    self::local();\n}\n\nfn local() {}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "src/a/b.rs", "run"),
+            [
+                fixture.key("src/main.rs", "top"),
+                fixture.key("src/a.rs", "sibling"),
+                fixture.key("src/a/b.rs", "local"),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_pub_use_re_exports() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "mod api;\nmod inner;\n\npub fn run() {\n    api::helper();\n    api::other();\n}\n",
+            ),
+            ("src/api.rs", "pub use crate::inner::helper;\npub use crate::inner::deep::*;\n"),
+            ("src/inner/mod.rs", "pub mod deep;\n\npub fn helper() {}\n"),
+            ("src/inner/deep.rs", "pub fn other() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "run"),
+            [
+                fixture.key("src/inner/mod.rs", "helper"),
+                fixture.key("src/inner/deep.rs", "other"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_inline_modules_and_path_attributes() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "#[path = \"elsewhere/impl.rs\"]\nmod renamed;\n\nmod inline {\n    pub fn nested() {}\n}\n\npub fn run() {\n    renamed::helper();\n    inline::nested();\n}\n",
+            ),
+            ("src/elsewhere/impl.rs", "pub fn helper() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        let nested = fixture.key("src/lib.rs", "inline.nested");
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "run"),
+            [
+                fixture.key("src/elsewhere/impl.rs", "helper"),
+                nested.clone()
+            ]
+        );
+        assert!(index.call_stack.get_node(&nested).is_some());
+    }
+
+    #[test]
+    fn keys_and_resolves_items_of_inline_modules_by_their_path() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "mod util;\n\nmod a {\n    use crate::util::tool;\n\n    pub fn helper() {\n        tool();\n        inner::deep();\n    }\n\n    pub mod inner {\n        pub fn deep() {\n            super::helper();\n        }\n    }\n}\n\nmod b {\n    pub fn helper() {\n        sibling();\n    }\n\n    fn sibling() {}\n}\n\npub fn run() {\n    a::helper();\n    b::helper();\n    a::inner::deep();\n}\n",
+            ),
+            ("src/util.rs", "pub fn tool() {}\n"),
+        ]);
+        let index = fixture.index();
+
+        let node = index
+            .call_stack
+            .get_node(&fixture.key("src/lib.rs", "a.inner.deep"))
+            .unwrap();
+        assert_eq!(node.qualified_name, "a::inner::deep");
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "run"),
+            [
+                fixture.key("src/lib.rs", "a.helper"),
+                fixture.key("src/lib.rs", "b.helper"),
+                fixture.key("src/lib.rs", "a.inner.deep"),
+            ]
+        );
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "a.helper"),
+            [
+                fixture.key("src/util.rs", "tool"),
+                fixture.key("src/lib.rs", "a.inner.deep"),
+            ]
+        );
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "a.inner.deep"),
+            [fixture.key("src/lib.rs", "a.helper")]
+        );
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "b.helper"),
+            [fixture.key("src/lib.rs", "b.sibling")]
+        );
+    }
+
+    #[test]
+    fn keys_trait_impl_methods_by_their_trait() {
+        let fixture = Fixture::new(&[(
+            "src/lib.rs",
+            "use std::fmt;\n\nstruct P;\n\nimpl P {\n    fn new() -> P {\n        P\n    }\n}\n\nimpl fmt::Display for P {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        Ok(())\n    }\n}\n\nimpl fmt::Debug for P {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        Ok(())\n    }\n}\n\nfn show(p: P) {\n    p.fmt();\n}\n",
+        )]);
+        let index = fixture.index();
+
+        for (name, qualified_name, trait_name) in [
+            ("P.new", "P::new", None),
+            ("P.<Display>.fmt", "P::<Display>::fmt", Some("Display")),
+            ("P.<Debug>.fmt", "P::<Debug>::fmt", Some("Debug")),
+        ] {
+            let node = index
+                .call_stack
+                .get_node(&fixture.key("src/lib.rs", name))
+                .unwrap();
+            assert_eq!(node.qualified_name, qualified_name);
+            assert_eq!(node.class_name.as_deref(), Some("P"));
+            assert_eq!(node.trait_name.as_deref(), trait_name);
+        }
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "show"),
+            [fixture.key("src/lib.rs", "P.<Display>.fmt")]
+        );
+    }
+
+    #[test]
+    fn treats_paths_into_other_crates_as_external() {
+        let manifest = Fixture::new(&[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"app\"\n\n[dependencies]\ntokio = \"1\"\n",
+            ),
+            (
+                "src/main.rs",
+                "fn main() {\n    tokio::spawn(work());\n    rand::random();\n}\n\nasync fn work() {}\n",
+            ),
+        ]);
+        let no_manifest = Fixture::new(&[(
+            "src/main.rs",
+            "fn main() {\n    tokio::spawn(work());\n    rand::random();\n}\n\nasync fn work() {}\n",
+        )]);
+
+        for fixture in [manifest, no_manifest] {
+            let index = fixture.index();
+            for (key, module) in [("tokio.spawn", "tokio"), ("rand.random", "rand")] {
+                let node = index.call_stack.get_node(key).unwrap();
+                assert_eq!(
+                    node.resolution,
+                    Resolution::External {
+                        module: module.to_string()
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_paths_into_workspace_members_through_renamed_dependencies() {
+        let fixture = Fixture::new(&[
+            ("Cargo.toml", "[workspace]\nmembers = [\"app\", \"bar\"]\n"),
+            (
+                "app/Cargo.toml",
+                "[package]\nname = \"app\"\n\n[dependencies]\nfoo = { package = \"bar\", path = \"../bar\" }\n",
+            ),
+            ("app/src/main.rs", "fn main() {\n    foo::helper();\n}\n"),
+            ("bar/Cargo.toml", "[package]\nname = \"bar\"\n"),
+            ("bar/src/lib.rs", "pub fn helper() {}\n"),
+        ]);
+
+        let index = fixture.index();
+        assert_eq!(
+            fixture.callees(&index, "app/src/main.rs", "main"),
+            [fixture.key("bar/src/lib.rs", "helper")]
+        );
+        assert!(index.call_stack.get_node("foo.helper").is_none());
+        assert!(index.call_stack.get_node("bar.helper").is_none());
+    }
+}
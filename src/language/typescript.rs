@@ -0,0 +1,151 @@
+use std::path::Path;
+use tree_sitter::Language;
+
+use super::{node, LanguageSupport, ResolvedImport};
+use crate::query::ImportTag;
+use crate::resolver::ResolveContext;
+
+extern "C" {
+    fn tree_sitter_typescript() -> Language;
+    fn tree_sitter_tsx() -> Language;
+}
+
+/// The built-in query for TypeScript.
+const TAGS: &str = include_str!("../../queries/typescript/tags.scm");
+
+/// The built-in query for TSX: the TypeScript query and the patterns of JSX nodes.
+const TSX_TAGS: &str = concat!(
+    include_str!("../../queries/typescript/tags.scm"),
+    include_str!("../../queries/tsx/jsx.scm")
+);
+
+/// The extensions the TypeScript compiler tries for specifiers without one, which
+/// include JavaScript files when `allowJs` is set.
+const RESOLVED_EXTENSIONS: [&str; 8] = ["ts", "tsx", "mts", "cts", "js", "mjs", "cjs", "jsx"];
+
+/// Support for TypeScript source files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypeScript;
+
+impl LanguageSupport for TypeScript {
+    fn name(&self) -> &str {
+        "typescript"
+    }
+
+    fn language(&self) -> Language {
+        unsafe { tree_sitter_typescript() }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ts", "mts", "cts"]
+    }
+
+    fn default_query(&self) -> Option<&str> {
+        Some(TAGS)
+    }
+
+    /// Resolves imports the way the TypeScript compiler does for Node, see
+    /// `node::resolve_import`.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        node::resolve_import(import, file_path, context, &RESOLVED_EXTENSIONS)
+    }
+
+    fn receiver_names(&self) -> &[&str] {
+        &["this"]
+    }
+
+    fn super_names(&self) -> &[&str] {
+        &["super"]
+    }
+
+    fn constructor_name(&self) -> Option<&str> {
+        Some("constructor")
+    }
+
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        node::builtin_module(name)
+    }
+}
+
+/// Support for TSX source files, which need their own grammar for JSX.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tsx;
+
+impl LanguageSupport for Tsx {
+    fn name(&self) -> &str {
+        "tsx"
+    }
+
+    fn language(&self) -> Language {
+        unsafe { tree_sitter_tsx() }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tsx"]
+    }
+
+    fn default_query(&self) -> Option<&str> {
+        Some(TSX_TAGS)
+    }
+
+    /// Resolves imports the way the TypeScript compiler does for Node, see
+    /// `node::resolve_import`.
+    fn resolve_import(
+        &self,
+        import: &ImportTag,
+        file_path: &Path,
+        context: &ResolveContext,
+    ) -> Option<ResolvedImport> {
+        node::resolve_import(import, file_path, context, &RESOLVED_EXTENSIONS)
+    }
+
+    fn receiver_names(&self) -> &[&str] {
+        &["this"]
+    }
+
+    fn super_names(&self) -> &[&str] {
+        &["super"]
+    }
+
+    fn constructor_name(&self) -> Option<&str> {
+        Some("constructor")
+    }
+
+    fn builtin_module(&self, name: &str) -> Option<&str> {
+        node::builtin_module(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::EdgeKind;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn tsx_adds_jsx_handlers_to_the_typescript_query() {
+        let fixture = Fixture::new(&[
+            (
+                "app.tsx",
+                "function handle(): void {}\n\nfunction main() {\n  return <button onClick={handle} />;\n}\n",
+            ),
+            (
+                "timer.ts",
+                "function tick(): void {}\n\nfunction main() {\n  setInterval(tick, 5);\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        for (path, callee) in [("app.tsx", "handle"), ("timer.ts", "tick")] {
+            let edge = index
+                .call_graph
+                .edge(&fixture.key(path, "main"), &fixture.key(path, callee))
+                .unwrap();
+            assert_eq!(edge.kind, EdgeKind::CallbackReference);
+        }
+    }
+}
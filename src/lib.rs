@@ -6,17 +6,38 @@
 //!
 //! The main components of the library are:
 //! - [`block`]: Defines the `Block` struct for representing code blocks.
+//! - [`cargo`]: Reads the Cargo manifests that map Rust crate names to source roots.
+//! - [`cache`]: Defines the `Cache` that persists parsed files between runs.
 //! - [`call_graph`]: Defines the `CallGraph` struct for representing call graphs.
 //! - [`call_stack`]: Defines the `CallStack` struct for representing call stacks.
+//! - [`hierarchy`]: Defines the `ClassHierarchy` of the classes of an indexed tree.
 //! - [`indexer`]: Provides functions for indexing code directories.
+//! - [`language`]: Defines the `LanguageSupport` trait and the registry of indexable languages.
 //! - [`parser`]: Provides functions for parsing code files using tree-sitter.
-//! - [`utils`]: Provides utility functions used throughout the library.
+//! - [`resolver`]: Resolves the calls of parsed files to the functions they call.
+//! - [`type_graph`]: Defines the `TypeGraph` of the inheritance relationships between types.
+//! - [`query`]: Provides the tree-sitter queries that drive extraction for each language.
+//! - [`watch`]: Keeps an index up to date as the files of its directory change.
 //! - [`config`]: Defines the `Config` struct for loading library configuration.
+//! - [`error`]: Defines the `Error` enum returned by fallible operations.
 
 pub mod block;
+pub mod cache;
 pub mod call_graph;
 pub mod call_stack;
+pub mod cargo;
 pub mod config;
+pub mod error;
+pub mod hierarchy;
 pub mod indexer;
+pub mod language;
 pub mod parser;
-pub mod utils;
+pub mod query;
+pub mod resolver;
+pub mod type_graph;
+pub mod watch;
+
+#[cfg(test)]
+mod fixture;
+
+pub use error::{Error, Result};
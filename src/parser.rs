@@ -1,21 +1,418 @@
-use crate::block::{Block, BlockType};
-use crate::config::{Config, Matchers};
+use crate::block::{Block, BlockType, EdgeKind, Position, SourceLocation};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::language::LanguageSupport;
+use crate::query::{ClassKind, DefinitionKind, ExportTag, ImportTag, ModuleTag, Tags};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use tree_sitter::{Language, Node, Parser};
+use tree_sitter::{Node, Parser};
 
 use crate::indexer::generate_node_key;
 
-// C FFI bindings to the tree-sitter language libraries.
-extern "C" {
-    fn tree_sitter_rust() -> Language;
-    fn tree_sitter_python() -> Language;
-    fn tree_sitter_javascript() -> Language;
-    // Add more language bindings here
+/// The receiver that replaces any expression a method is called on other than a name,
+/// such as a literal or the result of another call, in the callee of the call.
+pub(crate) const EXPRESSION_RECEIVER: &str = "<expr>";
+
+/// The code structure extracted from a single file, before its calls are resolved.
+///
+/// Everything in a `ParsedFile` depends only on the contents of the file, so it can be
+/// cached and reused for as long as the file does not change. Calls are turned into
+/// node keys afterwards by [`resolve_calls`](crate::resolver::resolve_calls), which
+/// can take the rest of the indexed tree into account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedFile {
+    /// The name of the module containing the file.
+    pub module_name: String,
+    /// The name of the language the file was parsed as.
+    pub language: String,
+    /// The blocks of the file, with empty `outgoing_calls`.
+    pub blocks: Vec<Block>,
+    /// The imports of the file, in source order.
+    pub imports: Vec<ImportTag>,
+    /// The submodules declared in the file, in source order.
+    pub modules: Vec<ModuleTag>,
+    /// The definitions the file exports under another name, in source order.
+    pub exports: Vec<ExportTag>,
+    /// The names of the functions and classes defined at the top level of the file, in
+    /// source order.
+    pub definitions: Vec<String>,
+    /// The classes defined in the file, in source order.
+    pub classes: Vec<ClassDefinition>,
+    /// The calls made in the file.
+    pub calls: Vec<CallSite>,
+    /// The position of the first syntax error in the file, if any. Everything around
+    /// it is extracted all the same.
+    #[serde(default)]
+    pub syntax_error: Option<Position>,
+}
+
+/// Represents a class defined in a file, or a Rust type with methods in `impl` blocks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassDefinition {
+    /// The node key of the class, which the node keys of its methods start with.
+    pub node_key: String,
+    /// The name of the class.
+    pub name: String,
+    /// The kind of type the class is.
+    #[serde(default)]
+    pub kind: ClassKind,
+    /// The classes the class extends, as written, in declaration order.
+    pub bases: Vec<String>,
+    /// The traits or interfaces the class implements, as written, in declaration order.
+    #[serde(default)]
+    pub implements: Vec<String>,
+    /// The names of the methods defined directly in the class, in source order. The
+    /// methods of an `impl Trait for Type` block are preceded by `<Trait>.`.
+    pub methods: Vec<String>,
+}
+
+/// Represents a call found in a file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CallSite {
+    /// The index in `ParsedFile::blocks` of the block making the call.
+    pub caller: usize,
+    /// The callee expression as written, e.g. `helper` or `self.area`.
+    pub callee: String,
+    /// The position of the call expression.
+    pub position: Position,
+    /// The node key of the callee, if the call was already resolved within the file,
+    /// such as a call to a nested function or the creation of a closure.
+    pub local_target: Option<String>,
+    /// Whether the call goes through `super`, in which case `callee` names the method
+    /// on the calling class, and the method is looked up in its bases only.
+    #[serde(default)]
+    pub super_call: bool,
+    /// Whether the receiver of the method call may be of a subtype of the type `callee`
+    /// names, because the type is only declared, like that of a parameter or `self`,
+    /// rather than known from a constructor.
+    #[serde(default)]
+    pub dynamic_receiver: bool,
+    /// The kind of the call set by the query pattern that captured it, if any. Other
+    /// calls are classified once their callee is resolved.
+    #[serde(default)]
+    pub kind: Option<EdgeKind>,
+    /// The number of arguments passed, or `None` if the callee is only referenced or
+    /// its arguments cannot be counted.
+    #[serde(default)]
+    pub arguments: Option<usize>,
+}
+
+/// A method call rewritten into a call on the type of its receiver.
+struct TypedCallee {
+    /// The rewritten callee, e.g. `Calc.add`.
+    callee: String,
+    /// Whether the call goes through `super`.
+    super_call: bool,
+    /// Whether the receiver may be of a subtype of the type.
+    dynamic_receiver: bool,
+}
+
+/// The kind of lexical scope the traversal is currently inside of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScopeKind {
+    /// The file itself; always at the bottom of the scope stack.
+    Module,
+    /// A class, Rust `impl`/`trait` block, TypeScript interface or namespace.
+    Class,
+    /// A named function or method.
+    Function,
+    /// An anonymous function such as a Rust closure or a Python lambda.
+    Closure,
+}
+
+/// A single entry on the scope stack maintained by `traverse_tree`.
+#[derive(Debug)]
+struct Scope {
+    /// The kind of the scope.
+    kind: ScopeKind,
+    /// The name of the scope as it appears in qualified paths.
+    name: String,
+    /// The trait the scope implements or declares, for Rust `impl` and `trait` blocks.
+    trait_name: Option<String>,
+    /// The index in `TraversalContext::blocks` of the block created for this scope, if any.
+    block_index: Option<usize>,
+    /// The index in `TraversalContext::classes` of the class of this scope, if any.
+    class_index: Option<usize>,
+    /// Named functions defined directly inside this scope, and closures bound to its
+    /// variables, mapped to their node keys.
+    locals: HashMap<String, String>,
+    /// The number of closures defined directly inside this scope so far.
+    closure_count: usize,
+    /// The types of the variables bound directly inside this scope, each with the
+    /// position it is bound at and whether the type is exact, in source order.
+    types: HashMap<String, Vec<(usize, String, bool)>>,
+    /// The parameters and variables bound directly inside this scope.
+    variables: HashSet<String>,
+}
+
+impl Scope {
+    /// Creates a new `Scope` with no block, locals or closures.
+    fn new(kind: ScopeKind, name: String) -> Self {
+        Scope {
+            kind,
+            name,
+            trait_name: None,
+            block_index: None,
+            class_index: None,
+            locals: HashMap::new(),
+            closure_count: 0,
+            types: HashMap::new(),
+            variables: HashSet::new(),
+        }
+    }
+
+    /// Returns the segment an `impl Trait for Type` block adds after the name of the
+    /// type, e.g. `<Display>`, so that the methods of two traits implemented by one
+    /// type do not share a key. `trait` items, named after the trait, add none.
+    fn trait_segment(&self) -> Option<String> {
+        self.trait_name
+            .as_ref()
+            .filter(|trait_name| **trait_name != self.name)
+            .map(|trait_name| format!("<{}>", trait_name))
+    }
+}
+
+/// The state shared across the recursive traversal of a single file.
+struct TraversalContext<'a> {
+    /// The code string of the file being parsed.
+    code: &'a str,
+    /// The language of the file being parsed.
+    language: &'a dyn LanguageSupport,
+    /// The name of the module containing the file being parsed.
+    module_name: &'a str,
+    /// The definitions, calls and imports the language's query found in the file.
+    tags: &'a Tags,
+    /// The function blocks extracted so far.
+    blocks: Vec<Block>,
+    /// The calls found so far.
+    calls: Vec<CallSite>,
+    /// The names of the top-level definitions found so far.
+    definitions: Vec<String>,
+    /// The classes found so far.
+    classes: Vec<ClassDefinition>,
+    /// The text of the nodes that do not belong to any function.
+    non_function_blocks: Vec<String>,
+    /// The stack of scopes enclosing the node currently being visited.
+    scopes: Vec<Scope>,
+}
+
+impl TraversalContext<'_> {
+    /// Returns the name of the innermost class enclosing the current node, if any.
+    fn class_name(&self) -> Option<String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.kind == ScopeKind::Class)
+            .map(|scope| scope.name.clone())
+    }
+
+    /// Returns the trait of the innermost class enclosing the current node, if any.
+    fn trait_name(&self) -> Option<String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.kind == ScopeKind::Class)
+            .and_then(|scope| scope.trait_name.clone())
+    }
+
+    /// Returns the path segments of the current scope stack followed by `name`.
+    ///
+    /// Languages with a locals segment, such as Python's `<locals>`, insert it after
+    /// every function scope, and `impl Trait for Type` blocks add the trait after the
+    /// type.
+    fn path_segments(&self, name: &str) -> Vec<String> {
+        let locals_segment = self.language.locals_segment();
+        let mut segments = Vec::new();
+
+        for scope in &self.scopes {
+            match scope.kind {
+                ScopeKind::Module => {}
+                ScopeKind::Class => {
+                    segments.push(scope.name.clone());
+                    segments.extend(scope.trait_segment());
+                }
+                ScopeKind::Function | ScopeKind::Closure => {
+                    segments.push(scope.name.clone());
+                    if let Some(locals_segment) = locals_segment {
+                        segments.push(locals_segment.to_string());
+                    }
+                }
+            }
+        }
+
+        segments.push(name.to_string());
+        segments
+    }
+
+    /// Returns the qualified name of `name` within the current scope stack, using the
+    /// path separator of the language being parsed.
+    fn qualified_name(&self, name: &str) -> String {
+        self.path_segments(name)
+            .join(self.language.path_separator())
+    }
+
+    /// Returns the node key of `name` within the current scope stack.
+    fn node_key(&self, name: &str) -> String {
+        let segments = self.path_segments(name);
+        let (name, parents) = segments.split_last().unwrap();
+        let parent_path = parents.join(".");
+
+        generate_node_key(
+            Path::new(self.module_name),
+            (!parent_path.is_empty()).then_some(parent_path.as_str()),
+            name,
+        )
+    }
+
+    /// Records a definition if it is made at the top level of the file.
+    fn define(&mut self, name: &str) {
+        let top_level = self.scopes.len() == 1;
+        if top_level && !self.definitions.iter().any(|definition| definition == name) {
+            self.definitions.push(name.to_string());
+        }
+    }
+
+    /// Looks up a function defined locally in one of the enclosing scopes, or a
+    /// closure bound to a variable of one of them or of the file.
+    ///
+    /// # Returns
+    ///
+    /// The node key of the innermost local function or bound closure named `name`, if
+    /// any.
+    fn resolve_local(&self, name: &str) -> Option<&String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.locals.get(name))
+    }
+
+    /// Checks whether a parameter or variable of an enclosing function shadows any
+    /// function named `name`, i.e. is bound in a scope nested deeper than the local
+    /// functions of that name.
+    fn is_variable(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .filter(|scope| matches!(scope.kind, ScopeKind::Function | ScopeKind::Closure))
+            .find_map(|scope| {
+                if scope.locals.contains_key(name) {
+                    Some(false)
+                } else {
+                    scope.variables.contains(name).then_some(true)
+                }
+            })
+            .unwrap_or(false)
+    }
+
+    /// Looks up the type of a variable in the enclosing scopes, as bound last before
+    /// `position`, or last at all if it is only bound after it. The bodies of classes
+    /// are skipped, as their variables are not visible to their methods.
+    ///
+    /// A type that is itself bound, like a Rust type parameter to its bound, is
+    /// replaced by what it is bound to.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the name of the type and whether it is exact.
+    fn variable_type(&self, name: &str, position: usize) -> Option<(&str, bool)> {
+        let lookup = |name: &str| {
+            self.scopes
+                .iter()
+                .rev()
+                .filter(|scope| scope.kind != ScopeKind::Class)
+                .find_map(|scope| scope.types.get(name))
+                .and_then(|types| {
+                    types
+                        .iter()
+                        .rev()
+                        .find(|(start, _, _)| *start < position)
+                        .or(types.last())
+                })
+                .map(|(_, type_name, exact)| (type_name.as_str(), *exact))
+        };
+
+        let (type_name, exact) = lookup(name)?;
+        match lookup(type_name).filter(|(bound, _)| *bound != type_name) {
+            Some((bound, _)) => Some((bound, false)),
+            None => Some((type_name, exact)),
+        }
+    }
+
+    /// Rewrites a method call on a receiver of a known type into a call on the type,
+    /// e.g. `c.add` into `Calc.add` after `c = Calc()`, and `self.add` or
+    /// `super().add` into `Calc.add` inside `Calc`.
+    ///
+    /// # Arguments
+    ///
+    /// * `callee` - The callee expression of the call.
+    /// * `position` - The byte offset of the call.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `TypedCallee`, or `None` if the callee is not a
+    /// method call or the type of its receiver is not known.
+    fn typed_callee(&self, callee: &str, position: usize) -> Option<TypedCallee> {
+        let separator = self.language.path_separator();
+
+        // Calling `super` itself, as in a JavaScript constructor, calls the method of
+        // the base class that the enclosing method overrides.
+        if self.language.super_names().contains(&callee) {
+            let class_index = self
+                .scopes
+                .iter()
+                .rposition(|scope| scope.kind == ScopeKind::Class)?;
+            let method = self
+                .scopes
+                .get(class_index + 1)
+                .filter(|scope| scope.kind == ScopeKind::Function)?;
+            let class_name = &self.scopes[class_index].name;
+            return Some(TypedCallee {
+                callee: format!("{}{}{}", class_name, separator, method.name),
+                super_call: true,
+                dynamic_receiver: false,
+            });
+        }
+
+        let (receiver, method) = [".", separator]
+            .iter()
+            .filter_map(|separator| callee.split_once(separator))
+            .min_by_key(|(receiver, _)| receiver.len())?;
+        if method.contains('.') || method.contains(separator) {
+            return None;
+        }
+
+        // `super` may be called to get the receiver, as in Python's `super().add`.
+        let receiver_function = receiver.split_once('(').map_or(receiver, |(name, _)| name);
+        if self.language.super_names().contains(&receiver_function) {
+            let class_name = self.class_name()?;
+            return Some(TypedCallee {
+                callee: format!("{}{}{}", class_name, separator, method),
+                super_call: true,
+                dynamic_receiver: false,
+            });
+        }
+
+        let receiver_names = self.language.receiver_names();
+        let (mut type_name, mut exact) = (receiver.to_string(), false);
+        if !receiver_names.contains(&receiver) {
+            let (variable_type, variable_exact) = self.variable_type(receiver, position)?;
+            (type_name, exact) = (variable_type.to_string(), variable_exact);
+        }
+        if receiver_names.contains(&type_name.as_str()) {
+            type_name = self.class_name()?;
+        }
+
+        Some(TypedCallee {
+            callee: format!("{}{}{}", type_name, separator, method),
+            super_call: false,
+            dynamic_receiver: !exact,
+        })
+    }
 }
 
-/// Parses a code file and returns a vector of `Block`s representing the code structure.
+/// Parses a code file and extracts its code structure.
 ///
 /// # Arguments
 ///
@@ -25,30 +422,78 @@ extern "C" {
 ///
 /// # Returns
 ///
-/// A vector of `Block`s representing the code structure of the parsed file.
-pub fn parse_file(file_path: &Path, module_name: &str, config: &Config) -> Vec<Block> {
-    let code = fs::read_to_string(file_path).unwrap();
-    let language = tree_sitter_language(file_path);
+/// A `Result` containing the `ParsedFile`, or an error if the file could not be read,
+/// its language is not supported, its query is invalid, or it could not be parsed.
+pub fn parse_file(file_path: &Path, module_name: &str, config: &Config) -> Result<ParsedFile> {
+    let code = fs::read_to_string(file_path).map_err(|source| Error::Io {
+        path: file_path.to_path_buf(),
+        source,
+    })?;
+
+    parse_code(&code, file_path, module_name, config)
+}
+
+/// Parses code that was already read from a file and extracts its code structure.
+///
+/// # Arguments
+///
+/// * `code` - The code string to parse.
+/// * `file_path` - The path of the file the code was read from, used to pick its language.
+/// * `module_name` - The name of the module containing the file.
+/// * `config` - The `Config` instance containing language-specific settings.
+///
+/// # Returns
+///
+/// A `Result` containing the `ParsedFile`, or an error if the language of the file is
+/// not supported, its query is invalid, or the code could not be parsed.
+pub fn parse_code(
+    code: &str,
+    file_path: &Path,
+    module_name: &str,
+    config: &Config,
+) -> Result<ParsedFile> {
+    let language = config
+        .registry
+        .for_path(file_path)
+        .ok_or_else(|| Error::UnsupportedLanguage(file_path.to_path_buf()))?;
+    let query = config.query(language)?;
+
     let mut parser = Parser::new();
-    parser.set_language(language).unwrap();
-    let tree = parser.parse(&code, None).unwrap();
+    parser.set_language(language.language())?;
+    let tree = parser
+        .parse(code, None)
+        .ok_or_else(|| Error::Parse(file_path.to_path_buf()))?;
 
-    let mut blocks = Vec::new();
-    let mut non_function_blocks = Vec::new();
-    let mut imports = HashMap::new();
-    let mut cursor = tree.root_node().walk();
+    let tags = language.extract_tags(&query, tree.root_node(), code);
+    let syntax_error = first_syntax_error(tree.root_node())
+        .map(|node| source_position(code, node.start_byte(), node.start_position()));
 
-    traverse_tree(
-        &code,
-        &mut cursor,
-        &mut blocks,
-        &mut non_function_blocks,
+    let mut context = TraversalContext {
+        code,
         language,
-        None,
         module_name,
-        &mut imports,
-        &config,
-    );
+        tags: &tags,
+        blocks: Vec::new(),
+        calls: Vec::new(),
+        definitions: Vec::new(),
+        classes: Vec::new(),
+        non_function_blocks: Vec::new(),
+        scopes: vec![Scope::new(ScopeKind::Module, String::new())],
+    };
+    context.scopes[0].types = find_typed_bindings(&context, tree.root_node());
+    context.scopes[0].locals = find_bound_closures(&context, tree.root_node());
+    let mut cursor = tree.root_node().walk();
+
+    traverse_tree(&mut context, &mut cursor);
+
+    let TraversalContext {
+        mut blocks,
+        calls,
+        definitions,
+        classes,
+        non_function_blocks,
+        ..
+    } = context;
 
     if !non_function_blocks.is_empty() {
         let non_function_block_content = non_function_blocks.join("\n");
@@ -56,147 +501,265 @@ pub fn parse_file(file_path: &Path, module_name: &str, config: &Config) -> Vec<B
             String::from("non_function_block"),
             BlockType::NonFunction,
             non_function_block_content,
+            source_location(code, module_name, tree.root_node()),
             None,
             None,
         ));
     }
 
-    blocks
+    Ok(ParsedFile {
+        module_name: module_name.to_string(),
+        language: language.name().to_string(),
+        blocks,
+        imports: tags.imports,
+        modules: tags.modules,
+        exports: tags.exports,
+        definitions,
+        classes,
+        calls,
+        syntax_error,
+    })
+}
+
+/// Finds the first node of a syntax tree that tree-sitter could not parse, or that it
+/// inserted to recover from a syntax error.
+///
+/// # Arguments
+///
+/// * `root` - The root node of the syntax tree.
+///
+/// # Returns
+///
+/// An `Option` containing the first `ERROR` or missing node, in source order.
+fn first_syntax_error(root: Node) -> Option<Node> {
+    let mut node = root;
+    while node.has_error() {
+        if node.is_error() || node.is_missing() {
+            return Some(node);
+        }
+        let mut cursor = node.walk();
+        let child = node
+            .children(&mut cursor)
+            .find(|child| child.has_error() || child.is_missing())?;
+        node = child;
+    }
+    None
 }
 
-/// Returns the appropriate tree-sitter `Language` for a given file based on its extension.
+/// Converts a tree-sitter point into a 1-based `Position`.
+///
+/// Tree-sitter measures columns in bytes, so the column is recounted in characters
+/// to match what editors display.
 ///
 /// # Arguments
 ///
-/// * `file_path` - The path of the file to get the language for.
+/// * `code` - The code string of the file being parsed.
+/// * `byte` - The byte offset of the point in `code`.
+/// * `point` - The tree-sitter point (row and byte column) at `byte`.
 ///
 /// # Returns
 ///
-/// The tree-sitter `Language` corresponding to the file's extension.
+/// The `Position` of the point.
+fn source_position(code: &str, byte: usize, point: tree_sitter::Point) -> Position {
+    let line_start = byte - point.column;
+    let column = code
+        .get(line_start..byte)
+        .map(|line| line.chars().count())
+        .unwrap_or(point.column);
+
+    Position {
+        line: point.row + 1,
+        column: column + 1,
+    }
+}
+
+/// Returns the `SourceLocation` of an AST node.
+///
+/// # Arguments
 ///
-/// # Panics
+/// * `code` - The code string of the file being parsed.
+/// * `file_path` - The path of the file being parsed.
+/// * `node` - The AST node to locate.
 ///
-/// Panics if the file's extension is not supported.
-fn tree_sitter_language(file_path: &Path) -> Language {
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
-    match extension {
-        "rs" => unsafe { tree_sitter_rust() },
-        "py" => unsafe { tree_sitter_python() },
-        "js" => unsafe { tree_sitter_javascript() },
-        "ts" => unsafe { tree_sitter_javascript() },
-        // Add more mappings for other supported languages
-        _ => panic!("Unsupported language"),
+/// # Returns
+///
+/// The `SourceLocation` spanning the node.
+fn source_location(code: &str, file_path: &str, node: Node) -> SourceLocation {
+    SourceLocation {
+        file_path: file_path.to_string(),
+        start: source_position(code, node.start_byte(), node.start_position()),
+        end: source_position(code, node.end_byte(), node.end_position()),
+        start_byte: node.start_byte(),
+        end_byte: node.end_byte(),
     }
 }
 
 /// Recursively traverses the AST and extracts code blocks and call information.
 ///
+/// The traversal keeps an explicit stack of the enclosing scopes (module, classes,
+/// functions and closures), so every block records its full qualified path and the
+/// calls inside a nested function or closure are attributed to that function rather
+/// than to the one enclosing it.
+///
 /// # Arguments
 ///
-/// * `code` - The code string of the file being parsed.
+/// * `context` - The `TraversalContext` of the file being parsed.
 /// * `cursor` - A mutable reference to the `TreeCursor` used to navigate the AST.
-/// * `blocks` - A mutable reference to the vector of `Block`s to populate.
-/// * `non_function_blocks` - A mutable reference to the vector of non-function block strings.
-/// * `language` - The tree-sitter `Language` of the file being parsed.
-/// * `class_name` - An optional string representing the name of the current class, if any.
-/// * `module_name` - The name of the module containing the file being parsed.
-/// * `imports` - A mutable reference to the map of import aliases to their full module names.
-/// * `config` - The `Config` instance containing language-specific settings.
-fn traverse_tree(
-    code: &str,
-    cursor: &mut tree_sitter::TreeCursor,
-    blocks: &mut Vec<Block>,
-    non_function_blocks: &mut Vec<String>,
-    language: Language,
-    class_name: Option<String>,
-    module_name: &str,
-    imports: &mut HashMap<String, String>,
-    config: &Config,
-) {
+fn traverse_tree(context: &mut TraversalContext, cursor: &mut tree_sitter::TreeCursor) {
     let node = cursor.node();
-    let kind = node.kind();
+    let code = context.code;
+    let language = context.language;
 
-    if is_import_statement(kind, language) {
-        let imports_list = parse_import_statement(code, node, language, config);
-        for (object_name, module_name) in imports_list {
-            imports.insert(object_name, module_name);
-        }
-    } else if is_class_definition(kind, language) {
-        let class_name_node = node.child_by_field_name("name");
-        if let Some(class_name_node) = class_name_node {
-            let extracted_class_name = class_name_node
-                .utf8_text(code.as_bytes())
-                .unwrap()
-                .to_string();
-
-            if cursor.goto_first_child() {
-                loop {
-                    traverse_tree(
-                        code,
-                        cursor,
-                        blocks,
-                        non_function_blocks,
-                        language,
-                        Some(extracted_class_name.clone()),
-                        module_name,
-                        imports,
-                        config,
-                    );
-                    if !cursor.goto_next_sibling() {
-                        break;
-                    }
+    let definition = context.tags.definitions.get(&node.id());
+
+    if let Some(definition) = definition.filter(|d| d.kind == DefinitionKind::Class) {
+        if let Some(class_name) = definition.name.clone() {
+            context.define(&class_name);
+
+            // Rust types may have methods in several `impl` blocks.
+            let node_key = context.node_key(&class_name);
+            let class_index = match context
+                .classes
+                .iter()
+                .position(|class| class.node_key == node_key)
+            {
+                Some(class_index) => class_index,
+                None => {
+                    context.classes.push(ClassDefinition {
+                        node_key,
+                        name: class_name.clone(),
+                        kind: definition.class_kind.unwrap_or_default(),
+                        bases: Vec::new(),
+                        implements: Vec::new(),
+                        methods: Vec::new(),
+                    });
+                    context.classes.len() - 1
+                }
+            };
+            let class = &mut context.classes[class_index];
+            for base in &definition.bases {
+                if !class.bases.contains(base) {
+                    class.bases.push(base.clone());
+                }
+            }
+            for implemented in &definition.implements {
+                if !class.implements.contains(implemented) {
+                    class.implements.push(implemented.clone());
                 }
-                cursor.goto_parent();
             }
+
+            let mut scope = Scope::new(ScopeKind::Class, class_name);
+            scope.trait_name = definition.trait_name.clone();
+            scope.class_index = Some(class_index);
+
+            context.scopes.push(scope);
+            traverse_children(context, cursor);
+            context.scopes.pop();
+            return;
         }
-    } else if is_function_node(kind, language) {
-        let function_name = get_function_name(code, node, language)
-            .unwrap_or_else(|| "UnnamedFunction".to_string());
-        let block_type = BlockType::Function;
-        let block_content = node.utf8_text(code.as_bytes()).unwrap().to_string();
+    } else if let Some(definition) = definition {
+        let is_closure = definition.kind == DefinitionKind::Closure;
+        let function_name = if is_closure {
+            let parent = context.scopes.last_mut().unwrap();
+            let index = parent.closure_count;
+            parent.closure_count += 1;
+            language.closure_name(index)
+        } else {
+            definition
+                .name
+                .clone()
+                .unwrap_or_else(|| "UnnamedFunction".to_string())
+        };
 
-        let node_key = generate_node_key(
-            Path::new(module_name),
-            class_name.as_deref(),
-            &function_name,
-        );
+        if !is_closure {
+            context.define(&function_name);
+            let scope = context.scopes.last().unwrap();
+            if let Some(class_index) = scope.class_index {
+                let method = match scope.trait_segment() {
+                    Some(trait_segment) => format!("{}.{}", trait_segment, function_name),
+                    None => function_name.clone(),
+                };
+                context.classes[class_index].methods.push(method);
+            }
+        }
 
+        let node_key = context.node_key(&function_name);
         let mut block = Block::new(
-            node_key,
-            block_type,
-            block_content,
+            node_key.clone(),
+            BlockType::Function,
+            node.utf8_text(code.as_bytes()).unwrap().to_string(),
+            source_location(code, context.module_name, node),
             Some(function_name.clone()),
-            class_name.clone(),
+            context.class_name(),
         );
+        block.qualified_name = context.qualified_name(&function_name);
+        block.trait_name = context.trait_name();
 
-        block.outgoing_calls = find_calls(code, node, language, module_name, imports);
-
-        // Avoid double pass function addition without class context
-        // Probably, need a better way to do this
-        if !blocks.iter().any(|b| b.function_name == Some(function_name.clone()) && b.class_name.is_some()) {
-            blocks.push(block);
+        // A closure is only reachable through the function that creates it, which
+        // hands it on to be called back later, unless it calls it right away. A
+        // pattern capturing the closure as a call, e.g. as the argument of a spawn,
+        // sets the kind of that edge.
+        if is_closure {
+            if let Some(parent_index) = context.scopes.last().unwrap().block_index {
+                let kind = context
+                    .tags
+                    .calls
+                    .get(&node.id())
+                    .and_then(|call| call.kind)
+                    .or((!is_called(node)).then_some(EdgeKind::CallbackReference));
+                context.calls.push(CallSite {
+                    caller: parent_index,
+                    callee: function_name.clone(),
+                    position: source_position(code, node.start_byte(), node.start_position()),
+                    local_target: Some(node_key.clone()),
+                    super_call: false,
+                    dynamic_receiver: false,
+                    kind,
+                    arguments: None,
+                });
+            }
         }
+
+        let kind = if is_closure {
+            ScopeKind::Closure
+        } else {
+            ScopeKind::Function
+        };
+        let block_index = context.blocks.len();
+        let mut scope = Scope::new(kind, function_name);
+        scope.block_index = Some(block_index);
+        context.scopes.push(scope);
+        let mut locals = find_local_functions(context, node);
+        locals.extend(find_bound_closures(context, node));
+        context.scopes.last_mut().unwrap().locals = locals;
+        context.scopes.last_mut().unwrap().types = find_typed_bindings(context, node);
+        context.scopes.last_mut().unwrap().variables = find_variables(context, node);
+
+        let calls = find_calls(context, node, block_index);
+        context.calls.extend(calls);
+        context.blocks.push(block);
+
+        traverse_children(context, cursor);
+        context.scopes.pop();
+        return;
     } else if !node.is_named() {
         let block_content = node.utf8_text(code.as_bytes()).unwrap().to_string();
-        non_function_blocks.push(block_content);
+        context.non_function_blocks.push(block_content);
     }
 
+    traverse_children(context, cursor);
+}
+
+/// Traverses every child of the node the cursor currently points at.
+///
+/// # Arguments
+///
+/// * `context` - The `TraversalContext` of the file being parsed.
+/// * `cursor` - A mutable reference to the `TreeCursor` used to navigate the AST.
+fn traverse_children(context: &mut TraversalContext, cursor: &mut tree_sitter::TreeCursor) {
     if cursor.goto_first_child() {
         loop {
-            traverse_tree(
-                code,
-                cursor,
-                blocks,
-                non_function_blocks,
-                language,
-                class_name.clone(),
-                module_name,
-                imports,
-                &config,
-            );
+            traverse_tree(context, cursor);
             if !cursor.goto_next_sibling() {
                 break;
             }
@@ -205,453 +768,645 @@ fn traverse_tree(
     }
 }
 
-/// Finds the function calls made within a given AST node and returns their keys.
+/// Finds the named functions defined directly inside a function, without descending
+/// into nested scopes.
 ///
 /// # Arguments
 ///
-/// * `code` - The code string of the file being parsed.
-/// * `root` - The AST node to search for function calls.
-/// * `language` - The tree-sitter `Language` of the file being parsed.
-/// * `module_name` - The name of the module containing the file being parsed.
-/// * `imports` - A reference to the map of import aliases to their full module names.
+/// * `context` - The `TraversalContext` of the file being parsed, with the scope of
+///   `root` on top of its scope stack.
+/// * `root` - The AST node of the function to search.
 ///
 /// # Returns
 ///
-/// A vector of strings representing the keys of the called functions.
-fn find_calls(
-    code: &str,
-    root: Node,
-    language: Language,
-    module_name: &str,
-    imports: &HashMap<String, String>,
-) -> Vec<String> {
-    let mut calls = HashSet::new();
+/// A map of the local function names to their node keys.
+fn find_local_functions(context: &TraversalContext, root: Node) -> HashMap<String, String> {
+    let definitions = &context.tags.definitions;
+    let mut locals = HashMap::new();
     let mut cursor = root.walk();
 
     loop {
         let node = cursor.node();
+        let nested = node != root && definitions.contains_key(&node.id());
 
-        if is_call_expression(node.kind(), language) {
-            if let Some(function_name) = get_call_expression_name(code, node, language) {
-                let parts: Vec<&str> = function_name.split('.').collect();
-
-                if parts.len() > 1 {
-                    // This is for method calls on an object; the part before '.' is treated as an object, not a module.
-                    let object_name = parts[0];
-                    let method_name = &parts[1..].join(".");
-
-                    // If the object name matches an alias from the imports, resolve to the correct module.
-                    if let Some(imported_module) = imports.get(object_name) {
-                        let call_key = generate_node_key(
-                            Path::new(imported_module),
-                            Some(object_name),
-                            method_name,
-                        );
-                        calls.insert(call_key);
-                    } else {
-                        let call_key = generate_node_key(
-                            Path::new(module_name),
-                            Some(object_name),
-                            method_name,
-                        );
-                        calls.insert(call_key);
-                    }
-                } else {
-                    // For global function calls, check if the function name matches an alias from the imports.
-                    if let Some(imported_module) = imports.get(&function_name) {
-                        let call_key = generate_node_key(
-                            Path::new(&format!("test-code-base/{}.py", imported_module)),
-                            None,
-                            &function_name,
-                        );
-                        calls.insert(call_key);
-                    } else {
-                        let function_key =
-                            generate_node_key(Path::new(module_name), None, &function_name);
-                        calls.insert(function_key);
-                    }
-                }
+        if let Some(definition) = definitions.get(&node.id()).filter(|_| nested) {
+            if let (DefinitionKind::Function, Some(name)) = (definition.kind, &definition.name) {
+                locals.insert(name.clone(), context.node_key(name));
             }
         }
 
-        if !cursor.goto_first_child() {
+        if nested || !cursor.goto_first_child() {
             while !cursor.goto_next_sibling() {
                 if !cursor.goto_parent() {
-                    return calls.into_iter().collect();
+                    return locals;
                 }
             }
         }
     }
 }
 
-/// Checks if an AST node represents an import statement in the given language.
+/// Finds the closures bound to variables directly inside a function, or a file, without
+/// descending into nested scopes, like Rust's `let c = |x| helper(x);` or Python's
+/// `f = lambda: helper()`, which are then called through the variable.
 ///
 /// # Arguments
 ///
-/// * `kind` - The kind (type) of the AST node.
-/// * `language` - The tree-sitter `Language` of the file being parsed.
+/// * `context` - The `TraversalContext` of the file being parsed, with the scope of
+///   `root` on top of its scope stack.
+/// * `root` - The AST node of the function or file to search.
 ///
 /// # Returns
 ///
-/// `true` if the node represents an import statement, `false` otherwise.
-fn is_import_statement(kind: &str, language: Language) -> bool {
-    match language {
-        lang if lang == unsafe { tree_sitter_python() } => {
-            kind == "import_statement" || kind == "import_from_statement"
+/// A map of the variable names to the node keys of the closures.
+fn find_bound_closures(context: &TraversalContext, root: Node) -> HashMap<String, String> {
+    let definitions = &context.tags.definitions;
+    let mut closures = HashMap::new();
+    // Closures are named in the order the traversal reaches them, which is this order.
+    let mut closure_count = 0;
+    let mut cursor = root.walk();
+
+    loop {
+        let node = cursor.node();
+        let nested = node != root && definitions.contains_key(&node.id());
+
+        if let Some(definition) = definitions.get(&node.id()).filter(|_| nested) {
+            if definition.kind == DefinitionKind::Closure {
+                let closure_name = context.language.closure_name(closure_count);
+                closure_count += 1;
+                if let Some(name) = &definition.name {
+                    closures.insert(name.clone(), context.node_key(&closure_name));
+                }
+            }
+        }
+
+        if nested || !cursor.goto_first_child() {
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return closures;
+                }
+            }
         }
-        lang if lang == unsafe { tree_sitter_rust() } => kind == "use_declaration",
-        lang if lang == unsafe { tree_sitter_javascript() } => kind == "import_statement",
-        // Add more language-specific checks here
-        _ => false,
     }
 }
 
-/// Filters the children of an import statement node using the provided matchers.
+/// Finds the variables of known type bound directly inside a function, or a file,
+/// without descending into nested scopes.
 ///
 /// # Arguments
 ///
-/// * `child` - The child node of the import statement to filter.
-/// * `code` - The code string of the file being parsed.
-/// * `matchers` - The `Matchers` instance containing the field names and node types to match.
+/// * `context` - The `TraversalContext` of the file being parsed.
+/// * `root` - The AST node of the function or file to search.
 ///
 /// # Returns
 ///
-/// A tuple containing:
-/// - An optional string representing the module name.
-/// - An optional string representing the imported object name.
-/// - An optional string representing the import alias.
-fn filter_import_matchers(
-    child: Node,
-    code: &str,
-    matchers: &Matchers,
-) -> (Option<String>, Option<String>, Option<String>) {
-    let module = child
-        .child_by_field_name(&matchers.module_name.field_name)
-        .map(|n| {
-            if n.kind() == matchers.module_name.kind {
-                return n.utf8_text(code.as_bytes()).unwrap_or_default().to_owned();
-            }
-
-            String::default()
-        });
+/// A map of the variable names to the positions they are bound at, their types and
+/// whether the types are exact, in source order.
+fn find_typed_bindings(
+    context: &TraversalContext,
+    root: Node,
+) -> HashMap<String, Vec<(usize, String, bool)>> {
+    let tags = context.tags;
+    let mut types: HashMap<String, Vec<(usize, String, bool)>> = HashMap::new();
+    let mut cursor = root.walk();
 
-    let name = child
-        .child_by_field_name(&matchers.object_name.field_name)
-        .map(|n| {
-            if n.kind() == matchers.object_name.kind {
-                return n.utf8_text(code.as_bytes()).unwrap_or_default().to_owned();
-            }
+    loop {
+        let node = cursor.node();
+        let nested = node != root && tags.definitions.contains_key(&node.id());
 
-            String::default()
-        });
+        if let Some(binding) = tags.bindings.get(&node.id()).filter(|_| !nested) {
+            types.entry(binding.name.clone()).or_default().push((
+                node.start_byte(),
+                binding.type_name.clone(),
+                binding.exact,
+            ));
+        }
 
-    let alias = child
-        .child_by_field_name(&matchers.alias.field_name)
-        .map(|n| {
-            if n.kind() == matchers.alias.kind {
-                return n.utf8_text(code.as_bytes()).unwrap_or_default().to_owned();
+        if nested || !cursor.goto_first_child() {
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return types;
+                }
             }
-
-            String::default()
-        });
-
-    (module, name, alias)
+        }
+    }
 }
 
-/// Parses an import statement node and returns the imported module and alias, if any.
+/// Finds the parameters and variables bound directly inside a function, without
+/// descending into nested scopes.
 ///
 /// # Arguments
 ///
-/// * `code` - The code string of the file being parsed.
-/// * `node` - The import statement AST node to parse.
-/// * `language` - The tree-sitter `Language` of the file being parsed.
-/// * `config` - The `Config` instance containing language-specific settings.
+/// * `context` - The `TraversalContext` of the file being parsed.
+/// * `root` - The AST node of the function to search.
 ///
 /// # Returns
 ///
-/// An `Option` containing a tuple of the imported module name and alias, if successfully parsed.
-fn parse_import_statement(
-    code: &str,
-    node: Node,
-    language: Language,
-    config: &Config,
-) -> Vec<(String, String)> {
-    let mut module_name = String::new();
-    let mut object_name = String::new();
-    let mut alias_name = String::new();
-
-    match language {
-        lang if lang == unsafe { tree_sitter_javascript() } => {
-            let mut cursor = node.walk();
-            let module_name = node
-                .child_by_field_name("source")
-                .map(|n| {
-                    return n.utf8_text(code.as_bytes()).unwrap_or_default().to_owned();
-                })
-                .unwrap_or_default();
-
-            let mut import_clause_string = String::default();
-
-            for child in node.named_children(&mut cursor) {
-                if child.kind() == "import_clause" {
-                    import_clause_string = child
-                        .utf8_text(code.as_bytes())
-                        .unwrap_or_default()
-                        .to_owned();
-                }
-            }
-
-            let mut imports: Vec<(String, String)> = Vec::new();
-
-            if import_clause_string.contains("as") {
-                let alias: Vec<String> = import_clause_string
-                    .split("as")
-                    .map(|s| {
-                        s.trim() // Trim whitespace
-                            .to_owned()
-                    }) // Convert to owned String
-                    .collect();
-
-                imports.push((
-                    module_name.clone(),
-                    alias.last().unwrap_or(&"".to_owned()).to_owned(),
-                ));
-            } else if import_clause_string.starts_with("{") {
-                let val: Vec<String> = import_clause_string
-                    .trim() // Trim whitespace around the string
-                    .trim_start_matches('{') // Remove the starting '{'
-                    .trim_end_matches('}') // Remove the ending '}'
-                    .split(',') // Split the string by commas
-                    .map(|s| s.trim().to_string()) // Trim whitespace and convert to String
-                    .filter(|s| !s.is_empty()) // Filter out any empty strings
-                    .collect();
-
-                imports = val
-                    .iter()
-                    .map(|num| (module_name.clone(), num.clone()))
-                    .collect();
-            }
-
-            println!(
-                "{}",
-                imports
-                    .iter()
-                    .map(|(key, value)| format!("({}, {})", key, value))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
-
-            return imports;
-        }
-        lang if lang == unsafe { tree_sitter_python() } => {
-            let matchers = &config
-                .languages
-                .get("python")
-                .expect("Failed to get Python matchers from config")
-                .matchers;
-
-            if node.kind() == matchers.import_statement {
-                let result = filter_import_matchers(node, code, matchers);
-                (module_name, object_name, alias_name) = (
-                    result.0.unwrap_or(module_name),
-                    result.1.unwrap_or(object_name),
-                    result.2.unwrap_or(alias_name),
-                );
-
-                let mut cursor = node.walk();
-                for child in node.named_children(&mut cursor) {
-                    let result = filter_import_matchers(child, code, matchers);
-                    (module_name, object_name, alias_name) = (
-                        result.0.unwrap_or(module_name),
-                        result.1.unwrap_or(object_name),
-                        result.2.unwrap_or(alias_name),
-                    );
-
-                    let mut cursor2 = child.walk();
-                    for child2 in child.named_children(&mut cursor2) {
-                        let result = filter_import_matchers(child2, code, matchers);
-                        (module_name, object_name, alias_name) = (
-                            result.0.unwrap_or(module_name),
-                            result.1.unwrap_or(object_name),
-                            result.2.unwrap_or(alias_name),
-                        );
-                    }
-                }
+/// The names of the parameters and variables.
+fn find_variables(context: &TraversalContext, root: Node) -> HashSet<String> {
+    let tags = context.tags;
+    let mut variables = HashSet::new();
+    let mut cursor = root.walk();
 
-                println!(
-                    "Module: {}, Object: {}, Alias: {}",
-                    module_name, object_name, alias_name
-                );
+    loop {
+        let node = cursor.node();
+        let nested = node != root && tags.definitions.contains_key(&node.id());
 
-                return vec![(module_name, object_name)];
-            }
-            vec![]
+        if let Some(name) = tags.variables.get(&node.id()).filter(|_| !nested) {
+            variables.insert(name.clone());
         }
-        lang if lang == unsafe { tree_sitter_rust() } => {
-            let matchers = &config
-                .languages
-                .get("rust")
-                .expect("Failed to get Python matchers from config")
-                .matchers;
-
-            if node.kind() == matchers.import_statement {
-                let result = filter_import_matchers(node, code, matchers);
-                (module_name, object_name, alias_name) = (
-                    result.0.unwrap_or(module_name),
-                    result.1.unwrap_or(object_name),
-                    result.2.unwrap_or(alias_name),
-                );
 
-                let mut cursor = node.walk();
-                for child in node.named_children(&mut cursor) {
-                    let result = filter_import_matchers(child, code, matchers);
-                    (module_name, object_name, alias_name) = (
-                        result.0.unwrap_or(module_name),
-                        result.1.unwrap_or(object_name),
-                        result.2.unwrap_or(alias_name),
-                    );
-
-                    let mut cursor2 = child.walk();
-                    for child2 in child.named_children(&mut cursor2) {
-                        let result = filter_import_matchers(child2, code, matchers);
-                        (module_name, object_name, alias_name) = (
-                            result.0.unwrap_or(module_name),
-                            result.1.unwrap_or(object_name),
-                            result.2.unwrap_or(alias_name),
-                        );
-                    }
+        if nested || !cursor.goto_first_child() {
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return variables;
                 }
-
-                println!(
-                    "Module: {}, Object: {}, Alias: {}",
-                    module_name, object_name, alias_name
-                );
-                return vec![(module_name, object_name)];
             }
-            vec![]
         }
-        _ => vec![],
     }
 }
 
-/// Checks if an AST node represents a class definition in the given language.
+/// Replaces the receiver of a method call with `EXPRESSION_RECEIVER` unless it is a
+/// name or a path of names, such as `self.items`, so that the calls of a method on
+/// literals or call results, like `"a".to_string()` or `v.iter().map()`, share one
+/// callee rather than each keeping its source text.
 ///
 /// # Arguments
 ///
-/// * `kind` - The kind (type) of the AST node.
-/// * language - The tree-sitter Language of the file being parsed.
+/// * `callee` - The callee expression, e.g. `self.area` or `v.iter().map`.
 ///
 /// # Returns
 ///
-/// true if the node represents a class definition, false otherwise.
-fn is_class_definition(kind: &str, language: Language) -> bool {
-    match language {
-        lang if lang == unsafe { tree_sitter_python() } => kind == "class_definition",
-        // Add more language-specific checks here
-        _ => false,
+/// The callee, with its receiver replaced if it is any other expression.
+fn collapse_receiver(callee: String) -> String {
+    let Some((receiver, method)) = callee.rsplit_once('.') else {
+        return callee;
+    };
+    let is_name = |segment: &str| {
+        !segment.is_empty()
+            && !segment.starts_with(|c: char| c.is_ascii_digit())
+            && segment
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '#'))
+    };
+    if receiver.replace("::", ".").split('.').all(is_name) {
+        callee
+    } else {
+        format!("{}.{}", EXPRESSION_RECEIVER, method)
     }
 }
 
-/// Checks if an AST node represents a function definition in the given language.
+/// Checks whether an expression is called where it is written, like the closure of
+/// `(() => f())()`, i.e. is the function of a call, possibly in parentheses.
 ///
 /// # Arguments
 ///
-/// * kind - The kind (type) of the AST node.
-/// * language - The tree-sitter Language of the file being parsed.
-///
-/// # Returns
-///
-/// true if the node represents a function definition, false otherwise.
-fn is_function_node(kind: &str, language: Language) -> bool {
-    match language {
-        lang if lang == unsafe { tree_sitter_rust() } => kind == "function_item",
-        lang if lang == unsafe { tree_sitter_python() } => kind == "function_definition",
-        lang if lang == unsafe { tree_sitter_javascript() } => kind == "function_declaration",
-        // Add more language-specific checks here
-        _ => false,
+/// * `node` - The AST node of the expression.
+fn is_called(node: Node) -> bool {
+    let mut expression = node;
+    while let Some(parent) = expression.parent() {
+        if parent.kind() != "parenthesized_expression" {
+            return parent
+                .child_by_field_name("function")
+                .is_some_and(|function| function.id() == expression.id());
+        }
+        expression = parent;
     }
+    false
 }
 
-/// Extracts the function name from a function definition AST node.
+/// Counts the arguments of a call expression.
 ///
 /// # Arguments
 ///
-/// * code - The code string of the file being parsed.
-/// * node - The function definition AST node to extract the name from.
-/// * language - The tree-sitter Language of the file being parsed.
+/// * `node` - The AST node of the call.
 ///
 /// # Returns
 ///
-/// An Option containing the function name, if successfully extracted.
-fn get_function_name(code: &str, node: Node, language: Language) -> Option<String> {
-    match language {
-        lang if lang == unsafe { tree_sitter_rust() } => node
-            .child_by_field_name("name")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        lang if lang == unsafe { tree_sitter_python() } => node             
-            .child_by_field_name("name")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        lang if lang == unsafe { tree_sitter_javascript() } => node
-            .child_by_field_name("name")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        lang if lang == unsafe { tree_sitter_javascript() } => node
-            .child_by_field_name("name")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        // Add more language-specific checks here
-        _ => None,
-    }
+/// An `Option` containing the number of arguments in the `arguments` field of the
+/// node, or `None` if it has none, like a function referenced as a value.
+fn argument_count(node: Node) -> Option<usize> {
+    let arguments = node.child_by_field_name("arguments")?;
+    let mut cursor = arguments.walk();
+    let count = arguments
+        .named_children(&mut cursor)
+        .filter(|argument| !argument.is_extra())
+        .count();
+    Some(count)
 }
 
-/// Checks if an AST node represents a function call expression in the given language.
+/// Finds the function calls made within a given AST node.
+///
+/// Calls made inside nested functions, closures and classes are skipped; they are
+/// attributed to the nested scope when the traversal reaches it. Calls to functions
+/// defined in an enclosing function are resolved right away, since they shadow any
+/// module-level or imported function of the same name, while parameters and variables
+/// passed as callbacks are skipped for the same reason. Method calls on a receiver of
+/// known type are recorded as calls on the type.
 ///
 /// # Arguments
 ///
-/// * kind - The kind (type) of the AST node.
-/// * language - The tree-sitter Language of the file being parsed.
+/// * `context` - The `TraversalContext` of the file being parsed, with the scope of
+///   `root` on top of its scope stack.
+/// * `root` - The AST node to search for function calls.
+/// * `caller` - The index of the block of `root` in `TraversalContext::blocks`.
 ///
 /// # Returns
 ///
-/// true if the node represents a function call expression, false otherwise.
-fn is_call_expression(kind: &str, language: Language) -> bool {
-    match language {
-        lang if lang == unsafe { tree_sitter_rust() } => kind == "call_expression",
-        lang if lang == unsafe { tree_sitter_python() } => kind == "call",
-        lang if lang == unsafe { tree_sitter_javascript() } => kind == "call_expression",
-        // Add more language-specific checks here
-        _ => false,
+/// A vector of `CallSite`s representing the calls, in source order.
+fn find_calls(context: &TraversalContext, root: Node, caller: usize) -> Vec<CallSite> {
+    let code = context.code;
+    let mut calls = Vec::new();
+    let mut cursor = root.walk();
+
+    loop {
+        let node = cursor.node();
+        let definition = context.tags.definitions.contains_key(&node.id());
+        let nested = node != root && definition;
+
+        // A call of `super` that only gets the receiver of another call is not a call
+        // of its own.
+        let receiver = context.tags.calls.get(&node.id()).is_some_and(|call| {
+            context
+                .language
+                .super_names()
+                .contains(&call.callee.as_str())
+                && code[node.end_byte()..].trim_start().starts_with('.')
+        });
+
+        // A parameter or variable passed as a callback refers to whatever it holds,
+        // not to the function of the same name it shadows.
+        let shadowed = context.tags.calls.get(&node.id()).is_some_and(|call| {
+            call.kind == Some(EdgeKind::CallbackReference) && context.is_variable(&call.callee)
+        });
+
+        // A closure captured as a call, such as a spawned one, is created rather than
+        // called here.
+        if !definition && !receiver && !shadowed {
+            if let Some(call) = context.tags.calls.get(&node.id()) {
+                let typed_callee = context.typed_callee(&call.callee, node.start_byte());
+                let (callee, super_call, dynamic_receiver) = match typed_callee {
+                    Some(typed) => (typed.callee, typed.super_call, typed.dynamic_receiver),
+                    None => (call.callee.clone(), false, false),
+                };
+                let callee = collapse_receiver(callee);
+                let local_target = if callee.contains('.') {
+                    None
+                } else {
+                    context.resolve_local(&callee).cloned()
+                };
+
+                calls.push(CallSite {
+                    caller,
+                    callee,
+                    position: source_position(code, node.start_byte(), node.start_position()),
+                    local_target,
+                    super_call,
+                    dynamic_receiver,
+                    kind: call.kind,
+                    arguments: argument_count(node),
+                });
+            }
+        }
+
+        if nested || !cursor.goto_first_child() {
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return calls;
+                }
+            }
+        }
     }
 }
 
-/// Extracts the called function name from a function call expression AST node.
-///
-/// # Arguments
-///
-/// * code - The code string of the file being parsed.
-/// * node - The function call expression AST node to extract the name from.
-/// * language - The tree-sitter Language of the file being parsed.
-///
-/// # Returns
-///
-/// An Option containing the called function name, if successfully extracted.
-fn get_call_expression_name(code: &str, node: Node, language: Language) -> Option<String> {
-    match language {
-        lang if lang == unsafe { tree_sitter_rust() } => node
-            .child_by_field_name("function")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        lang if lang == unsafe { tree_sitter_python() } => node
-            .child_by_field_name("function")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        lang if lang == unsafe { tree_sitter_javascript() } => node
-            .child_by_field_name("function")
-            .and_then(|child| Some(child.utf8_text(code.as_bytes()).unwrap()))
-            .map(|s| s.to_string()),
-        // Add more language-specific checks here
-        _ => None,
-    }
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::block::{BlockType, EdgeKind, Resolution};
+    use crate::fixture::Fixture;
+    use crate::indexer::Index;
+
+    /// Returns the qualified names of the functions of a file of the fixture, with the
+    /// classes they belong to and the lines they start on.
+    fn functions(
+        fixture: &Fixture,
+        index: &Index,
+        path: &str,
+    ) -> Vec<(String, Option<String>, usize)> {
+        let file_path = fixture.root().join(path);
+        index
+            .blocks
+            .iter()
+            .filter(|block| {
+                block.block_type == BlockType::Function
+                    && Path::new(&block.location.file_path) == file_path
+            })
+            .map(|block| {
+                (
+                    block.qualified_name.clone(),
+                    block.class_name.clone(),
+                    block.location.start.line,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn infers_the_type_of_receivers_from_how_they_are_bound() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "struct Dog;\n\nimpl Dog {\n    fn new() -> Dog {\n        Dog\n    }\n\n    fn greet(&self) {}\n}\n\nfn unit() {\n    let d = Dog;\n    d.greet();\n}\n\nfn literal() {\n    let d = Dog {};\n    d.greet();\n}\n\nfn annotated(d: &Dog) {\n    d.greet();\n}\n\nfn constructed() {\n    let d = Dog::new();\n    d.greet();\n}\n\nfn unknown(d: u8) {\n    let e = d;\n    e.greet();\n}\n",
+            ),
+            (
+                "calc.py",
+                "class Calc:\n    def add(self):\n        pass\n\ndef main(other: Calc):\n    c = Calc()\n    c.add()\n    other.add()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        let greet = fixture.key("src/lib.rs", "Dog.greet");
+        for function in ["unit", "literal", "annotated"] {
+            assert_eq!(
+                fixture.callees(&index, "src/lib.rs", function),
+                [greet.as_str()]
+            );
+        }
+        assert_eq!(
+            fixture.callees(&index, "src/lib.rs", "constructed"),
+            [fixture.key("src/lib.rs", "Dog.new"), greet]
+        );
+        // A variable bound to anything but a type keeps its unknown receiver.
+        let unknown = fixture.callees(&index, "src/lib.rs", "unknown");
+        assert_eq!(unknown, [fixture.key("src/lib.rs", "e.greet")]);
+        assert!(matches!(
+            index.call_stack.get_node(&unknown[0]).unwrap().resolution,
+            Resolution::Unresolved { .. }
+        ));
+
+        let add = fixture.key("calc.py", "Calc.add");
+        assert_eq!(
+            fixture.callees(&index, "calc.py", "main"),
+            [fixture.key("calc.py", "Calc"), add]
+        );
+    }
+
+    #[test]
+    fn skips_callbacks_shadowed_by_parameters_and_variables() {
+        let fixture = Fixture::new(&[
+            (
+                "app.py",
+                "def helper():\n    pass\n\ndef passes():\n    pick(key=helper)\n\ndef parameter(helper):\n    pick(key=helper)\n\ndef variable():\n    helper = 1\n    pick(helper)\n\ndef lambdas():\n    pick(lambda helper: pick(helper))\n",
+            ),
+            (
+                "timer.js",
+                "function tick() {}\n\nfunction passes() {\n  setTimeout(tick, 10);\n}\n\nfunction later(tick) {\n  setTimeout(tick, 10);\n}\n\nfunction declared() {\n  const tick = 1;\n  setTimeout(tick, 10);\n}\n\nconst deferred = (tick) => setTimeout(tick, 10);\n",
+            ),
+            (
+                "src/lib.rs",
+                "fn parse() {}\n\nfn passes() {\n    run(parse);\n}\n\nfn parameter(parse: fn()) {\n    run(parse);\n}\n\nfn bound() {\n    let parse = 1;\n    run(parse);\n}\n\nfn closure() {\n    run(|parse: fn()| run(parse));\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        for (path, function, callers) in [
+            (
+                "app.py",
+                "helper",
+                ["parameter", "variable", "lambdas.<locals>.<lambda#0>"],
+            ),
+            ("timer.js", "tick", ["later", "declared", "deferred"]),
+            (
+                "src/lib.rs",
+                "parse",
+                ["parameter", "bound", "closure.{closure#0}"],
+            ),
+        ] {
+            let function = fixture.key(path, function);
+            let edge = index
+                .call_graph
+                .edge(&fixture.key(path, "passes"), &function)
+                .unwrap();
+            assert_eq!(edge.kind, EdgeKind::CallbackReference, "{}", path);
+            for caller in callers {
+                let caller = fixture.key(path, caller);
+                assert!(
+                    index.call_graph.edge(&caller, &function).is_none(),
+                    "{}",
+                    caller
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn creating_a_closure_refers_to_it_as_a_callback() {
+        let fixture = Fixture::new(&[
+            (
+                "app.jsx",
+                "function f() {}\nfunction g() {}\nfunction util() {}\n\nfunction main(arr, ready) {\n  arr.map(() => f());\n  const h = ready ? () => g() : null;\n  (() => f())();\n  return <button onClick={() => util()}>x</button>;\n}\n",
+            ),
+            (
+                "app.py",
+                "def f():\n    pass\n\ndef main(arr):\n    map(lambda x: f(), arr)\n    h = lambda: f()\n",
+            ),
+            (
+                "src/lib.rs",
+                "fn f() {}\n\nfn main(v: Vec<u8>) {\n    v.iter().map(|x| f());\n    let h = || f();\n    std::thread::spawn(|| f());\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        for (path, closures) in [
+            (
+                "app.jsx",
+                [
+                    ("main.{closure#0}", EdgeKind::CallbackReference),
+                    ("main.{closure#1}", EdgeKind::CallbackReference),
+                    ("main.{closure#2}", EdgeKind::Direct),
+                    ("main.{closure#3}", EdgeKind::CallbackReference),
+                ]
+                .as_slice(),
+            ),
+            (
+                "app.py",
+                &[
+                    ("main.<locals>.<lambda#0>", EdgeKind::CallbackReference),
+                    ("main.<locals>.<lambda#1>", EdgeKind::CallbackReference),
+                ],
+            ),
+            (
+                "src/lib.rs",
+                &[
+                    ("main.{closure#0}", EdgeKind::CallbackReference),
+                    ("main.{closure#1}", EdgeKind::CallbackReference),
+                    ("main.{closure#2}", EdgeKind::Spawn),
+                ],
+            ),
+        ] {
+            let main = fixture.key(path, "main");
+            for (closure, kind) in closures {
+                let closure = fixture.key(path, closure);
+                let edge = index.call_graph.edge(&main, &closure).unwrap();
+                assert_eq!(edge.kind, *kind, "{}", closure);
+            }
+        }
+    }
+
+    #[test]
+    fn extracts_javascript_classes_methods_and_bound_functions() {
+        let fixture = Fixture::new(&[(
+            "app.js",
+            "class Foo {\n  constructor() {\n    this.x = helper();\n  }\n\n  bar() {\n    helper();\n  }\n\n  static make() {\n    return new Foo();\n  }\n\n  *items() {}\n\n  handle = () => helper();\n}\n\nconst arrow = () => helper();\nconst expr = function () {\n  helper();\n};\nconst gen = function* () {};\nconst obj = {\n  method() {\n    helper();\n  },\n  prop: () => helper(),\n};\n\nexport default function () {\n  helper();\n}\n\nfunction* walk() {}\n\nfunction helper() {}\n\nfunction main() {\n  const foo = new Foo();\n  foo.bar();\n  Foo.make();\n  arrow();\n  expr();\n  obj.method();\n  obj.prop();\n  walk();\n}\n",
+        )]);
+        let index = fixture.index();
+
+        let class = |name: &str| Some(name.to_string());
+        let expected = [
+            ("Foo.constructor", class("Foo"), 2),
+            ("Foo.bar", class("Foo"), 6),
+            ("Foo.make", class("Foo"), 10),
+            ("Foo.items", class("Foo"), 14),
+            ("Foo.handle", class("Foo"), 16),
+            ("arrow", None, 19),
+            ("expr", None, 20),
+            ("gen", None, 23),
+            ("obj.method", class("obj"), 25),
+            ("obj.prop", class("obj"), 28),
+            ("default", None, 31),
+            ("walk", None, 35),
+            ("helper", None, 37),
+            ("main", None, 39),
+        ]
+        .map(|(name, class, line)| (name.to_string(), class, line));
+        assert_eq!(functions(&fixture, &index, "app.js"), expected);
+
+        let constructor = fixture.key("app.js", "Foo.constructor");
+        assert_eq!(
+            fixture.callees(&index, "app.js", "main"),
+            [
+                "Foo.constructor",
+                "Foo.bar",
+                "Foo.make",
+                "arrow",
+                "expr",
+                "obj.method",
+                "obj.prop",
+                "walk",
+            ]
+            .map(|callee| fixture.key("app.js", callee))
+        );
+        assert_eq!(
+            fixture.callees(&index, "app.js", "Foo.make"),
+            [constructor.as_str()]
+        );
+        let edge = index
+            .call_graph
+            .edge(&fixture.key("app.js", "Foo.make"), &constructor)
+            .unwrap();
+        assert_eq!(edge.kind, EdgeKind::Constructor);
+    }
+
+    #[test]
+    fn extracts_typescript_declarations_and_signatures() {
+        let fixture = Fixture::new(&[(
+            "app.ts",
+            "interface Shape {\n  area(): number;\n}\n\nabstract class Base implements Shape {\n  abstract area(): number;\n\n  describe(): string {\n    return format(this.area());\n  }\n}\n\nclass Square extends Base {\n  area(): number {\n    return 1;\n  }\n}\n\nfunction format(value: number): string;\nfunction format(value: string): string;\nfunction format(value: unknown): string {\n  return String(value);\n}\n\ndeclare function external(name: string): void;\n\ndeclare class Legacy {\n  run(): void;\n}\n\nnamespace Geometry {\n  export function make<T extends Shape>(kind: T): Shape {\n    return new Square();\n  }\n}\n\nfunction main(shape: Shape) {\n  const square = new Square();\n  square.describe();\n  shape.area();\n  external(format(Geometry.make<Shape>(shape)));\n}\n",
+        )]);
+        let index = fixture.index();
+
+        // Overload signatures are left out in favor of the implementation.
+        let class = |name: &str| Some(name.to_string());
+        let expected = [
+            ("Shape.area", class("Shape"), 2),
+            ("Base.area", class("Base"), 6),
+            ("Base.describe", class("Base"), 8),
+            ("Square.area", class("Square"), 14),
+            ("format", None, 21),
+            ("external", None, 25),
+            ("Legacy.run", class("Legacy"), 28),
+            ("Geometry.make", class("Geometry"), 32),
+            ("main", None, 37),
+        ]
+        .map(|(name, class, line)| (name.to_string(), class, line));
+        assert_eq!(functions(&fixture, &index, "app.ts"), expected);
+
+        assert_eq!(
+            fixture.callees(&index, "app.ts", "main"),
+            [
+                "Square",
+                "Base.describe",
+                "Shape.area",
+                "external",
+                "format",
+                "Geometry.make",
+            ]
+            .map(|callee| fixture.key("app.ts", callee))
+        );
+        assert_eq!(
+            fixture.callees(&index, "app.ts", "Base.describe"),
+            ["format", "Base.area"].map(|callee| fixture.key("app.ts", callee))
+        );
+        assert!(index.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn calls_through_a_variable_reach_the_closure_bound_to_it() {
+        let fixture = Fixture::new(&[
+            (
+                "src/lib.rs",
+                "fn helper() {}\n\nfn outer() {\n    let c = |x: u8| helper();\n    c(1);\n}\n",
+            ),
+            (
+                "app.py",
+                "def helper():\n    pass\n\nf = lambda: helper()\n\ndef main():\n    g = lambda: helper()\n    g()\n    f()\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        let kinds = |path: &str, caller: &str| -> Vec<(String, EdgeKind)> {
+            let caller = fixture.key(path, caller);
+            index
+                .call_graph
+                .edges()
+                .iter()
+                .filter(|edge| edge.from == caller)
+                .map(|edge| (edge.to.clone(), edge.kind))
+                .collect()
+        };
+        // A closure bound in the function is created there, and called too.
+        let closure = fixture.key("src/lib.rs", "outer.{closure#0}");
+        assert_eq!(
+            kinds("src/lib.rs", "outer"),
+            [
+                (closure.clone(), EdgeKind::CallbackReference),
+                (closure, EdgeKind::Direct),
+            ]
+        );
+        let lambda = fixture.key("app.py", "main.<locals>.<lambda#0>");
+        assert_eq!(
+            kinds("app.py", "main"),
+            [
+                (lambda.clone(), EdgeKind::CallbackReference),
+                (lambda, EdgeKind::Direct),
+                (fixture.key("app.py", "<lambda#0>"), EdgeKind::Direct),
+            ]
+        );
+
+        for (path, closure) in [
+            ("src/lib.rs", "outer.{closure#0}"),
+            ("app.py", "main.<locals>.<lambda#0>"),
+            ("app.py", "<lambda#0>"),
+        ] {
+            assert_eq!(
+                fixture.callees(&index, path, closure),
+                [fixture.key(path, "helper")]
+            );
+        }
+        // No call is left to a function named after the variable.
+        for (path, variable) in [("src/lib.rs", "c"), ("app.py", "g"), ("app.py", "f")] {
+            let key = fixture.key(path, variable);
+            assert!(index.call_stack.get_node(&key).is_none(), "{}", key);
+        }
+    }
+}
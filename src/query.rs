@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::block::EdgeKind;
+
+/// The name of the query file looked up in each language's query directory.
+pub const TAGS_FILE_NAME: &str = "tags.scm";
+
+/// Represents the kind of a definition captured by a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    /// A named function, method or macro (`@definition.function`, `@definition.method`,
+    /// `@definition.macro`).
+    Function,
+    /// An anonymous function (`@definition.closure`).
+    Closure,
+    /// A scope that owns methods (`@definition.class`, `@definition.interface`,
+    /// `@definition.module`, `@definition.trait`, `@definition.impl`, ...).
+    Class,
+}
+
+impl DefinitionKind {
+    /// Maps the suffix of a `@definition.*` capture to a `DefinitionKind`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the kind, or `None` for definitions stackwalk does not
+    /// track, such as constants.
+    fn from_capture_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "function" | "method" | "macro" => Some(DefinitionKind::Function),
+            "closure" => Some(DefinitionKind::Closure),
+            "class" | "interface" | "module" | "namespace" | "trait" | "impl" | "struct"
+            | "enum" => Some(DefinitionKind::Class),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of type a scope that owns methods defines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassKind {
+    /// A class (`@definition.class`).
+    #[default]
+    Class,
+    /// An interface (`@definition.interface`).
+    Interface,
+    /// A trait (`@definition.trait`).
+    Trait,
+    /// A type whose methods are defined apart from it, like a Rust struct with `impl`
+    /// blocks (`@definition.impl`, `@definition.struct`, `@definition.enum`).
+    Type,
+    /// A module or an object grouping functions, which is not a type
+    /// (`@definition.module`, `@definition.namespace`).
+    Module,
+}
+
+impl ClassKind {
+    /// Maps the suffix of a `@definition.*` capture of a scope to a `ClassKind`.
+    fn from_capture_suffix(suffix: &str) -> Self {
+        match suffix {
+            "interface" => ClassKind::Interface,
+            "trait" => ClassKind::Trait,
+            "impl" | "struct" | "enum" => ClassKind::Type,
+            "module" | "namespace" => ClassKind::Module,
+            _ => ClassKind::Class,
+        }
+    }
+}
+
+/// Represents a definition captured by a query.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    /// The kind of the definition.
+    pub kind: DefinitionKind,
+    /// The text of the `@name` capture, if any.
+    pub name: Option<String>,
+    /// The text of the `@trait` capture, if any.
+    pub trait_name: Option<String>,
+    /// The kind of type the definition defines, for definitions of kind `Class`.
+    pub class_kind: Option<ClassKind>,
+    /// The texts of the `@base` captures of every pattern matching the definition, in
+    /// source order.
+    pub bases: Vec<String>,
+    /// The texts of the `@implements` captures of every pattern matching the
+    /// definition, in source order.
+    pub implements: Vec<String>,
+}
+
+/// Represents one imported binding captured by a query.
+///
+/// A pattern may set `import.name` to a fixed name with `(#set! import.name "...")`,
+/// e.g. to import the `default` export of a JavaScript module.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportTag {
+    /// The text of the `@import.module` capture, with string quotes removed.
+    pub module: String,
+    /// The text of the `@import.name` capture, or the name set by the pattern, if any.
+    pub name: Option<String>,
+    /// The text of the `@import.alias` capture, if any.
+    pub alias: Option<String>,
+    /// Whether the import is captured by an `@import.fallback` pattern too, i.e. it
+    /// only runs when an earlier import of the same binding failed.
+    #[serde(default)]
+    pub fallback: bool,
+    /// Whether the import makes the binding available to other modules too, like
+    /// Rust's `pub use`, i.e. it is captured by an `@import.reexport` pattern too.
+    #[serde(default)]
+    pub reexport: bool,
+    /// The names of the inline modules the import is made in, outermost first, like
+    /// `ModuleTag::parents`. The import only binds its name in the innermost one.
+    #[serde(default)]
+    pub parents: Vec<String>,
+}
+
+/// Represents a definition that a module exports under another name, such as
+/// JavaScript's `export { helper as assist }` or `export default main`.
+///
+/// A pattern may set `export.alias` to a fixed name with `(#set! export.alias "...")`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportTag {
+    /// The text of the `@export.name` capture, i.e. the name of the definition in the
+    /// module.
+    pub name: String,
+    /// The text of the `@export.alias` capture, or the name set by the pattern, i.e.
+    /// the name other modules import the definition by.
+    pub alias: String,
+}
+
+/// Represents a call captured by a query, or a function referenced as a value, such as
+/// a callback passed as an argument (`@reference.callback`).
+///
+/// A pattern may set the kind of the call with `(#set! reference.kind "...")`, e.g. to
+/// mark the calls whose futures are spawned as tasks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallTag {
+    /// The text of the `@name` capture, i.e. the callee expression.
+    pub callee: String,
+    /// The kind set by the pattern, if any.
+    pub kind: Option<EdgeKind>,
+}
+
+/// Represents a variable whose type a query captured, such as `c` in `c = Calc()` or a
+/// parameter annotated with its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedBinding {
+    /// The text of the `@binding.name` capture, i.e. the name of the variable.
+    pub name: String,
+    /// The text of the `@binding.type` capture, i.e. the name of the type.
+    pub type_name: String,
+    /// Whether the variable is known to hold a value of exactly that type, rather than
+    /// of any subtype, as set by the pattern with `(#set! binding.exact "true")`.
+    pub exact: bool,
+}
+
+/// Represents a declaration of a submodule captured by a query, such as Rust's
+/// `mod person;`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModuleTag {
+    /// The text of the `@module.name` capture.
+    pub name: String,
+    /// The text of the `@module.path` capture, with string quotes removed, if any.
+    pub path: Option<String>,
+    /// The names of the enclosing declarations of the same kind, outermost first.
+    pub parents: Vec<String>,
+    /// Whether the module is defined inline, i.e. the declaration has a body, rather
+    /// than in a file of its own.
+    pub inline: bool,
+}
+
+/// The definitions, calls and imports a query found in a syntax tree.
+///
+/// Definitions and calls are keyed by the id of the captured node, so the traversal
+/// can look up any node it visits.
+#[derive(Debug, Default)]
+pub struct Tags {
+    /// The definitions, keyed by node id.
+    pub definitions: HashMap<usize, Definition>,
+    /// The calls and function references, keyed by the node id of the call or
+    /// reference.
+    pub calls: HashMap<usize, CallTag>,
+    /// The imported bindings, in source order.
+    pub imports: Vec<ImportTag>,
+    /// The submodule declarations, in source order.
+    pub modules: Vec<ModuleTag>,
+    /// The definitions exported under another name, in source order.
+    pub exports: Vec<ExportTag>,
+    /// The variables whose type is known, keyed by the id of the captured node.
+    pub bindings: HashMap<usize, TypedBinding>,
+    /// The names of the parameters and local variables, keyed by the id of the
+    /// captured node.
+    pub variables: HashMap<usize, String>,
+}
+
+/// Runs a query over a syntax tree and collects the captures stackwalk understands.
+///
+/// When several patterns capture the same node, the first pattern in the query wins.
+///
+/// # Arguments
+///
+/// * `query` - The compiled query for the language of the tree.
+/// * `root` - The root node of the syntax tree.
+/// * `code` - The code string the tree was parsed from.
+///
+/// # Returns
+///
+/// The `Tags` found in the tree.
+pub fn collect_tags(query: &Query, root: Node, code: &str) -> Tags {
+    let mut tags = Tags::default();
+    let mut cursor = QueryCursor::new();
+    let capture_names = query.capture_names();
+    let text = |node: Node| {
+        node.utf8_text(code.as_bytes())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let mut matches: Vec<_> = cursor
+        .matches(query, root, code.as_bytes())
+        .map(|query_match| {
+            let captures: Vec<_> = query_match
+                .captures
+                .iter()
+                .map(|capture| (capture_names[capture.index as usize].as_str(), capture.node))
+                .collect();
+            (query_match.pattern_index, captures)
+        })
+        .collect();
+    matches.sort_by_key(|(pattern_index, _)| *pattern_index);
+
+    // The value of a `#set!` property of a pattern.
+    let setting = |pattern_index: usize, key: &str| {
+        query
+            .property_settings(pattern_index)
+            .iter()
+            .find(|property| &*property.key == key)
+            .and_then(|property| property.value.as_deref())
+            .map(str::to_string)
+    };
+
+    let mut bases = Vec::new();
+    let mut implements = Vec::new();
+    let mut imports = Vec::new();
+    let mut fallbacks = HashSet::new();
+    let mut reexports = HashSet::new();
+    let mut exports = Vec::new();
+    let mut modules = Vec::new();
+    let mut module_ids = HashSet::new();
+    let unquote = |node: Node| text(node).trim_matches(['"', '\'']).to_string();
+
+    for (pattern_index, captures) in matches {
+        let capture = |name: &str| {
+            captures
+                .iter()
+                .find(|(capture_name, _)| *capture_name == name)
+                .map(|(_, node)| *node)
+        };
+
+        for (capture_name, node) in &captures {
+            if let Some(suffix) = capture_name.strip_prefix("definition.") {
+                let Some(kind) = DefinitionKind::from_capture_suffix(suffix) else {
+                    continue;
+                };
+                tags.definitions.entry(node.id()).or_insert(Definition {
+                    kind,
+                    name: capture("name").map(unquote),
+                    trait_name: capture("trait").map(text),
+                    class_kind: (kind == DefinitionKind::Class)
+                        .then(|| ClassKind::from_capture_suffix(suffix)),
+                    bases: Vec::new(),
+                    implements: Vec::new(),
+                });
+                // A class may extend or implement several types, each matched separately.
+                for (name, supertype) in &captures {
+                    let supertype = (node.id(), supertype.start_byte(), text(*supertype));
+                    match *name {
+                        "base" => bases.push(supertype),
+                        "implements" => implements.push(supertype),
+                        _ => {}
+                    }
+                }
+            } else if *capture_name == "reference.call" || *capture_name == "reference.callback" {
+                if let Some(name) = capture("name") {
+                    // A function referenced as a value is a callback unless the pattern
+                    // says otherwise.
+                    let kind = setting(pattern_index, "reference.kind")
+                        .and_then(|kind| EdgeKind::from_name(&kind))
+                        .or((*capture_name == "reference.callback")
+                            .then_some(EdgeKind::CallbackReference));
+                    tags.calls.entry(node.id()).or_insert_with(|| CallTag {
+                        callee: text(name),
+                        kind,
+                    });
+                }
+            } else if *capture_name == "import" {
+                if let Some(module) = capture("import.module") {
+                    let import = ImportTag {
+                        module: text(module).trim_matches(['"', '\'', '`']).to_string(),
+                        name: capture("import.name")
+                            .map(text)
+                            .or_else(|| setting(pattern_index, "import.name")),
+                        alias: capture("import.alias").map(text),
+                        fallback: false,
+                        reexport: false,
+                        parents: Vec::new(),
+                    };
+                    let binding = capture("import.alias")
+                        .or(capture("import.name"))
+                        .unwrap_or(module);
+                    imports.push(((node.start_byte(), binding.start_byte()), node.id(), import));
+                }
+            } else if *capture_name == "binding" {
+                if let (Some(name), Some(type_name)) =
+                    (capture("binding.name"), capture("binding.type"))
+                {
+                    tags.bindings.entry(node.id()).or_insert(TypedBinding {
+                        name: text(name),
+                        type_name: text(type_name),
+                        exact: setting(pattern_index, "binding.exact").is_some(),
+                    });
+                }
+            } else if *capture_name == "variable" {
+                tags.variables.insert(node.id(), text(*node));
+            } else if *capture_name == "import.fallback" {
+                fallbacks.insert(node.id());
+            } else if *capture_name == "import.reexport" {
+                reexports.insert(node.id());
+            } else if *capture_name == "export" {
+                let alias = capture("export.alias")
+                    .map(unquote)
+                    .or_else(|| setting(pattern_index, "export.alias"));
+                if let (Some(name), Some(alias)) = (capture("export.name"), alias) {
+                    let export = ExportTag {
+                        name: text(name),
+                        alias,
+                    };
+                    exports.push((name.start_byte(), export));
+                }
+            } else if *capture_name == "module" {
+                let Some(name) = capture("module.name") else {
+                    continue;
+                };
+                if module_ids.insert(node.id()) {
+                    let module = ModuleTag {
+                        name: text(name),
+                        path: capture("module.path").map(unquote),
+                        parents: enclosing_names(*node, code),
+                        inline: node.child_by_field_name("body").is_some(),
+                    };
+                    modules.push((node.start_byte(), module));
+                }
+            }
+        }
+    }
+
+    imports.sort_by_key(|(start_bytes, _, _)| *start_bytes);
+    tags.imports = imports
+        .into_iter()
+        .map(|(_, node_id, mut import)| {
+            import.fallback = fallbacks.contains(&node_id);
+            import.reexport = reexports.contains(&node_id);
+            import
+        })
+        .collect();
+    exports.sort_by_key(|(start_byte, _)| *start_byte);
+    tags.exports = exports.into_iter().map(|(_, export)| export).collect();
+    modules.sort_by_key(|(start_byte, _)| *start_byte);
+    tags.modules = modules.into_iter().map(|(_, module)| module).collect();
+    bases.sort();
+    bases.dedup();
+    for (node_id, _, base) in bases {
+        if let Some(definition) = tags.definitions.get_mut(&node_id) {
+            definition.bases.push(base);
+        }
+    }
+    implements.sort();
+    implements.dedup();
+    for (node_id, _, implemented) in implements {
+        if let Some(definition) = tags.definitions.get_mut(&node_id) {
+            definition.implements.push(implemented);
+        }
+    }
+
+    tags
+}
+
+/// Returns the names of the ancestors of a node that are of the same kind as the node,
+/// such as the inline modules enclosing a module declaration.
+///
+/// # Arguments
+///
+/// * `node` - The node whose ancestors to name.
+/// * `code` - The code string the tree was parsed from.
+///
+/// # Returns
+///
+/// The text of the `name` field of the ancestors, outermost first.
+fn enclosing_names(node: Node, code: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut parent = node.parent();
+
+    while let Some(ancestor) = parent {
+        if ancestor.kind() == node.kind() {
+            if let Some(name) = ancestor.child_by_field_name("name") {
+                let name = name.utf8_text(code.as_bytes()).unwrap_or_default();
+                names.push(name.to_string());
+            }
+        }
+        parent = ancestor.parent();
+    }
+
+    names.reverse();
+    names
+}
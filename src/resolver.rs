@@ -0,0 +1,1246 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::block::{
+    Block, BlockType, CallLocation, EdgeKind, OutgoingCall, Resolution, UnresolvedReason,
+};
+use crate::cargo::Workspace;
+use crate::config::{Config, Dispatch};
+use crate::hierarchy::{ClassHierarchy, ClassNode};
+use crate::indexer::generate_node_key;
+use crate::language::{LanguageSupport, ResolvedImport};
+use crate::parser::{ParsedFile, EXPRESSION_RECEIVER};
+use crate::query::{ClassKind, ImportTag};
+
+/// The calls of a `ParsedFile`, resolved to the node keys of their callees.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResolvedCalls {
+    /// The outgoing calls of every block, indexed like `ParsedFile::blocks`.
+    pub outgoing_calls: Vec<Vec<OutgoingCall>>,
+    /// The modules the imports of the file resolved to, and the other files their
+    /// resolution depends on, such as configuration files, sorted.
+    ///
+    /// The calls have to be resolved again whenever one of these files changes.
+    pub dependencies: Vec<String>,
+    /// The classes of the file with their bases resolved, keyed by class key.
+    #[serde(default)]
+    pub classes: BTreeMap<String, ClassNode>,
+}
+
+impl ResolvedCalls {
+    /// Returns the blocks of a `ParsedFile` with their outgoing calls filled in.
+    ///
+    /// # Arguments
+    ///
+    /// * `parsed_file` - The `ParsedFile` the calls were resolved for.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the file's `Block`s.
+    pub fn apply(&self, parsed_file: &ParsedFile) -> Vec<Block> {
+        parsed_file
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                let mut block = block.clone();
+                block.outgoing_calls = self.outgoing_calls.get(index).cloned().unwrap_or_default();
+                block
+            })
+            .collect()
+    }
+}
+
+/// The `@import.name` of an import that binds every top-level definition of its module.
+const WILDCARD: &str = "*";
+
+/// The indexed tree that the imports and calls of a file are resolved against.
+pub struct ResolveContext<'a> {
+    /// The configuration the tree is indexed with.
+    pub config: &'a Config,
+    /// The Cargo packages of the tree.
+    pub workspace: &'a Workspace,
+    /// The parsed files of the tree, keyed by module name.
+    files: HashMap<&'a str, &'a ParsedFile>,
+    /// The classes of the tree and the classes they extend.
+    hierarchy: ClassHierarchy,
+    /// The node keys of the functions of the tree.
+    functions: HashSet<&'a str>,
+}
+
+impl<'a> ResolveContext<'a> {
+    /// Creates a `ResolveContext` for a set of parsed files, resolving the bases of
+    /// their classes into a `ClassHierarchy`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The `Config` instance containing language-specific settings.
+    /// * `workspace` - The Cargo packages of the indexed tree.
+    /// * `files` - The parsed files of the indexed tree.
+    pub fn new(
+        config: &'a Config,
+        workspace: &'a Workspace,
+        files: impl IntoIterator<Item = &'a ParsedFile>,
+    ) -> Self {
+        let mut context = ResolveContext {
+            config,
+            workspace,
+            files: files
+                .into_iter()
+                .map(|parsed_file| (parsed_file.module_name.as_str(), parsed_file))
+                .collect(),
+            hierarchy: ClassHierarchy::new(),
+            functions: HashSet::new(),
+        };
+        context.functions = context
+            .files()
+            .flat_map(|parsed_file| &parsed_file.blocks)
+            .filter(|block| block.block_type == BlockType::Function)
+            .map(|block| block.node_key.as_str())
+            .collect();
+
+        let mut hierarchy = ClassHierarchy::new();
+        for parsed_file in context.files() {
+            let Some(language) = config.registry.get(&parsed_file.language) else {
+                continue;
+            };
+            for class in &parsed_file.classes {
+                let mut stand_ins = BTreeMap::new();
+                let mut supertype_keys = |names: &[String]| -> Vec<String> {
+                    names
+                        .iter()
+                        .map(|name| {
+                            let (key, resolution) = context.class_key(parsed_file, language, name);
+                            if resolution != Resolution::Resolved {
+                                stand_ins.insert(key.clone(), resolution);
+                            }
+                            key
+                        })
+                        .collect()
+                };
+                let bases = supertype_keys(&class.bases);
+                let implements = supertype_keys(&class.implements);
+                let class_node = ClassNode {
+                    file_path: parsed_file.module_name.clone(),
+                    name: class.name.clone(),
+                    kind: class.kind,
+                    bases,
+                    implements,
+                    stand_ins,
+                    methods: class.methods.clone(),
+                };
+                hierarchy.add_class(class.node_key.clone(), class_node);
+            }
+        }
+        context.hierarchy = hierarchy;
+
+        context
+    }
+
+    /// Returns the parsed file of a module, if it is part of the indexed tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `module_name` - The module name, and path, of the file.
+    pub fn file(&self, module_name: &str) -> Option<&'a ParsedFile> {
+        self.files.get(module_name).copied()
+    }
+
+    /// Returns the parsed files of the indexed tree, in no particular order.
+    pub fn files(&self) -> impl Iterator<Item = &'a ParsedFile> + '_ {
+        self.files.values().copied()
+    }
+
+    /// Returns the classes of the indexed tree and the types they extend or implement.
+    pub fn hierarchy(&self) -> &ClassHierarchy {
+        &self.hierarchy
+    }
+
+    /// Resolves the name of a class, trait or interface as written in a file, such as
+    /// `Base`, `models.Base` or `fmt::Display`, to the key of the type.
+    ///
+    /// # Arguments
+    ///
+    /// * `parsed_file` - The file the name is written in.
+    /// * `language` - The language of the file.
+    /// * `name` - The name of the class.
+    ///
+    /// # Returns
+    ///
+    /// The key of the class, or the name as written if it could not be resolved, and
+    /// what it resolved to. Builtins of the language are keyed under the module
+    /// providing them.
+    fn class_key(
+        &self,
+        parsed_file: &ParsedFile,
+        language: &dyn LanguageSupport,
+        name: &str,
+    ) -> (String, Resolution) {
+        if let Some(class) = parsed_file.classes.iter().find(|class| class.name == name) {
+            return (class.node_key.clone(), Resolution::Resolved);
+        }
+        // What a class imported from a module resolves to.
+        let imported = |resolved: ResolvedImport, key: &str| {
+            let defined = self
+                .file(&resolved.module)
+                .is_some_and(|target| target.classes.iter().any(|class| class.node_key == key));
+            if resolved.external {
+                Resolution::External {
+                    module: resolved.module,
+                }
+            } else if defined {
+                Resolution::Resolved
+            } else {
+                Resolution::Unresolved {
+                    reason: UnresolvedReason::MissingDefinition,
+                }
+            }
+        };
+
+        let file_path = Path::new(&parsed_file.module_name);
+        let separator = language.path_separator();
+        let (first, rest) = match [".", separator]
+            .iter()
+            .filter_map(|separator| name.split_once(separator))
+            .min_by_key(|(first, _)| first.len())
+        {
+            Some((first, rest)) => (first, Some(rest.replace(separator, "."))),
+            None => (name, None),
+        };
+        let binding = |import: &&ImportTag| {
+            import
+                .alias
+                .as_deref()
+                .or(import.name.as_deref())
+                .unwrap_or(&import.module)
+                == first
+        };
+
+        if let Some(import) = parsed_file.imports.iter().rev().find(binding) {
+            let Some(resolved) = language.resolve_import(import, file_path, self) else {
+                let reason = UnresolvedReason::ModuleNotFound;
+                return (name.to_string(), Resolution::Unresolved { reason });
+            };
+            let imported_name = resolved.name.clone().or_else(|| import.name.clone());
+            let path: Vec<&str> = imported_name
+                .as_deref()
+                .filter(|_| !resolved.is_module)
+                .into_iter()
+                .chain(rest.as_deref())
+                .collect();
+            let key = generate_node_key(Path::new(&resolved.module), None, &path.join("."));
+            let resolution = imported(resolved, &key);
+            return (key, resolution);
+        }
+
+        // A path of its own, like Rust's `crate::shapes::Shape`, is resolved like an
+        // import of it.
+        if let Some((module, type_name)) = name.rsplit_once(separator).filter(|_| separator != ".")
+        {
+            let import = ImportTag {
+                module: module.to_string(),
+                name: Some(type_name.to_string()),
+                alias: None,
+                fallback: false,
+                reexport: false,
+                parents: Vec::new(),
+            };
+            if let Some(resolved) = language.resolve_import(&import, file_path, self) {
+                let type_name = resolved.name.as_deref().unwrap_or(type_name);
+                let key = generate_node_key(Path::new(&resolved.module), None, type_name);
+                let resolution = imported(resolved, &key);
+                return (key, resolution);
+            }
+        }
+
+        // A wildcard import binds every class of its module.
+        let wildcard_imported = parsed_file
+            .imports
+            .iter()
+            .filter(|import| import.name.as_deref() == Some(WILDCARD))
+            .filter_map(|import| language.resolve_import(import, file_path, self))
+            .filter_map(|resolved| self.file(&resolved.module))
+            .find_map(|target| {
+                target
+                    .classes
+                    .iter()
+                    .find(|class| class.name == name)
+                    .map(|class| class.node_key.clone())
+            });
+        match (wildcard_imported, language.builtin_module(name)) {
+            (Some(key), _) => (key, Resolution::Resolved),
+            (None, Some(module)) => (
+                generate_node_key(Path::new(module), None, name),
+                Resolution::External {
+                    module: module.to_string(),
+                },
+            ),
+            (None, None) => (
+                name.to_string(),
+                Resolution::Unresolved {
+                    reason: UnresolvedReason::UndefinedName,
+                },
+            ),
+        }
+    }
+}
+
+/// The names imported into a module.
+#[derive(Default)]
+struct ImportScope {
+    /// The imported bindings, keyed by the name they bind.
+    bindings: HashMap<String, Binding>,
+    /// The bindings of aliased imports, keyed by the name they were imported as.
+    original_names: HashMap<String, String>,
+}
+
+/// What an imported name refers to.
+struct Binding {
+    /// The path the name was imported from as written, e.g. `person::Person`.
+    path: String,
+    /// The module path of the module that defines the imported name.
+    module: String,
+    /// The name of the definition in `module`, or `None` if the binding is the module
+    /// itself.
+    name: Option<String>,
+    /// Whether the language resolved the import, as opposed to using the module as
+    /// written.
+    resolved: bool,
+    /// Whether the module is outside the indexed tree.
+    external: bool,
+}
+
+impl Binding {
+    /// Returns what a name reached through the binding resolves to if the indexed tree
+    /// does not define it.
+    fn resolution(&self) -> Resolution {
+        if self.external {
+            Resolution::External {
+                module: self.module.clone(),
+            }
+        } else if self.resolved {
+            Resolution::Unresolved {
+                reason: UnresolvedReason::MissingDefinition,
+            }
+        } else {
+            Resolution::Unresolved {
+                reason: UnresolvedReason::ModuleNotFound,
+            }
+        }
+    }
+}
+
+/// Returns what a name imported from a module resolves to if the indexed tree does not
+/// define it.
+///
+/// # Arguments
+///
+/// * `module` - The module path the name was imported from.
+/// * `external` - Whether the module is outside the indexed tree.
+///
+/// # Returns
+///
+/// `Resolution::External` for external modules, and an unresolved
+/// `MissingDefinition` otherwise.
+fn imported(module: &str, external: bool) -> Resolution {
+    if external {
+        Resolution::External {
+            module: module.to_string(),
+        }
+    } else {
+        Resolution::Unresolved {
+            reason: UnresolvedReason::MissingDefinition,
+        }
+    }
+}
+
+/// Finds the imported binding a dotted callee expression starts with.
+///
+/// # Arguments
+///
+/// * `imports` - The imported bindings of the file.
+/// * `callee` - The callee expression, e.g. `os.path.join`.
+///
+/// # Returns
+///
+/// An `Option` containing the binding of the longest matching prefix, e.g. the one of
+/// `os.path` for `import os.path`, and the rest of the expression.
+fn find_binding<'a, 'b>(
+    imports: &'a HashMap<String, Binding>,
+    callee: &'b str,
+) -> Option<(&'a Binding, &'b str)> {
+    callee.rmatch_indices('.').find_map(|(index, _)| {
+        imports
+            .get(&callee[..index])
+            .map(|binding| (binding, &callee[index + 1..]))
+    })
+}
+
+/// Resolves the calls of a parsed file to node keys.
+///
+/// Calls already resolved within the file keep their target. A call through an
+/// imported name is keyed under the module the import resolves to, using the name the
+/// definition has in that module rather than any alias, and any other call is keyed
+/// under the file's own module. A function called several times from the same
+/// block is reported once, at the position of its first call, with every call site.
+///
+/// Imports are applied in source order, so a later import of a name replaces an
+/// earlier one, except that a fallback import only replaces an import that could not
+/// be resolved. A wildcard import binds every top-level definition of its module.
+///
+/// In languages whose paths have a separator of their own, such as Rust's `::`, a call
+/// through a path like `animal::Animal::new` is resolved by the language like an
+/// import of that path, after expanding an imported first segment into the path it
+/// was imported from.
+///
+/// A call to a member of an imported module, such as `ns.name()`, is resolved by the
+/// language like an import of the member, so it follows what the module exports under
+/// that name.
+///
+/// A method called on a class of the tree resolves to the class that defines it,
+/// following the class's bases. A method called through `super` that no ancestor of
+/// the tree defines is keyed under the first base outside the tree, never under the
+/// calling class. Depending on the `dispatch` mode of the
+/// configuration, the call also leads to the methods overriding it in subclasses and
+/// implementations, through `Virtual` calls. A class of the tree that is called is
+/// constructed through its constructor, such as Python's `__init__`, which it may
+/// inherit; a class without one stands in for it.
+///
+/// A function that is referenced rather than called, such as a callback passed as an
+/// argument, leads to a `CallbackReference` call if it resolves to a function of the
+/// tree, and is dropped otherwise.
+///
+/// Every callee is classified by its `Resolution`. Calls to modules outside the indexed
+/// tree, such as crates that were not indexed or the standard library, and to the
+/// builtins of the language are external, and calls to anything else the tree does
+/// not define are unresolved, with the reason why.
+///
+/// # Arguments
+///
+/// * `parsed_file` - The `ParsedFile` whose calls to resolve.
+/// * `language` - The language the file was parsed as.
+/// * `context` - The `ResolveContext` of the indexed tree.
+///
+/// # Returns
+///
+/// The `ResolvedCalls` of the file.
+pub fn resolve_calls(
+    parsed_file: &ParsedFile,
+    language: &dyn LanguageSupport,
+    context: &ResolveContext,
+) -> ResolvedCalls {
+    let file_path = Path::new(&parsed_file.module_name);
+    let module_name = parsed_file.module_name.as_str();
+    let separator = language.path_separator();
+
+    // The names imported into the file, and into each inline module it defines, keyed
+    // by the path of the module within the file.
+    let mut scopes: HashMap<&[String], ImportScope> = HashMap::new();
+    let mut dependencies = BTreeSet::new();
+    for import in &parsed_file.imports {
+        let scope = scopes.entry(import.parents.as_slice()).or_default();
+        let (imports, original_names) = (&mut scope.bindings, &mut scope.original_names);
+        let resolved = language.resolve_import(import, file_path, context);
+        let is_resolved = resolved.is_some();
+        let (module, is_module, resolved_name, external) = match resolved {
+            Some(resolved) => {
+                dependencies.extend(resolved.dependencies);
+                (
+                    resolved.module,
+                    resolved.is_module,
+                    resolved.name,
+                    resolved.external,
+                )
+            }
+            None => (import.module.clone(), import.name.is_none(), None, false),
+        };
+        if !external {
+            dependencies.insert(module.clone());
+        }
+
+        if import.name.as_deref() == Some(WILDCARD) {
+            let definitions = context
+                .file(&module)
+                .map(|parsed_file| parsed_file.definitions.as_slice())
+                .unwrap_or_default();
+            for name in definitions {
+                // Names starting with an underscore are private to their module.
+                if !name.starts_with('_') {
+                    let binding = Binding {
+                        path: format!("{}{}{}", import.module, separator, name),
+                        module: module.clone(),
+                        name: Some(name.clone()),
+                        resolved: is_resolved,
+                        external,
+                    };
+                    imports.insert(name.clone(), binding);
+                }
+            }
+            continue;
+        }
+
+        let binding = import
+            .alias
+            .clone()
+            .or_else(|| import.name.clone())
+            .unwrap_or_else(|| import.module.clone());
+        // A fallback import, such as the one in the `except ImportError` branch of a
+        // `try`, only wins over an import that was not found in the tree.
+        let replaces_failed_import = match imports.get(&binding) {
+            Some(previous) => (!previous.resolved || previous.external) && is_resolved && !external,
+            None => true,
+        };
+        if import.fallback && !replaces_failed_import {
+            continue;
+        }
+
+        if let (Some(name), Some(_)) = (&import.name, &import.alias) {
+            original_names.insert(name.clone(), binding.clone());
+        }
+        let path = match &import.name {
+            Some(name) => format!("{}{}{}", import.module, separator, name),
+            None => import.module.clone(),
+        };
+        let name = resolved_name
+            .or_else(|| import.name.clone())
+            .filter(|_| !is_module);
+        imports.insert(
+            binding,
+            Binding {
+                path,
+                module,
+                name,
+                resolved: is_resolved,
+                external,
+            },
+        );
+    }
+
+    // Whether the tree has a node under a key: a function, or a class, whose node
+    // stands in for the constructor it does not define.
+    let found =
+        |key: &str| context.functions.contains(key) || context.hierarchy.class(key).is_some();
+    // A name the file does not define, nor import, may be a builtin of the language,
+    // like Python's `print`, which is then keyed under the module providing it.
+    let builtin = |key: String, name: &str, path: &str, reason: UnresolvedReason| match language
+        .builtin_module(name)
+    {
+        Some(module) if !found(&key) => (
+            generate_node_key(Path::new(module), None, path),
+            Resolution::External {
+                module: module.to_string(),
+            },
+        ),
+        _ => (key, Resolution::Unresolved { reason }),
+    };
+
+    let mut calls: Vec<_> = parsed_file.calls.iter().collect();
+    calls.sort_by_key(|call| (call.caller, call.position));
+
+    let mut outgoing_calls: Vec<Vec<OutgoingCall>> = vec![Vec::new(); parsed_file.blocks.len()];
+    // The index of the outgoing call of each block to each callee, by kind of call.
+    let mut seen: HashMap<(usize, String, EdgeKind), usize> = HashMap::new();
+
+    let no_imports = ImportScope::default();
+    for call in calls {
+        let is_path = separator != "." && call.callee.contains(separator);
+        let parents = inline_modules(parsed_file, call.caller);
+        let ImportScope {
+            bindings: imports,
+            original_names,
+        } = scopes.get(parents.as_slice()).unwrap_or(&no_imports);
+
+        // Each callee comes with what it resolved to should the tree not define it.
+        let (callee, resolution) = match &call.local_target {
+            Some(target) => (target.clone(), Resolution::Resolved),
+            None if is_path => {
+                let segments: Vec<&str> = call.callee.split(separator).collect();
+                let binding = imports.get(segments[0]).or_else(|| {
+                    original_names
+                        .get(segments[0])
+                        .and_then(|binding| imports.get(binding))
+                });
+
+                let mut path: Vec<&str> = match binding {
+                    Some(binding) => binding.path.split(separator).collect(),
+                    None => vec![segments[0]],
+                };
+                path.extend(&segments[1..]);
+                let (name, module) = path.split_last().unwrap();
+                let import = ImportTag {
+                    module: module.join(separator),
+                    name: Some(name.to_string()),
+                    alias: None,
+                    fallback: false,
+                    reexport: false,
+                    parents: parents.clone(),
+                };
+
+                match (
+                    language.resolve_import(&import, file_path, context),
+                    binding,
+                ) {
+                    (Some(resolved), _) => {
+                        if !resolved.external {
+                            dependencies.insert(resolved.module.clone());
+                        }
+                        dependencies.extend(resolved.dependencies);
+                        let key = generate_node_key(
+                            Path::new(&resolved.module),
+                            None,
+                            resolved.name.as_deref().unwrap_or(name),
+                        );
+                        match binding {
+                            // A path the tree does not define may start with a builtin,
+                            // like `Vec::new`.
+                            None if !resolved.external => builtin(
+                                key,
+                                segments[0],
+                                &segments.join("."),
+                                UnresolvedReason::MissingDefinition,
+                            ),
+                            _ => (key, imported(&resolved.module, resolved.external)),
+                        }
+                    }
+                    (None, Some(binding)) => {
+                        let key = generate_node_key(
+                            Path::new(&binding.module),
+                            binding.name.as_deref(),
+                            &segments[1..].join("."),
+                        );
+                        (key, binding.resolution())
+                    }
+                    (None, None) => {
+                        let key =
+                            generate_node_key(Path::new(module_name), None, &segments.join("."));
+                        builtin(
+                            key,
+                            segments[0],
+                            &segments.join("."),
+                            UnresolvedReason::UndefinedName,
+                        )
+                    }
+                }
+            }
+            None => match call.callee.split_once('.') {
+                // A method called on a literal or the result of a call is keyed by its
+                // name alone, as nothing tells which type defines it.
+                Some((EXPRESSION_RECEIVER, _)) => (
+                    call.callee.clone(),
+                    Resolution::Unresolved {
+                        reason: UnresolvedReason::UnknownReceiver,
+                    },
+                ),
+                // This is for method calls on an object; the part before '.' is treated as an object, not a module.
+                // Where paths have a separator of their own, '.' only accesses values.
+                Some((object_name, method_name)) => {
+                    match find_binding(imports, &call.callee).filter(|_| separator == ".") {
+                        // If the object matches an imported name, resolve to the correct module.
+                        Some((binding, rest)) => {
+                            // A member of an imported module is resolved like an import
+                            // of it, as the module may export it under another name.
+                            let member = ImportTag {
+                                module: binding.path.clone(),
+                                name: Some(rest.to_string()),
+                                alias: None,
+                                fallback: false,
+                                reexport: false,
+                                parents: Vec::new(),
+                            };
+                            let resolved = (binding.name.is_none() && !rest.contains('.'))
+                                .then(|| language.resolve_import(&member, file_path, context))
+                                .flatten()
+                                .filter(|resolved| !resolved.is_module);
+
+                            match resolved {
+                                Some(ResolvedImport {
+                                    module,
+                                    name: Some(name),
+                                    external,
+                                    dependencies: consulted,
+                                    ..
+                                }) => {
+                                    if !external {
+                                        dependencies.insert(module.clone());
+                                    }
+                                    dependencies.extend(consulted);
+                                    let key = generate_node_key(Path::new(&module), None, &name);
+                                    (key, imported(&module, external))
+                                }
+                                _ => {
+                                    let key = generate_node_key(
+                                        Path::new(&binding.module),
+                                        binding.name.as_deref(),
+                                        rest,
+                                    );
+                                    (key, binding.resolution())
+                                }
+                            }
+                        }
+                        None => {
+                            let key = generate_node_key(
+                                Path::new(module_name),
+                                Some(object_name),
+                                method_name,
+                            );
+                            builtin(
+                                key,
+                                object_name,
+                                &call.callee,
+                                UnresolvedReason::UnknownReceiver,
+                            )
+                        }
+                    }
+                }
+                None => match imports.get(&call.callee) {
+                    // For global function calls, check if the function name matches an alias from the imports.
+                    Some(binding) => {
+                        let key = generate_node_key(
+                            Path::new(&binding.module),
+                            None,
+                            binding.name.as_deref().unwrap_or(&call.callee),
+                        );
+                        (key, binding.resolution())
+                    }
+                    None => {
+                        // A function of the inline module the call is made in comes
+                        // before those of the file.
+                        let local = (!parents.is_empty())
+                            .then(|| {
+                                let module_path = parents.join(".");
+                                generate_node_key(
+                                    Path::new(module_name),
+                                    Some(&module_path),
+                                    &call.callee,
+                                )
+                            })
+                            .filter(|key| found(key));
+                        match local {
+                            Some(key) => (key, Resolution::Resolved),
+                            None => {
+                                let key =
+                                    generate_node_key(Path::new(module_name), None, &call.callee);
+                                builtin(
+                                    key,
+                                    &call.callee,
+                                    &call.callee,
+                                    UnresolvedReason::UndefinedName,
+                                )
+                            }
+                        }
+                    }
+                },
+            },
+        };
+
+        // A method called on a class of the tree, including through `self`, `this` or
+        // `super`, is resolved to the class that defines it, and may dispatch to the
+        // methods overriding it. Calling a class of the tree itself constructs it.
+        let external = matches!(resolution, Resolution::External { .. });
+        let mut resolution = resolution;
+        let mut overrides = Vec::new();
+        let mut kind = EdgeKind::Direct;
+        let callee = match callee.rsplit_once('.') {
+            Some((class_key, method))
+                if !external
+                    && context.hierarchy.class(class_key).is_some()
+                    && context.hierarchy.class(&callee).is_none() =>
+            {
+                let hierarchy = &context.hierarchy;
+                // The functions of a module or namespace are not methods.
+                if hierarchy
+                    .class(class_key)
+                    .is_some_and(|class| class.kind != ClassKind::Module)
+                {
+                    kind = EdgeKind::Method;
+                }
+                let defining = if call.super_call {
+                    hierarchy.find_super_method(class_key, method)
+                } else {
+                    hierarchy.find_method(class_key, method)
+                };
+                let dispatch = match context.config.dispatch {
+                    Dispatch::Off => false,
+                    Dispatch::Conservative => call.dynamic_receiver,
+                    Dispatch::All => !call.super_call,
+                };
+                if dispatch {
+                    overrides = hierarchy.find_overrides(class_key, method);
+                }
+
+                for class_key in defining.iter().chain(&overrides) {
+                    if let Some(class) = hierarchy.class(class_key) {
+                        if class.file_path != *module_name {
+                            dependencies.insert(class.file_path.clone());
+                        }
+                    }
+                }
+                overrides = overrides
+                    .into_iter()
+                    .filter_map(|overriding| hierarchy.method_key(&overriding, method))
+                    .collect();
+
+                match defining.and_then(|defining| hierarchy.method_key(&defining, method)) {
+                    Some(method_key) => method_key,
+                    // A method no indexed ancestor defines is looked up in the first
+                    // base outside the tree, and never in the calling class itself.
+                    None if call.super_call => {
+                        let mro = hierarchy.mro(class_key);
+                        let base = mro
+                            .iter()
+                            .skip(1)
+                            .find(|base| hierarchy.class(base).is_none())
+                            .or_else(|| mro.get(1));
+                        resolution = match base.map(|base| hierarchy.resolution(base)) {
+                            Some(external @ Resolution::External { .. }) => external,
+                            _ => Resolution::Unresolved {
+                                reason: UnresolvedReason::MissingDefinition,
+                            },
+                        };
+                        match base {
+                            Some(base) => format!("{}.{}", base, method),
+                            None => {
+                                generate_node_key(Path::new(module_name), Some("super"), method)
+                            }
+                        }
+                    }
+                    None => {
+                        resolution = Resolution::Unresolved {
+                            reason: UnresolvedReason::MissingDefinition,
+                        };
+                        callee
+                    }
+                }
+            }
+            _ if !external && context.hierarchy.class(&callee).is_some() => {
+                kind = EdgeKind::Constructor;
+                // Constructing a class calls its constructor, its own or an inherited
+                // one. A class without one is called itself.
+                let defining = language
+                    .constructor_name()
+                    .and_then(|name| Some((context.hierarchy.find_method(&callee, name)?, name)));
+                match defining {
+                    Some((defining, name)) => {
+                        if let Some(class) = context.hierarchy.class(&defining) {
+                            if class.file_path != *module_name {
+                                dependencies.insert(class.file_path.clone());
+                            }
+                        }
+                        context
+                            .hierarchy
+                            .method_key(&defining, name)
+                            .unwrap_or(callee)
+                    }
+                    None => callee,
+                }
+            }
+            _ => callee,
+        };
+        // The kind set by the query, like that of an awaited call, takes precedence.
+        let kind = call.kind.unwrap_or(kind);
+        // A reference to anything but a function of the tree, like a variable passed as
+        // an argument, is not a callback.
+        if kind == EdgeKind::CallbackReference && !context.functions.contains(callee.as_str()) {
+            continue;
+        }
+
+        if !external && found(&callee) {
+            resolution = Resolution::Resolved;
+        }
+
+        let callees = std::iter::once((callee, kind, resolution)).chain(
+            overrides
+                .into_iter()
+                .map(|callee| (callee, EdgeKind::Virtual, Resolution::Resolved)),
+        );
+        for (callee, kind, resolution) in callees {
+            let Some(block_calls) = outgoing_calls.get_mut(call.caller) else {
+                continue;
+            };
+            let site = CallLocation {
+                file_path: module_name.to_string(),
+                position: call.position,
+                arguments: call.arguments,
+            };
+            match seen.get(&(call.caller, callee.clone(), kind)) {
+                Some(&index) => block_calls[index].sites.push(site),
+                None => {
+                    seen.insert((call.caller, callee.clone(), kind), block_calls.len());
+                    block_calls.push(OutgoingCall {
+                        callee,
+                        position: call.position,
+                        sites: vec![site],
+                        kind,
+                        resolution,
+                    });
+                }
+            }
+        }
+    }
+
+    // The methods of the classes depend on every class they inherit from.
+    for class in &parsed_file.classes {
+        for ancestor in context.hierarchy.mro(&class.node_key) {
+            if let Some(ancestor) = context.hierarchy.class(&ancestor) {
+                if ancestor.file_path != *module_name {
+                    dependencies.insert(ancestor.file_path.clone());
+                }
+            }
+        }
+    }
+
+    let classes = parsed_file
+        .classes
+        .iter()
+        .filter_map(|class| {
+            let class_node = context.hierarchy.class(&class.node_key)?;
+            Some((class.node_key.clone(), class_node.clone()))
+        })
+        .collect();
+
+    ResolvedCalls {
+        outgoing_calls,
+        dependencies: dependencies.into_iter().collect(),
+        classes,
+    }
+}
+
+/// Returns the names of the inline modules enclosing a block, outermost first.
+///
+/// # Arguments
+///
+/// * `parsed_file` - The file defining the block.
+/// * `block_index` - The index of the block in `ParsedFile::blocks`.
+fn inline_modules(parsed_file: &ParsedFile, block_index: usize) -> Vec<String> {
+    let Some(block) = parsed_file.blocks.get(block_index) else {
+        return Vec::new();
+    };
+    parsed_file
+        .modules
+        .iter()
+        .filter(|module| module.inline)
+        .map(|module| {
+            let mut path = module.parents.clone();
+            path.push(module.name.clone());
+            path
+        })
+        .filter(|path| {
+            let module_key =
+                generate_node_key(Path::new(&parsed_file.module_name), None, &path.join("."));
+            block.node_key.starts_with(&format!("{}.", module_key))
+        })
+        .max_by_key(|path| path.len())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::{EdgeKind, Resolution, UnresolvedReason};
+    use crate::config::{Config, Dispatch};
+    use crate::fixture::Fixture;
+    use crate::indexer::Index;
+
+    #[test]
+    fn keeps_an_edge_per_kind_of_call() {
+        let fixture = Fixture::new(&[(
+            "src/main.rs",
+            "async fn main() {\n    tokio::spawn(work());\n    work().await;\n    work().await;\n}\n\nasync fn work() {}\n",
+        )]);
+        let index = fixture.index();
+
+        let main = fixture.key("src/main.rs", "main");
+        let work = fixture.key("src/main.rs", "work");
+        let edges: Vec<_> = index
+            .call_graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.from == main && edge.to == work)
+            .map(|edge| (edge.kind, edge.count()))
+            .collect();
+        assert_eq!(edges, [(EdgeKind::Spawn, 1), (EdgeKind::Await, 2)]);
+    }
+
+    #[test]
+    fn constructing_a_class_calls_its_constructor() {
+        let fixture = Fixture::new(&[
+            (
+                "shapes.py",
+                "class Base:\n    def __init__(self):\n        pass\n\nclass Child(Base):\n    pass\n\nclass Plain:\n    pass\n\ndef main():\n    Child()\n    Plain()\n",
+            ),
+            (
+                "shapes.js",
+                "class A {\n  constructor() {}\n}\n\nclass B extends A {}\n\nclass C {}\n\nfunction main() {\n  new B();\n  new C();\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        for (path, constructor, plain) in [
+            ("shapes.py", "Base.__init__", "Plain"),
+            ("shapes.js", "A.constructor", "C"),
+        ] {
+            let main = fixture.key(path, "main");
+            assert_eq!(
+                fixture.callees(&index, path, "main"),
+                [fixture.key(path, constructor), fixture.key(path, plain)]
+            );
+            for callee in fixture.callees(&index, path, "main") {
+                let edge = index.call_graph.edge(&main, &callee).unwrap();
+                assert_eq!(edge.kind, EdgeKind::Constructor);
+            }
+            // The class without a constructor gets a node of its own.
+            let class = index
+                .call_stack
+                .get_node(&fixture.key(path, plain))
+                .unwrap();
+            assert!(class.is_stand_in());
+            assert_eq!(class.resolution, Resolution::Resolved);
+        }
+
+        // Constructors are no entry points.
+        let mut entry_points = index.call_graph.get_entry_points();
+        entry_points.sort();
+        assert_eq!(
+            entry_points,
+            [
+                fixture.key("shapes.js", "main"),
+                fixture.key("shapes.py", "main")
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_each_kind_of_call() {
+        let fixture = Fixture::new(&[
+            (
+                "app.py",
+                "import threading\n\n\nclass Shape:\n    def area(self):\n        return 0\n\n\nclass Square(Shape):\n    def area(self):\n        return 1\n\n\ndef helper():\n    pass\n\n\ndef worker():\n    pass\n\n\ndef pick(key):\n    pass\n\n\nasync def fetch():\n    pass\n\n\nasync def main(shape: Shape):\n    helper()\n    Square()\n    shape.area()\n    threading.Thread(target=worker)\n    pick(key=helper)\n    await fetch()\n",
+            ),
+            ("lib.rs", "fn run() {\n    println!(\"{}\", 1);\n}\n"),
+        ]);
+        let mut config = Config::default();
+        config.dispatch = Dispatch::Conservative;
+        let index = fixture.index_with(&config);
+
+        let main = fixture.key("app.py", "main");
+        let edges: Vec<_> = index
+            .call_graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.from == main)
+            .map(|edge| (edge.to.clone(), edge.kind))
+            .collect();
+        let key = |name: &str| fixture.key("app.py", name);
+        let expected = [
+            (key("helper"), EdgeKind::Direct),
+            (key("Square"), EdgeKind::Constructor),
+            (key("Shape.area"), EdgeKind::Method),
+            (key("Square.area"), EdgeKind::Virtual),
+            ("threading.Thread".to_string(), EdgeKind::Direct),
+            (key("worker"), EdgeKind::Spawn),
+            (key("pick"), EdgeKind::Direct),
+            (key("helper"), EdgeKind::CallbackReference),
+            (key("fetch"), EdgeKind::Await),
+        ];
+        assert_eq!(edges, expected);
+
+        let run = fixture.key("lib.rs", "run");
+        let println = index.call_graph.edge(&run, "std.println").unwrap();
+        assert_eq!(println.kind, EdgeKind::Macro);
+    }
+
+    #[test]
+    fn finds_trait_default_methods_through_implementors() {
+        let fixture = Fixture::new(&[(
+            "src/lib.rs",
+            "trait Speak {\n    fn greet(&self) {}\n}\n\nstruct Dog;\nstruct Cat;\n\nimpl Speak for Dog {}\n\nimpl Speak for Cat {\n    fn greet(&self) {}\n}\n\nfn main(d: Dog, s: &dyn Speak) {\n    d.greet();\n    s.greet();\n}\n",
+        )]);
+        let mut config = Config::default();
+        config.dispatch = Dispatch::Conservative;
+        let index = fixture.index_with(&config);
+
+        let main = fixture.key("src/lib.rs", "main");
+        let edges: Vec<_> = index
+            .call_graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.from == main)
+            .map(|edge| (edge.to.clone(), edge.kind))
+            .collect();
+        let key = |name: &str| fixture.key("src/lib.rs", name);
+        assert_eq!(
+            edges,
+            [
+                (key("Speak.greet"), EdgeKind::Method),
+                (key("Cat.<Speak>.greet"), EdgeKind::Virtual),
+            ]
+        );
+        assert_eq!(
+            index
+                .call_stack
+                .get_node(&key("Speak.greet"))
+                .unwrap()
+                .resolution,
+            Resolution::Resolved
+        );
+    }
+
+    #[test]
+    fn super_calls_never_resolve_to_the_calling_method() {
+        let fixture = Fixture::new(&[
+            (
+                "client.py",
+                "from requests import Session\n\nclass Client(Session):\n    def get(self):\n        super().get()\n\nclass Missing(Unknown):\n    def run(self):\n        super().run()\n\nclass Plain:\n    def run(self):\n        super().run()\n",
+            ),
+            (
+                "view.js",
+                "import { Component } from \"react\";\n\nclass View extends Component {\n  constructor() {\n    super();\n  }\n}\n\nclass Missing extends Unknown {\n  render() {\n    super.render();\n  }\n}\n\nclass Plain {\n  render() {\n    super.render();\n  }\n}\n",
+            ),
+            (
+                "view.ts",
+                "import { Component } from \"react\";\n\nclass View extends Component {\n  constructor() {\n    super();\n  }\n}\n\nclass Missing extends Unknown {\n  render(): void {\n    super.render();\n  }\n}\n\nclass Plain {\n  render(): void {\n    super.render();\n  }\n}\n",
+            ),
+        ]);
+        let index = fixture.index();
+
+        let external = |module: &str| Resolution::External {
+            module: module.to_string(),
+        };
+        let missing = Resolution::Unresolved {
+            reason: UnresolvedReason::MissingDefinition,
+        };
+        for (path, caller, callee, resolution) in [
+            (
+                "client.py",
+                "Client.get",
+                "requests.Session.get".to_string(),
+                external("requests"),
+            ),
+            (
+                "client.py",
+                "Missing.run",
+                "Unknown.run".to_string(),
+                missing.clone(),
+            ),
+            (
+                "client.py",
+                "Plain.run",
+                fixture.key("client.py", "super.run"),
+                missing.clone(),
+            ),
+            (
+                "view.js",
+                "View.constructor",
+                "react.Component.constructor".to_string(),
+                external("react"),
+            ),
+            (
+                "view.js",
+                "Missing.render",
+                "Unknown.render".to_string(),
+                missing.clone(),
+            ),
+            (
+                "view.js",
+                "Plain.render",
+                fixture.key("view.js", "super.render"),
+                missing.clone(),
+            ),
+            (
+                "view.ts",
+                "View.constructor",
+                "react.Component.constructor".to_string(),
+                external("react"),
+            ),
+            (
+                "view.ts",
+                "Missing.render",
+                "Unknown.render".to_string(),
+                missing.clone(),
+            ),
+            (
+                "view.ts",
+                "Plain.render",
+                fixture.key("view.ts", "super.render"),
+                missing.clone(),
+            ),
+        ] {
+            assert_eq!(fixture.callees(&index, path, caller), [callee.as_str()]);
+            let node = index.call_stack.get_node(&callee).unwrap();
+            assert_eq!(node.resolution, resolution, "{}", callee);
+        }
+    }
+
+    #[test]
+    fn dispatch_mode_decides_which_calls_fan_out() {
+        let fixture = Fixture::new(&[
+            (
+                "shapes.py",
+                "class Shape:\n    def area(self):\n        return 0\n\n    def describe(self):\n        return self.area()\n\n\nclass Square(Shape):\n    def area(self):\n        return super().area()\n\n\nclass Tile(Square):\n    def area(self):\n        return 1\n\n\nclass Circle(Shape):\n    def area(self):\n        return 2\n\n\ndef measure(shape: Shape):\n    shape.area()\n\n\ndef exact():\n    square = Square()\n    square.area()\n",
+            ),
+            (
+                "src/lib.rs",
+                "trait Speak {\n    fn speak(&self) {}\n}\n\nstruct Dog;\nstruct Cat;\n\nimpl Speak for Dog {\n    fn speak(&self) {}\n}\n\nimpl Speak for Cat {\n    fn speak(&self) {}\n}\n\nfn talk(s: &dyn Speak) {\n    s.speak();\n}\n",
+            ),
+        ]);
+
+        let edges = |index: &Index, path: &str, from: &str| -> Vec<(String, EdgeKind)> {
+            let from = fixture.key(path, from);
+            let root = format!("{}/", fixture.root().display());
+            index
+                .call_graph
+                .edges()
+                .iter()
+                .filter(|edge| edge.from == from)
+                .map(|edge| (edge.to.replace(&root, ""), edge.kind))
+                .collect()
+        };
+        let method = |to: &str| (to.to_string(), EdgeKind::Method);
+        let virtual_call = |to: &str| (to.to_string(), EdgeKind::Virtual);
+
+        for dispatch in [Dispatch::Off, Dispatch::Conservative, Dispatch::All] {
+            let mut config = Config::default();
+            config.dispatch = dispatch;
+            let index = fixture.index_with(&config);
+
+            // A parameter and `self` may hold any subtype of their declared type.
+            let declared = match dispatch {
+                Dispatch::Off => vec![method("shapes.py.Shape.area")],
+                _ => vec![
+                    method("shapes.py.Shape.area"),
+                    virtual_call("shapes.py.Circle.area"),
+                    virtual_call("shapes.py.Square.area"),
+                    virtual_call("shapes.py.Tile.area"),
+                ],
+            };
+            assert_eq!(edges(&index, "shapes.py", "measure"), declared);
+            assert_eq!(edges(&index, "shapes.py", "Shape.describe"), declared);
+
+            // A value created with a constructor is only expanded by `all`.
+            let mut constructed = vec![
+                ("shapes.py.Square".to_string(), EdgeKind::Constructor),
+                method("shapes.py.Square.area"),
+            ];
+            if dispatch == Dispatch::All {
+                constructed.push(virtual_call("shapes.py.Tile.area"));
+            }
+            assert_eq!(edges(&index, "shapes.py", "exact"), constructed);
+
+            // A super call never dispatches.
+            assert_eq!(
+                edges(&index, "shapes.py", "Square.area"),
+                [method("shapes.py.Shape.area")]
+            );
+
+            let talk = match dispatch {
+                Dispatch::Off => vec![method("src/lib.rs.Speak.speak")],
+                _ => vec![
+                    method("src/lib.rs.Speak.speak"),
+                    virtual_call("src/lib.rs.Cat.<Speak>.speak"),
+                    virtual_call("src/lib.rs.Dog.<Speak>.speak"),
+                ],
+            };
+            assert_eq!(edges(&index, "src/lib.rs", "talk"), talk, "{:?}", dispatch);
+        }
+    }
+}
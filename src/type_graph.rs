@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::block::Resolution;
+use crate::call_graph::{dot_escape, mermaid_id, mermaid_label};
+use crate::query::ClassKind;
+
+/// Represents a type graph, which is a directed graph of the relationships between
+/// types.
+///
+/// The `TypeGraph` complements the `CallGraph`: each node corresponds to a class,
+/// interface, trait or type with methods, and each edge leads from a type to a type it
+/// extends or implements, such as Python's `class A(B)`, JavaScript's
+/// `class A extends B`, Rust's `impl Trait for Type` or a Rust supertrait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeGraph {
+    /// A map of type keys to their corresponding `TypeNode`s, ordered by key so that
+    /// every export of the same graph is identical.
+    nodes: BTreeMap<String, TypeNode>,
+    /// A vector of edges from subtypes to their supertypes.
+    edges: Vec<TypeEdge>,
+}
+
+/// Represents a type in the type graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeNode {
+    /// The path of the file defining the type, the module it was imported from if it
+    /// is external, or empty if it could not be resolved.
+    pub file_path: String,
+    /// The name of the type.
+    pub name: String,
+    /// The kind of the type.
+    pub kind: ClassKind,
+    /// Whether the type is a type of the indexed tree, or is defined outside of it or
+    /// could not be found, in which case the node only stands in for it.
+    #[serde(default)]
+    pub resolution: Resolution,
+}
+
+/// Represents a relationship between a subtype and one of its supertypes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeEdge {
+    /// The key of the subtype.
+    pub from: String,
+    /// The key of the supertype.
+    pub to: String,
+    /// How the subtype relates to the supertype.
+    pub kind: TypeEdgeKind,
+}
+
+/// How a subtype relates to one of its supertypes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeEdgeKind {
+    /// The subtype extends the supertype, like a subclass or a Rust subtrait.
+    Extends,
+    /// The subtype implements the supertype, a trait or interface.
+    Implements,
+}
+
+impl TypeNode {
+    /// Creates a `TypeNode` standing in for a type that is not a type of the indexed
+    /// tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the type, i.e. the module it was imported from
+    ///   followed by `.` and its name, or its name as written if it could not be
+    ///   resolved.
+    /// * `kind` - The kind of the type.
+    /// * `resolution` - Whether the type is external or could not be resolved.
+    pub fn stand_in(type_key: &str, kind: ClassKind, resolution: Resolution) -> Self {
+        let (file_path, name) = match &resolution {
+            Resolution::External { module } => (
+                module.as_str(),
+                type_key
+                    .strip_prefix(module.as_str())
+                    .and_then(|name| name.strip_prefix('.'))
+                    .unwrap_or(type_key),
+            ),
+            _ => ("", type_key),
+        };
+        TypeNode {
+            file_path: file_path.to_string(),
+            name: name.to_string(),
+            kind,
+            resolution,
+        }
+    }
+
+    /// Returns whether the node stands in for a type that is not a type of the
+    /// indexed tree.
+    pub fn is_stand_in(&self) -> bool {
+        self.resolution != Resolution::Resolved
+    }
+
+    /// Returns the label of the node in the exported graphs.
+    fn label(&self) -> String {
+        if self.is_stand_in() {
+            return self.name.clone();
+        }
+        let file_name = self.file_path.split('/').next_back().unwrap_or("");
+        format!("{}::{}", file_name, self.name)
+    }
+}
+
+impl TypeGraph {
+    /// Creates a new, empty `TypeGraph`.
+    pub fn new() -> Self {
+        TypeGraph {
+            nodes: BTreeMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Adds a new node to the type graph, replacing any node with the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The unique key for the type.
+    /// * `node` - The `TypeNode` to add.
+    pub fn add_node(&mut self, type_key: String, node: TypeNode) {
+        self.nodes.insert(type_key, node);
+    }
+
+    /// Adds a new edge to the type graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The key of the subtype.
+    /// * `to` - The key of the supertype.
+    /// * `kind` - How the subtype relates to the supertype.
+    pub fn add_edge(&mut self, from: String, to: String, kind: TypeEdgeKind) {
+        self.edges.push(TypeEdge { from, to, kind });
+    }
+
+    /// Retrieves a type by its key.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the type.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a reference to the `TypeNode`, or `None` if the type is
+    /// not part of the graph.
+    pub fn node(&self, type_key: &str) -> Option<&TypeNode> {
+        self.nodes.get(type_key)
+    }
+
+    /// Returns the types of the graph, keyed by type key.
+    pub fn nodes(&self) -> &BTreeMap<String, TypeNode> {
+        &self.nodes
+    }
+
+    /// Returns the edges of the graph, grouped by subtype in key order.
+    pub fn edges(&self) -> &[TypeEdge] {
+        &self.edges
+    }
+
+    /// Returns the types a type directly extends or implements.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the type.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the supertypes, in declaration order, the extended ones first.
+    pub fn supertypes(&self, type_key: &str) -> Vec<&str> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.from == type_key)
+            .map(|edge| edge.to.as_str())
+            .collect()
+    }
+
+    /// Returns the types that directly extend or implement a type.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the type.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the subtypes, sorted.
+    pub fn subtypes(&self, type_key: &str) -> Vec<&str> {
+        let subtypes: BTreeSet<&str> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.to == type_key)
+            .map(|edge| edge.from.as_str())
+            .collect();
+        subtypes.into_iter().collect()
+    }
+
+    /// Returns the types that implement a trait or interface.
+    ///
+    /// A type implements it if it implements the trait itself or one of the traits
+    /// extending it, or if it extends a type that does.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_key` - The key of the trait or interface.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the implementing types, sorted.
+    pub fn implementors(&self, type_key: &str) -> Vec<&str> {
+        let Some((type_key, _)) = self.nodes.get_key_value(type_key) else {
+            return Vec::new();
+        };
+        let traits = self.descendants(vec![type_key.as_str()], TypeEdgeKind::Extends);
+        let implementors = self
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == TypeEdgeKind::Implements)
+            .filter(|edge| traits.contains(edge.to.as_str()))
+            .map(|edge| edge.from.as_str())
+            .collect();
+
+        self.descendants(implementors, TypeEdgeKind::Extends)
+            .into_iter()
+            .collect()
+    }
+
+    /// Collects a set of types and every type reachable from them through edges of
+    /// one kind, followed from supertype to subtype.
+    fn descendants<'a>(&'a self, roots: Vec<&'a str>, kind: TypeEdgeKind) -> BTreeSet<&'a str> {
+        let mut descendants = BTreeSet::new();
+        let mut stack = roots;
+
+        while let Some(type_key) = stack.pop() {
+            if descendants.insert(type_key) {
+                stack.extend(
+                    self.edges
+                        .iter()
+                        .filter(|edge| edge.kind == kind && edge.to == type_key)
+                        .map(|edge| edge.from.as_str()),
+                );
+            }
+        }
+
+        descendants
+    }
+
+    /// Converts the `TypeGraph` to a Graphviz DOT format string.
+    ///
+    /// Edges point from subtypes to supertypes with hollow arrowheads; those to
+    /// implemented traits and interfaces are dashed, as are types outside the indexed
+    /// tree. Types that could not be resolved are dotted.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the Graphviz DOT representation of the type graph.
+    pub fn to_graphviz(&self) -> String {
+        let mut graphviz = String::from("digraph TypeGraph {\n");
+        graphviz.push_str("  rankdir=BT;\n");
+        graphviz.push_str("  node [shape=box];\n");
+        graphviz.push_str("  edge [arrowhead=empty];\n");
+
+        for (type_key, node) in &self.nodes {
+            let style = match node.resolution {
+                Resolution::Unresolved { .. } => ", style=dotted",
+                Resolution::External { .. } => ", style=dashed",
+                Resolution::Resolved => "",
+            };
+            graphviz.push_str(&format!(
+                "  \"{}\" [label=\"{}\"{}];\n",
+                dot_escape(type_key),
+                dot_escape(&node.label()),
+                style
+            ));
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                TypeEdgeKind::Extends => "",
+                TypeEdgeKind::Implements => " [style=dashed]",
+            };
+            graphviz.push_str(&format!(
+                "  \"{}\" -> \"{}\"{};\n",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to),
+                style
+            ));
+        }
+
+        graphviz.push('}');
+        graphviz
+    }
+
+    /// Converts the `TypeGraph` to a Mermaid diagram format string.
+    ///
+    /// Edges to implemented traits and interfaces are dotted, types outside the
+    /// indexed tree get the `external` class, which draws them dashed, and types that
+    /// could not be resolved get the `unresolved` class.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the Mermaid representation of the type graph.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph BT;\n");
+        for (type_key, node) in &self.nodes {
+            let type_key = mermaid_id(type_key);
+            let node_label = mermaid_label(&node.label());
+            mermaid.push_str(&format!("  {}[\"{}\"];\n", type_key, node_label));
+        }
+
+        for edge in &self.edges {
+            let from = mermaid_id(&edge.from);
+            let to = mermaid_id(&edge.to);
+            let arrow = match edge.kind {
+                TypeEdgeKind::Extends => "-->",
+                TypeEdgeKind::Implements => "-.->",
+            };
+            mermaid.push_str(&format!("  {} {} {};\n", from, arrow, to));
+        }
+
+        let external: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.resolution.is_external())
+            .map(|(type_key, _)| mermaid_id(type_key))
+            .collect();
+        if !external.is_empty() {
+            mermaid.push_str("  classDef external stroke-dasharray: 5 5;\n");
+            mermaid.push_str(&format!("  class {} external;\n", external.join(",")));
+        }
+        let unresolved: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| matches!(node.resolution, Resolution::Unresolved { .. }))
+            .map(|(type_key, _)| mermaid_id(type_key))
+            .collect();
+        if !unresolved.is_empty() {
+            mermaid.push_str("  classDef unresolved stroke-dasharray: 2 2;\n");
+            mermaid.push_str(&format!("  class {} unresolved;\n", unresolved.join(",")));
+        }
+
+        mermaid
+    }
+
+    /// Converts the `TypeGraph` to a JSON formatted string suitable for generating
+    /// flowcharts.
+    ///
+    /// # Returns
+    ///
+    /// A pretty JSON string representing the type graph with nodes and edges.
+    pub fn to_json_flowchart(&self) -> String {
+        let nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|(key, node)| {
+                json!({
+                    "id": key,
+                    "label": node.label(),
+                    "kind": node.kind,
+                    "external": node.resolution.is_external(),
+                    "resolution": node.resolution
+                })
+            })
+            .collect();
+
+        let edges: Vec<_> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "from": edge.from,
+                    "to": edge.to,
+                    "kind": edge.kind
+                })
+            })
+            .collect();
+
+        let flowchart = json!({
+            "nodes": nodes,
+            "edges": edges
+        });
+
+        serde_json::to_string_pretty(&flowchart).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::UnresolvedReason;
+    use crate::fixture::Fixture;
+
+    /// Builds a graph of the types of a file, each given by its key and kind, from the
+    /// edges between them.
+    fn graph(types: &[(&str, ClassKind)], edges: &[(&str, &str, TypeEdgeKind)]) -> TypeGraph {
+        let mut graph = TypeGraph::new();
+        for &(key, kind) in types {
+            let node = TypeNode {
+                file_path: "shapes.ts".to_string(),
+                name: key.to_string(),
+                kind,
+                resolution: Resolution::Resolved,
+            };
+            graph.add_node(key.to_string(), node);
+        }
+        for &(from, to, kind) in edges {
+            graph.add_edge(from.to_string(), to.to_string(), kind);
+        }
+        graph
+    }
+
+    #[test]
+    fn answers_subtype_supertype_and_implementor_queries() {
+        let graph = graph(
+            &[
+                ("Shape", ClassKind::Interface),
+                ("Solid", ClassKind::Interface),
+                ("Base", ClassKind::Class),
+                ("Square", ClassKind::Class),
+                ("Cube", ClassKind::Class),
+                ("Other", ClassKind::Class),
+            ],
+            &[
+                ("Solid", "Shape", TypeEdgeKind::Extends),
+                ("Base", "Shape", TypeEdgeKind::Implements),
+                ("Square", "Base", TypeEdgeKind::Extends),
+                ("Cube", "Other", TypeEdgeKind::Extends),
+                ("Cube", "Solid", TypeEdgeKind::Implements),
+            ],
+        );
+
+        assert_eq!(graph.supertypes("Cube"), ["Other", "Solid"]);
+        assert_eq!(graph.supertypes("Shape"), Vec::<&str>::new());
+        assert_eq!(graph.subtypes("Shape"), ["Base", "Solid"]);
+        assert_eq!(graph.subtypes("Square"), Vec::<&str>::new());
+        // Implementing a subtrait, or extending an implementor, implements the trait.
+        assert_eq!(graph.implementors("Shape"), ["Base", "Cube", "Square"]);
+        assert_eq!(graph.implementors("Solid"), ["Cube"]);
+        assert_eq!(graph.implementors("Missing"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn exports_mark_external_and_unresolved_types() {
+        let fixture = Fixture::new(&[(
+            "client.py",
+            "from requests import Session\n\nclass Client(Session):\n    pass\n\nclass Broken(Missing):\n    pass\n\nclass Local(Client):\n    pass\n",
+        )]);
+        let graph = fixture.index().type_graph;
+
+        let client = fixture.key("client.py", "Client");
+        let local = fixture.key("client.py", "Local");
+        let broken = fixture.key("client.py", "Broken");
+        assert_eq!(
+            graph.node("requests.Session").unwrap(),
+            &TypeNode {
+                file_path: "requests".to_string(),
+                name: "Session".to_string(),
+                kind: ClassKind::Class,
+                resolution: Resolution::External {
+                    module: "requests".to_string(),
+                },
+            }
+        );
+        assert_eq!(
+            graph.node("Missing").unwrap().resolution,
+            Resolution::Unresolved {
+                reason: UnresolvedReason::UndefinedName,
+            }
+        );
+        assert!(!graph.node(&client).unwrap().is_stand_in());
+        assert_eq!(graph.supertypes(&local), [client.as_str()]);
+
+        let dot = graph.to_graphviz();
+        for line in [
+            format!("  \"{}\" [label=\"client.py::Client\"];", client),
+            "  \"requests.Session\" [label=\"Session\", style=dashed];".to_string(),
+            "  \"Missing\" [label=\"Missing\", style=dotted];".to_string(),
+            format!("  \"{}\" -> \"Missing\";", broken),
+            format!("  \"{}\" -> \"{}\";", local, client),
+        ] {
+            assert!(dot.lines().any(|dot_line| dot_line == line), "{}", line);
+        }
+
+        let mermaid = graph.to_mermaid();
+        for line in [
+            format!("  {} --> requests.Session;", mermaid_id(&client)),
+            "  class requests.Session external;".to_string(),
+            "  class Missing unresolved;".to_string(),
+        ] {
+            assert!(
+                mermaid.lines().any(|mermaid_line| mermaid_line == line),
+                "{}",
+                line
+            );
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json_flowchart()).unwrap();
+        let node = |id: &str| {
+            json["nodes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|node| node["id"] == id)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(node("requests.Session")["external"], true);
+        assert_eq!(node("Missing")["external"], false);
+        assert_eq!(
+            node("Missing")["resolution"],
+            serde_json::json!({ "unresolved": { "reason": "undefined_name" } })
+        );
+        assert_eq!(node(&client)["resolution"], "resolved");
+        assert_eq!(json["edges"].as_array().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,171 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::indexer::{Diagnostic, Indexer};
+
+/// How long to wait for more file system events before updating the index, so that
+/// bursts of events, such as an editor saving a file, cause a single update.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Watches the directory of an `Indexer` and updates its index as files are created,
+/// modified, removed or renamed.
+///
+/// Updates run on a background thread. Subscribers of the `Indexer` receive the
+/// resulting `ChangeEvent`s as they happen. Watching stops when the `Watch` is
+/// stopped or dropped.
+pub struct Watch {
+    /// The watched indexer, shared with the update thread.
+    indexer: Arc<Mutex<Indexer>>,
+    /// The file system watcher, dropped to stop the update thread.
+    watcher: Option<RecommendedWatcher>,
+    /// The thread that updates the index.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watch {
+    /// Starts watching the directory of an `Indexer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `indexer` - The `Indexer` to keep up to date.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Watch`, or an error if the directory could not be
+    /// watched.
+    pub(crate) fn start(indexer: Indexer) -> Result<Self> {
+        // Events are reported under the watched path, which the indexer maps back to
+        // the path it indexed the directory with.
+        let root = indexer.canonical_root().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        let indexer = Arc::new(Mutex::new(indexer));
+        let shared = Arc::clone(&indexer);
+        let thread = thread::spawn(move || {
+            // The channel disconnects once the watcher is dropped.
+            while let Ok(event) = receiver.recv() {
+                let mut events = vec![event];
+                loop {
+                    match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(event) => events.push(event),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                let mut indexer = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                let mut paths: Vec<PathBuf> = Vec::new();
+                for event in events {
+                    match event {
+                        Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                        Ok(event) => paths.extend(event.paths),
+                        Err(error) => {
+                            let path = error.paths.first().cloned().unwrap_or(root.clone());
+                            indexer.push_diagnostic(Diagnostic {
+                                path,
+                                error: Error::Watch(error),
+                            });
+                        }
+                    }
+                }
+                paths.sort();
+                paths.dedup();
+
+                if !paths.is_empty() {
+                    indexer.update(&paths);
+                }
+            }
+        });
+
+        Ok(Watch {
+            indexer,
+            watcher: Some(watcher),
+            thread: Some(thread),
+        })
+    }
+
+    /// Locks the watched `Indexer`, for example to read its current index.
+    ///
+    /// Updates wait until the returned guard is dropped.
+    pub fn indexer(&self) -> MutexGuard<'_, Indexer> {
+        self.indexer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Stops watching and returns the `Indexer`.
+    ///
+    /// Pending file system events are discarded.
+    pub fn stop(mut self) -> Indexer {
+        self.shutdown();
+
+        let indexer = Arc::clone(&self.indexer);
+        drop(self);
+        match Arc::try_unwrap(indexer) {
+            Ok(indexer) => indexer.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(_) => unreachable!("the update thread has exited"),
+        }
+    }
+
+    /// Drops the watcher and waits for the update thread to exit.
+    fn shutdown(&mut self) {
+        drop(self.watcher.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use crate::config::Config;
+    use crate::fixture::Fixture;
+    use crate::indexer::{ChangeEvent, Indexer};
+
+    /// Returns the path of a directory relative to the current directory.
+    fn relative_to_current_dir(path: &Path) -> PathBuf {
+        let current_dir = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let up: PathBuf = current_dir.components().skip(1).map(|_| "..").collect();
+        up.join(path.strip_prefix("/").unwrap())
+    }
+
+    #[test]
+    fn watch_from_relative_root_indexes_new_files() {
+        let fixture = Fixture::new(&[("a.py", "def f():\n    pass\n")]);
+        let root = relative_to_current_dir(fixture.root());
+        assert!(root.is_relative());
+
+        let mut indexer = Indexer::new(Config::default(), &root).unwrap();
+        let changes = indexer.subscribe();
+        let watch = indexer.watch().unwrap();
+        fs::write(fixture.root().join("b.py"), "def g():\n    pass\n").unwrap();
+
+        let node_key = format!("{}.g", root.join("b.py").display());
+        let added = std::iter::from_fn(|| changes.recv_timeout(Duration::from_secs(10)).ok())
+            .any(|change| change == ChangeEvent::NodeAdded(node_key.clone()));
+
+        assert!(added);
+        assert!(watch
+            .indexer()
+            .index()
+            .call_stack
+            .get_node(&node_key)
+            .is_some());
+        watch.stop();
+    }
+}